@@ -1,4 +1,50 @@
+use cggtts::delay::{Delay, SystemDelay};
+use cggtts::prelude::Duration;
+use cggtts::{Code, Coordinates};
 use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command};
+use std::str::FromStr;
+
+/// CGGTTS generation from RINEX files
+pub struct Generation {
+    /// RINEX observation file
+    pub observation: String,
+    /// RINEX navigation file
+    pub navigation: String,
+    /// Station name
+    pub station: String,
+    /// Antenna phase center, ECEF [m]
+    pub apc: Coordinates,
+    /// Measurement system delays
+    pub delay: SystemDelay,
+    /// Directory holding the NeQuick-G maps, if any
+    pub nequick: Option<String>,
+}
+
+/* "X,Y,Z" antenna coordinates [m] */
+fn parse_apc(content: &str) -> Result<Coordinates, String> {
+    let xyz = content
+        .split(',')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid antenna coordinates: {}", e))?;
+    match xyz[..] {
+        [x, y, z] => Ok(Coordinates { x, y, z }),
+        _ => Err("antenna coordinates should be specified as X,Y,Z".to_string()),
+    }
+}
+
+/* "CODE:NS" internal delay */
+fn parse_delay(content: &str) -> Result<(Code, Delay), String> {
+    let (code, value) = content
+        .split_once(':')
+        .ok_or("delays should be specified as CODE:NS")?;
+    let code = Code::from_str(code.trim()).map_err(|e| format!("invalid delay code: {}", e))?;
+    let value = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid delay value: {}", e))?;
+    Ok((code, Delay::Internal(value)))
+}
 
 pub struct Cli {
     /// Arguments passed by user
@@ -15,6 +61,7 @@ impl Cli {
                     .version(env!("CARGO_PKG_VERSION"))
                     .about("CGGTTS post processing and clock comparison tool")
                     .arg_required_else_help(true)
+                    .subcommand_negates_reqs(true)
                     .color(ColorChoice::Always)
                     .arg(Arg::new("filepath")
                         .short('f')
@@ -41,10 +88,69 @@ Folder does not have to exist."))
                         .short('i')
                         .action(ArgAction::SetTrue)
                         .help("Identify local and remote setups."))
+                    .arg(Arg::new("interpolate")
+                        .long("interpolate")
+                        .value_name("SECONDS")
+                        .value_parser(clap::value_parser!(f64))
+                        .help("Interpolate the reference clock REFSYS when it did not track a SV
+at the remote track Epoch, using neighboring tracks that lie within SECONDS.
+Interpolated results are flagged in the textfile output."))
                     .arg(Arg::new("quiet")
                         .short('q')
                         .action(ArgAction::SetTrue)
                         .help("Turn off verbosity."))
+                    .subcommand(Command::new("qc")
+                        .about("Completeness and gap report, for each station.
+Tracks are compared to the BIPM schedule (89 tracks per day).
+Reports are generated within the workspace."))
+                    .subcommand(Command::new("generate")
+                        .about("Generate CGGTTS from RINEX observation and navigation files.
+Tracks follow the BIPM schedule, one file per day is generated within the workspace.")
+                        .arg(Arg::new("obs")
+                            .long("obs")
+                            .value_name("FILE")
+                            .required(true)
+                            .help("RINEX (V3 or V4) observation file."))
+                        .arg(Arg::new("nav")
+                            .long("nav")
+                            .value_name("FILE")
+                            .required(true)
+                            .help("RINEX (V3 or V4) navigation file."))
+                        .arg(Arg::new("station")
+                            .long("station")
+                            .value_name("NAME")
+                            .default_value("LAB")
+                            .help("Station name."))
+                        .arg(Arg::new("apc")
+                            .long("apc")
+                            .value_name("X,Y,Z")
+                            .value_parser(parse_apc)
+                            .required(true)
+                            .help("Antenna phase center ECEF coordinates [m]."))
+                        .arg(Arg::new("delay")
+                            .long("delay")
+                            .value_name("CODE:NS")
+                            .value_parser(parse_delay)
+                            .action(ArgAction::Append)
+                            .help("Internal delay [ns] of given code, for example \"C1:25.0\".
+Codes with no delay are not processed."))
+                        .arg(Arg::new("cable")
+                            .long("cable")
+                            .value_name("NS")
+                            .value_parser(clap::value_parser!(f64))
+                            .default_value("0.0")
+                            .help("Antenna cable delay [ns]."))
+                        .arg(Arg::new("ref")
+                            .long("ref")
+                            .value_name("NS")
+                            .value_parser(clap::value_parser!(f64))
+                            .default_value("0.0")
+                            .help("Reference delay [ns]."))
+                        .arg(Arg::new("nequick")
+                            .long("nequick")
+                            .value_name("DIRECTORY")
+                            .help("Directory holding the NeQuick-G CCIR (ccir11.asc to ccir22.asc) and MODIP (modipNeQG_wrapped.asc) maps.
+Galileo ionospheric delay is only modeled when they are provided.")))
                     .get_matches()
             },
         }
@@ -71,6 +177,37 @@ Folder does not have to exist."))
     pub fn workspace(&self) -> Option<&String> {
         self.matches.get_one::<String>("workspace")
     }
+    /// Returns maximal interpolation span, if interpolation is requested
+    pub fn interpolation(&self) -> Option<Duration> {
+        self.matches
+            .get_one::<f64>("interpolate")
+            .map(|secs| Duration::from_seconds(*secs))
+    }
+    /// Returns true if data quality report is requested
+    pub fn quality_check(&self) -> bool {
+        self.matches.subcommand_matches("qc").is_some()
+    }
+    /// Returns CGGTTS generation options, if generation is requested
+    pub fn generation(&self) -> Option<Generation> {
+        let matches = self.matches.subcommand_matches("generate")?;
+        let delays = matches
+            .get_many::<(Code, Delay)>("delay")
+            .map(|delays| delays.cloned().collect())
+            .unwrap_or_default();
+        Some(Generation {
+            observation: matches.get_one::<String>("obs").unwrap().clone(),
+            navigation: matches.get_one::<String>("nav").unwrap().clone(),
+            station: matches.get_one::<String>("station").unwrap().clone(),
+            apc: *matches.get_one::<Coordinates>("apc").unwrap(),
+            delay: SystemDelay {
+                rf_cable_delay: *matches.get_one::<f64>("cable").unwrap(),
+                ref_delay: *matches.get_one::<f64>("ref").unwrap(),
+                delays,
+                cal_id: None,
+            },
+            nequick: matches.get_one::<String>("nequick").cloned(),
+        })
+    }
     pub fn quiet(&self) -> bool {
         self.matches.get_flag("quiet")
     }
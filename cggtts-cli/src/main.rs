@@ -16,6 +16,7 @@ extern crate log;
 use env_logger::{Builder, Target};
 use walkdir::WalkDir;
 
+use cggtts::coordinates::Ellipsoid;
 use cggtts::prelude::CGGTTS;
 
 use itertools::Itertools;
@@ -105,11 +106,18 @@ pub fn main() {
             .to_path_buf(),
     };
 
+    if let Some(opts) = cli.generation() {
+        processing::generate(&workspace_path, &opts);
+        return;
+    }
+
     let pool = load_files(&cli);
 
     if cli.identification() {
-        for p in pool {
+        for p in &pool {
+            let (lat, lon, h) = p.apc_coordinates.to_geodetic(Ellipsoid::WGS84);
             info!("STATION          \"{}\"", p.station);
+            info!("LAT/LON/HEIGHT    {:.6}°, {:.6}°, {:.3} m", lat, lon, h);
             info!("NUMBER OF TRACKS  {}", p.tracks.len());
             info!(
                 "CODES            {:?}",
@@ -126,13 +134,32 @@ pub fn main() {
                     .collect::<Vec<_>>()
             );
         }
+        for (a, b) in pool.iter().tuple_combinations() {
+            let baseline = a.baseline(b);
+            info!(
+                "BASELINE         {}-{}: {:.3} km (azimuth {:.2}°)",
+                a.station,
+                b.station,
+                baseline.length / 1.0E3,
+                baseline.azimuth
+            );
+        }
         return;
     }
 
+    if cli.quality_check() {
+        processing::quality_check(&workspace_path, &pool);
+        return;
+    }
+
+    for cggtts in &pool {
+        processing::skyplot(cggtts, &mut plot_ctx);
+    }
+
     if pool.len() == 1 {
         processing::single_clock(&pool[0], &mut plot_ctx);
     } else {
-        processing::clock_comparison(&workspace_path, &pool, &mut plot_ctx);
+        processing::clock_comparison(&workspace_path, &pool, cli.interpolation(), &mut plot_ctx);
     }
 
     /*
@@ -1,4 +1,4 @@
-use super::{build_timedomain_2y_plot, build_timedomain_plot, Plot};
+use super::{build_polar_plot, build_timedomain_2y_plot, build_timedomain_plot, Plot};
 
 use plotly::Trace;
 
@@ -25,6 +25,9 @@ impl PlotContext {
         self.plots
             .push(build_timedomain_2y_plot(title, y1_label, y2_label));
     }
+    pub fn add_polar_plot(&mut self, title: &str) {
+        self.plots.push(build_polar_plot(title));
+    }
     pub fn add_trace(&mut self, trace: Box<dyn Trace>) {
         let len = self.plots.len() - 1;
         self.plots[len].add_trace(trace);
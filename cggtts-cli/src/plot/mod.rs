@@ -59,6 +59,19 @@ pub fn build_timedomain_plot(title: &str, y_title: &str) -> Plot {
     )
 }
 
+/*
+ * builds a polar plot, to represent SV positions in the sky
+ */
+pub fn build_polar_plot(title: &str) -> Plot {
+    let layout = Layout::new()
+        .title(Title::new(title).font(Font::default()))
+        .show_legend(true)
+        .auto_size(true);
+    let mut p = Plot::new();
+    p.set_layout(layout);
+    p
+}
+
 /*
  * build a standard 2D plot dual Y axes,
  * to plot against `Epochs`
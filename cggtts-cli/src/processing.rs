@@ -1,17 +1,48 @@
-use cggtts::prelude::{Epoch, CGGTTS};
+use cggtts::ionosphere::NeQuickMaps;
+use cggtts::prelude::{ClockModel, Duration, TimeSeries, CGGTTS};
+use cggtts::rinex::{Navigation, Observations};
+use cggtts::timeseries::StepDetector;
+use cggtts::track::Scheduler;
 use itertools::Itertools;
-use plotly::common::Mode;
-use std::collections::HashMap;
+use plotly::common::{HoverInfo, Mode};
+use plotly::ScatterPolar;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
+use std::sync::Arc;
 
+use crate::cli::Generation;
 use crate::plot::{
     //build_timedomain_plot,
     build_chart_epoch_axis,
     PlotContext,
 };
 
+/*
+ * Sky plot: SV paths in the local sky. Zenith is at the center,
+ * North is up and azimuth increases clockwise.
+ */
+pub fn skyplot(cggtts: &CGGTTS, ctx: &mut PlotContext) {
+    ctx.add_polar_plot(&format!(
+        "{} sky plot (r = 90° - elevation, North up, East right)",
+        cggtts.station
+    ));
+    for (sv, points) in cggtts.sky_tracks() {
+        let theta: Vec<f64> = points.iter().map(|p| 90.0 - p.azimuth).collect();
+        let rho: Vec<f64> = points.iter().map(|p| 90.0 - p.elevation).collect();
+        let txt: Vec<String> = points
+            .iter()
+            .map(|p| format!("{} elev={:.1}° azi={:.1}°", p.epoch, p.elevation, p.azimuth))
+            .collect();
+        let trace = ScatterPolar::new(theta, rho)
+            .mode(Mode::LinesMarkers)
+            .name(sv.to_string())
+            .hover_text_array(txt)
+            .hover_info(HoverInfo::Text);
+        ctx.add_trace(trace);
+    }
+}
+
 pub fn single_clock(cggtts: &CGGTTS, ctx: &mut PlotContext) {
     let sv: Vec<_> = cggtts.tracks().map(|trk| trk.sv).unique().collect();
     let codes: Vec<_> = cggtts
@@ -128,6 +159,17 @@ pub fn single_clock(cggtts: &CGGTTS, ctx: &mut PlotContext) {
         }
     }
 
+    //REFSYS discontinuities
+    for code in &codes {
+        let series = TimeSeries::from_refsys(cggtts.tracks().filter(|trk| &trk.frc == code));
+        for step in series.detect_steps(&StepDetector::default()) {
+            warn!(
+                "{} ({}) {:?} step of {:.3E} @ {} (confidence {:.4})",
+                cggtts.station, code, step.kind, step.magnitude, step.epoch, step.confidence
+            );
+        }
+    }
+
     //TROPO
     ctx.add_timedomain_2y_plot(
         &format!("{} MDTR/SMDT", cggtts.station),
@@ -183,55 +225,101 @@ pub fn single_clock(cggtts: &CGGTTS, ctx: &mut PlotContext) {
     }
 }
 
-pub fn clock_comparison(workspace: &Path, pool: &Vec<CGGTTS>, ctx: &mut PlotContext) {
+pub fn clock_comparison(
+    workspace: &Path,
+    pool: &Vec<CGGTTS>,
+    interpolation: Option<Duration>,
+    ctx: &mut PlotContext,
+) {
     let ref_clock = &pool[0];
     info!("{} is considered reference clock", ref_clock.station);
 
-    let ref_sv: Vec<_> = ref_clock.tracks().map(|trk| trk.sv).unique().collect();
-    let ref_codes: Vec<_> = ref_clock
-        .tracks()
-        .map(|trk| trk.frc.clone())
-        .unique()
-        .collect();
-    let refsys: HashMap<Epoch, f64> = ref_clock
-        .tracks()
-        .map(|trk| (trk.epoch, trk.data.refsys))
-        .collect();
+    let mut fd = File::create(workspace.join(&pool[0].station))
+        .expect("failed to create textfile: permission denied");
+
+    writeln!(fd, "t, CLOCK(A), CLOCK(B), SV, (elev[°], azi[°]) @REF, (elev[°], azi[°]) @CLOCK, SIGNAL, CLOCK(A) - CLOCK(B) [s], INTERPOLATED")
+        .expect("failed to generate textfile");
 
     for i in 1..pool.len() {
+        let cv = match interpolation {
+            Some(max_span) => pool[i].common_view_interpolated(ref_clock, max_span),
+            None => pool[i].common_view(ref_clock),
+        };
+
+        if interpolation.is_some() {
+            info!(
+                "{}-{}: {} common view measurements, {} interpolated",
+                pool[i].station,
+                ref_clock.station,
+                cv.len(),
+                cv.iter().filter(|cv| cv.interpolated).count()
+            );
+        }
+
+        for cv in &cv {
+            let (b_elev, b_azim) = pool[i]
+                .tracks()
+                .find(|trk| trk.epoch == cv.epoch && trk.sv == cv.sv && trk.frc == cv.frc)
+                .map(|trk| (trk.elevation, trk.azimuth))
+                .unwrap_or((f64::NAN, f64::NAN));
+            let (ref_elev, ref_azim) = ref_clock
+                .tracks()
+                .find(|trk| trk.epoch == cv.epoch && trk.sv == cv.sv && trk.frc == cv.frc)
+                .map(|trk| (trk.elevation, trk.azimuth))
+                .unwrap_or((f64::NAN, f64::NAN));
+            writeln!(
+                fd,
+                "{:?}, {}, {}, {}, ({:.2E}, {:.2E}), ({:.2E}, {:.2E}), {}, {:.3E}, {}",
+                cv.epoch,
+                pool[i].station,
+                pool[0].station,
+                cv.sv,
+                ref_elev,
+                ref_azim,
+                b_elev,
+                b_azim,
+                cv.frc,
+                cv.dt,
+                cv.interpolated,
+            )
+            .expect("failed to generate textfile");
+        }
+
         ctx.add_timedomain_plot(
-            &format!("{}-{}", ref_clock.station, pool[i].station),
+            &format!("{}-{}", pool[i].station, ref_clock.station),
             "Delta [s]",
         );
-        for sv in &ref_sv {
-            for code in &ref_codes {
-                let x_err: Vec<_> = ref_clock
-                    .tracks()
-                    .filter_map(|trk| {
-                        if trk.sv == *sv && &trk.frc == code {
-                            if refsys.get(&trk.epoch).is_some() {
-                                Some(trk.epoch)
-                            } else {
-                                None
-                            }
+
+        let sv: Vec<_> = cv.iter().map(|cv| cv.sv).unique().collect();
+        let codes: Vec<_> = cv.iter().map(|cv| cv.frc.clone()).unique().collect();
+
+        for sv in &sv {
+            for code in &codes {
+                let x_err: Vec<_> = cv
+                    .iter()
+                    .filter_map(|cv| {
+                        if cv.sv == *sv && &cv.frc == code {
+                            Some(cv.epoch)
                         } else {
                             None
                         }
                     })
                     .collect();
-                let t_err: Vec<_> = ref_clock
-                    .tracks()
-                    .filter_map(|trk| {
-                        if trk.sv == *sv && &trk.frc == code {
-                            refsys
-                                .get(&trk.epoch)
-                                .map(|refsys| trk.data.refsys - refsys)
+                let t_err: Vec<_> = cv
+                    .iter()
+                    .filter_map(|cv| {
+                        if cv.sv == *sv && &cv.frc == code {
+                            Some(cv.dt)
                         } else {
                             None
                         }
                     })
                     .collect();
 
+                if x_err.is_empty() {
+                    continue;
+                }
+
                 let chart = build_chart_epoch_axis(
                     &format!("({};{})", sv, code),
                     Mode::Markers,
@@ -241,43 +329,133 @@ pub fn clock_comparison(workspace: &Path, pool: &Vec<CGGTTS>, ctx: &mut PlotCont
                 ctx.add_trace(chart);
             }
         }
+
+        /*
+         * Clock model: averaged over all SV in common view
+         */
+        let series = TimeSeries::from_common_view(&cv);
+        for model in [ClockModel::FrequencyOffset, ClockModel::LinearDrift] {
+            match series.clock_fit(model) {
+                Ok(fit) => {
+                    info!(
+                        "{} relative to {}: {}",
+                        pool[i].station, ref_clock.station, fit
+                    );
+                    let epochs: Vec<_> = series.samples().map(|s| s.epoch).collect();
+                    let model: Vec<_> = epochs.iter().map(|t| fit.phase(*t)).collect();
+                    let chart = build_chart_epoch_axis(
+                        &format!("{:?}", fit.model),
+                        Mode::Lines,
+                        epochs,
+                        model,
+                    );
+                    ctx.add_trace(chart);
+                },
+                Err(e) => warn!(
+                    "{} relative to {}: {:?} fit failed: {}",
+                    pool[i].station, ref_clock.station, model, e
+                ),
+            }
+        }
     }
+}
 
-    let mut fd = File::create(workspace.join(&pool[0].station))
-        .expect("failed to create textfile: permission denied");
+pub fn quality_check(workspace: &Path, pool: &[CGGTTS]) {
+    let scheduler = Scheduler::default();
+    for cggtts in pool {
+        let report = cggtts.qc_report(&scheduler);
+        for day in &report.days {
+            info!(
+                "{} - MJD {}: {}/{} scheduled tracks ({:.1} %)",
+                report.station,
+                day.mjd,
+                day.expected - day.missing_slots.len(),
+                day.expected,
+                day.completeness() * 100.0
+            );
+        }
+        if !report.duplicates.is_empty() {
+            warn!(
+                "{}: {} duplicate tracks",
+                report.station,
+                report.duplicates.len()
+            );
+        }
+        if !report.off_schedule.is_empty() {
+            warn!(
+                "{}: {} tracks are not on the BIPM schedule",
+                report.station,
+                report.off_schedule.len()
+            );
+        }
+        if !report.non_standard_durations.is_empty() {
+            warn!(
+                "{}: {} tracks do not follow BIPM specifications",
+                report.station,
+                report.non_standard_durations.len()
+            );
+        }
+        if !report.non_monotonic_epochs.is_empty() {
+            warn!(
+                "{}: {} non monotonic epochs",
+                report.station,
+                report.non_monotonic_epochs.len()
+            );
+        }
 
-    writeln!(fd, "t, CLOCK(A), CLOCK(B), SV, (elev[°], azi[°]) @REF, (elev[°], azi[°]) @CLOCK, SIGNAL, CLOCK(A) - CLOCK(B) [s]")
-        .expect("failed to generate textfile");
+        let path = workspace.join(format!("{}-qc.txt", report.station));
+        let mut fd = File::create(&path).expect("failed to create textfile: permission denied");
+        write!(fd, "{}", report).expect("failed to generate textfile");
+        info!(
+            "{} report generated in $WORKSPACE/{}-qc.txt",
+            report.station, report.station
+        );
+    }
+}
 
-    for trk in ref_clock.tracks() {
-        let ref_t = trk.epoch;
-        let ref_sv = trk.sv;
-        let (ref_elev, ref_azim) = (trk.elevation, trk.azimuth);
-        let ref_frc = &trk.frc;
-        for i in 1..pool.len() {
-            let track = pool[i]
-                .tracks()
-                .filter(|trk| trk.epoch == ref_t && trk.sv == ref_sv && trk.frc == *ref_frc)
-                .reduce(|trk, _| trk);
-            if let Some(b_trk) = track {
-                let (b_elev, b_azim) = (b_trk.elevation, b_trk.azimuth);
-                let dt = b_trk.data.refsys - trk.data.refsys;
-                writeln!(
-                    fd,
-                    "{:?}, {}, {}, {}, ({:.2E}, {:.2E}), ({:.2E}, {:.2E}), {}, {:.3E}",
-                    ref_t,
-                    pool[i].station,
-                    pool[0].station,
-                    ref_sv,
-                    ref_elev,
-                    ref_azim,
-                    b_elev,
-                    b_azim,
-                    ref_frc,
-                    dt
-                )
-                .expect("failed to generate textfile");
-            }
-        }
+/*
+ * CGGTTS generation from RINEX files: one file per day
+ */
+pub fn generate(workspace: &Path, opts: &Generation) {
+    let obs = Observations::from_file(&opts.observation)
+        .unwrap_or_else(|e| panic!("failed to parse \"{}\": {}", opts.observation, e));
+    let nav = Navigation::from_file(&opts.navigation)
+        .unwrap_or_else(|e| panic!("failed to parse \"{}\": {}", opts.navigation, e));
+    info!(
+        "loaded {} observation epochs, {} ephemeris",
+        obs.pseudo_ranges.len(),
+        nav.kepler.len() + nav.glonass.len()
+    );
+
+    let nequick_maps = opts.nequick.as_ref().map(|dir| {
+        let maps = NeQuickMaps::from_directory(dir)
+            .unwrap_or_else(|e| panic!("failed to load NeQuick-G maps from \"{}\": {}", dir, e));
+        Arc::new(maps)
+    });
+
+    std::fs::create_dir_all(workspace).expect("failed to create workspace");
+
+    let pool = CGGTTS::from_rinex(
+        &obs,
+        &nav,
+        opts.apc,
+        &opts.delay,
+        &Scheduler::default(),
+        nequick_maps,
+    );
+    if pool.is_empty() {
+        warn!("no track could be formed");
+    }
+    for cggtts in pool {
+        let cggtts = cggtts.station(&opts.station);
+        let filename = cggtts.filename();
+        let mut fd = File::create(workspace.join(&filename))
+            .expect("failed to create textfile: permission denied");
+        write!(fd, "{}", cggtts).expect("failed to generate textfile");
+        info!(
+            "{} tracks generated in $WORKSPACE/{}",
+            cggtts.tracks.len(),
+            filename
+        );
     }
 }
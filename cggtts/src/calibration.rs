@@ -0,0 +1,382 @@
+//! Delay calibration of CGGTTS data
+use crate::delay::SystemDelay;
+use crate::prelude::{Constellation, TimeSeries, Track};
+use crate::track::{carrier_frequency, GlonassChannel};
+use crate::{Code, CGGTTS};
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Calibration errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+    /// Frequency code could not be associated to a delay [Code]
+    #[error("no delay code for \"{0}\" frequency code")]
+    UnknownCode(String),
+    /// Delay is not specified for this [Code]
+    #[error("no delay specified for {0}")]
+    MissingDelay(Code),
+    /// Both data sets do not share any common view measurement
+    #[error("no common view measurements")]
+    NoCommonView,
+}
+
+/// Delay difference, for a given [Code], between a station
+/// and a travelling calibrator, see [CGGTTS::differential_calibration]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CodeCalibration {
+    /// Calibrated code
+    pub code: Code,
+    /// Mean station - calibrator REFSYS difference [ns].
+    /// This is the amount by which the station delay is underestimated.
+    pub offset: f64,
+    /// Standard deviation of the per-epoch differences [ns]
+    pub std_dev: f64,
+    /// Uncertainty of the mean (statistical only) [ns]
+    pub uncertainty: f64,
+    /// Number of common view epochs
+    pub nb_epochs: usize,
+}
+
+/// Result of a differential calibration campaign
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DifferentialCalibration {
+    /// Results per code
+    pub codes: Vec<CodeCalibration>,
+    /// Updated station delays, to apply with [CGGTTS::recalibrate]
+    pub delay: SystemDelay,
+}
+
+/*
+ * Returns the delay code that applies to given constellation
+ * and single frequency code.
+ */
+pub(crate) fn delay_code(constellation: Constellation, frc: &str) -> Option<Code> {
+    let band = frc.get(0..2)?;
+    let attribute = frc.get(2..3).unwrap_or("");
+    let code = match constellation {
+        Constellation::Galileo => match band {
+            "E1" | "L1" => Code::E1,
+            "E5" | "L5" | "L7" | "L8" => Code::E5,
+            _ => return None,
+        },
+        Constellation::BeiDou => match band {
+            "B1" | "L1" | "L2" => Code::B1,
+            "B2" | "L7" => Code::B2,
+            _ => return None,
+        },
+        _ => match (band, attribute) {
+            ("L1", "C") => Code::C1,
+            ("L1", "P" | "W" | "Y") => Code::P1,
+            ("L2", "C" | "S" | "L" | "X") => Code::C2,
+            ("L2", "P" | "W" | "Y") => Code::P2,
+            _ => return None,
+        },
+    };
+    Some(code)
+}
+
+/* Delay variation [ns] to apply to this track */
+fn correction(old: &SystemDelay, new: &SystemDelay, trk: &Track) -> Result<f64, Error> {
+    let constellation = trk.sv.constellation;
+    let unknown = || Error::UnknownCode(trk.frc.clone());
+    let delta = |frc: &str| -> Result<f64, Error> {
+        let code = delay_code(constellation, frc).ok_or_else(unknown)?;
+        let total = |delay: &SystemDelay| delay.total_delay(code).ok_or(Error::MissingDelay(code));
+        Ok(total(new)? - total(old)?)
+    };
+    match trk.combined_frc() {
+        Some((lhs, rhs)) => {
+            // Glonass FDMA frequency ratio does not depend on the channel
+            let fr = match trk.fr {
+                GlonassChannel::Unknown => GlonassChannel::ChanNum(0),
+                fr => fr,
+            };
+            let f1 = carrier_frequency(constellation, &lhs, fr).ok_or_else(unknown)?;
+            let f2 = carrier_frequency(constellation, &rhs, fr).ok_or_else(unknown)?;
+            let (g1, g2) = (f1.powi(2), f2.powi(2));
+            Ok((g1 * delta(&lhs)? - g2 * delta(&rhs)?) / (g1 - g2))
+        },
+        None => delta(&trk.frc),
+    }
+}
+
+impl CGGTTS {
+    /// Applies a new delay calibration to Self. Every REFSV and REFSYS
+    /// is shifted by the variation of the total delay
+    /// (INT DLY + CAB DLY - REF DLY, or SYS DLY - REF DLY)
+    /// that applies to its frequency code, and the header is updated with
+    /// the new `SystemDelay` (and its `CAL_ID`).
+    /// Ionosphere-free (L3) tracks are corrected with the combination
+    /// of both delays they rely on.
+    /// Fails, leaving Self untouched, when a track uses a code
+    /// for which either calibration does not specify a delay.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::delay::{Delay, SystemDelay};
+    /// use cggtts::Code;
+    /// let cggtts = CGGTTS::from_file("../data/single/GZSY8259.506")
+    ///     .unwrap();
+    /// let mut delay = SystemDelay::new()
+    ///     .with_calibration_id("1234-2024");
+    /// delay.delays.push((Code::C1, Delay::System(10.0)));
+    /// let recalibrated = cggtts.recalibrate(delay)
+    ///     .unwrap();
+    /// assert_eq!(recalibrated.delay.cal_id, Some("1234-2024".to_string()));
+    /// ```
+    pub fn recalibrate(&self, delay: SystemDelay) -> Result<Self, Error> {
+        let mut tracks = Vec::with_capacity(self.tracks.len());
+        for trk in self.tracks() {
+            let dt = correction(&self.delay, &delay, trk)? * 1.0E-9;
+            let mut trk = trk.clone();
+            trk.data.refsv -= dt;
+            trk.data.refsys -= dt;
+            tracks.push(trk);
+        }
+        Ok(Self {
+            delay,
+            tracks,
+            ..self.clone()
+        })
+    }
+    /// Differential (G1/G2 type) calibration of Self, the station,
+    /// against a travelling calibrator whose delays are known.
+    /// Both systems should share the same clock and antenna location
+    /// (common-clock, zero-baseline): their REFSYS difference then only
+    /// reflects the station delay error. For each code, the per-epoch
+    /// common view differences are averaged, and the station delay is
+    /// corrected by that mean difference. Codes without common view
+    /// measurements keep their current delay.
+    /// The updated `SystemDelay` is tagged with `cal_id`.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let station = CGGTTS::from_file("../data/single/GZSY8259.506")
+    ///     .unwrap();
+    /// let calibrator = station.clone();
+    /// let calibration = station.differential_calibration(&calibrator, "1234-2024")
+    ///     .unwrap();
+    /// assert_eq!(calibration.codes[0].offset, 0.0);
+    /// let station = station.recalibrate(calibration.delay)
+    ///     .unwrap();
+    /// ```
+    pub fn differential_calibration(
+        &self,
+        calibrator: &Self,
+        cal_id: &str,
+    ) -> Result<DifferentialCalibration, Error> {
+        let cv = self.common_view(calibrator);
+        if cv.is_empty() {
+            return Err(Error::NoCommonView);
+        }
+
+        let mut codes = Vec::<CodeCalibration>::new();
+        let mut delay = self.delay.with_calibration_id(cal_id);
+
+        for (index, (code, _)) in self.delay.delays.iter().enumerate() {
+            let matching: Vec<_> = cv
+                .iter()
+                .filter(|cv| delay_code(cv.sv.constellation, &cv.frc) == Some(*code))
+                .cloned()
+                .collect();
+
+            let series = TimeSeries::from_common_view(&matching);
+            if series.is_empty() {
+                continue;
+            }
+
+            let values: Vec<f64> = series.samples().map(|s| s.value * 1.0E9).collect();
+            let n = values.len();
+            let offset = values.iter().sum::<f64>() / n as f64;
+            let std_dev = if n > 1 {
+                (values.iter().map(|v| (v - offset).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+            } else {
+                0.0
+            };
+
+            codes.push(CodeCalibration {
+                code: *code,
+                offset,
+                std_dev,
+                uncertainty: std_dev / (n as f64).sqrt(),
+                nb_epochs: n,
+            });
+
+            delay.delays[index].1 = delay.delays[index].1.add_value(offset);
+        }
+
+        if codes.is_empty() {
+            return Err(Error::NoCommonView);
+        }
+
+        Ok(DifferentialCalibration { codes, delay })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{correction, delay_code, Error};
+    use crate::delay::{Delay, SystemDelay};
+    use crate::prelude::{Constellation, Track, CGGTTS, SV};
+    use crate::Code;
+    use std::str::FromStr;
+    #[test]
+    fn codes() {
+        assert_eq!(delay_code(Constellation::GPS, "L1C"), Some(Code::C1));
+        assert_eq!(delay_code(Constellation::GPS, "L2P"), Some(Code::P2));
+        assert_eq!(delay_code(Constellation::GPS, "L3P"), None);
+        assert_eq!(delay_code(Constellation::Galileo, "E5a"), Some(Code::E5));
+        assert_eq!(delay_code(Constellation::BeiDou, "B2"), Some(Code::B2));
+        assert_eq!(delay_code(Constellation::GPS, "L5C"), None);
+    }
+    #[test]
+    fn ionosphere_free_correction() {
+        let mut old = SystemDelay::new();
+        old.delays = vec![
+            (Code::C1, Delay::System(0.0)),
+            (Code::C2, Delay::System(0.0)),
+            (Code::P1, Delay::System(0.0)),
+            (Code::P2, Delay::System(0.0)),
+            (Code::E1, Delay::System(0.0)),
+            (Code::E5, Delay::System(0.0)),
+            (Code::B1, Delay::System(0.0)),
+            (Code::B2, Delay::System(0.0)),
+        ];
+        let mut new = old.clone();
+        for (code, delay) in new.delays.iter_mut() {
+            let value = match code {
+                Code::C1 => 1.0,
+                Code::P1 => 2.0,
+                Code::P2 => 3.0,
+                Code::E1 => 4.0,
+                Code::E5 => 5.0,
+                Code::B1 => 6.0,
+                Code::B2 => 7.0,
+                _ => 8.0,
+            };
+            *delay = Delay::System(value);
+        }
+        let iono_free = |f1: f64, d1: f64, f2: f64, d2: f64| {
+            let (g1, g2) = (f1.powi(2), f2.powi(2));
+            (g1 * d1 - g2 * d2) / (g1 - g2)
+        };
+
+        let gps = SV::from_str("G01").unwrap();
+        let trk = Track::default().with_sv(gps).with_carrier_code("L1C");
+        assert_eq!(correction(&old, &new, &trk), Ok(1.0));
+
+        // parsed L3P track: P1/P2
+        let trk = trk.with_carrier_code("L3P");
+        let expected = iono_free(1575.42E6, 2.0, 1227.60E6, 3.0);
+        assert!((correction(&old, &new, &trk).unwrap() - expected).abs() < 1.0E-12);
+
+        // parsed L3C track: C1/C2
+        let trk = trk.with_carrier_code("L3C");
+        let expected = iono_free(1575.42E6, 1.0, 1227.60E6, 8.0);
+        assert!((correction(&old, &new, &trk).unwrap() - expected).abs() < 1.0E-12);
+
+        // parsed Galileo track: E1/E5a
+        let trk = trk
+            .with_sv(SV::from_str("E01").unwrap())
+            .with_carrier_code("L3Q");
+        let expected = iono_free(1575.42E6, 4.0, 1176.45E6, 5.0);
+        assert!((correction(&old, &new, &trk).unwrap() - expected).abs() < 1.0E-12);
+
+        // parsed BeiDou track: B1I/B2I
+        let trk = Track::default()
+            .with_sv(SV::from_str("C10").unwrap())
+            .with_carrier_code("L3I");
+        let expected = iono_free(1561.098E6, 6.0, 1207.14E6, 7.0);
+        assert!((correction(&old, &new, &trk).unwrap() - expected).abs() < 1.0E-12);
+
+        let trk = trk.with_sv(gps).with_carrier_code("L3Q");
+        assert_eq!(
+            correction(&old, &new, &trk),
+            Err(Error::UnknownCode("L3Q".to_string()))
+        );
+    }
+    #[test]
+    fn recalibration() {
+        let cggtts = CGGTTS::from_file("../data/single/GZSY8259.506").unwrap();
+        let mut delay = cggtts.delay.with_calibration_id("1234-2024");
+        delay.rf_cable_delay += 1.0;
+        delay.ref_delay += 3.0;
+        delay.delays = vec![(Code::C1, Delay::System(5.0))];
+
+        let recalibrated = cggtts.recalibrate(delay.clone()).unwrap();
+        assert_eq!(recalibrated.delay, delay);
+        assert_eq!(recalibrated.tracks.len(), cggtts.tracks.len());
+        for (old, new) in cggtts.tracks().zip(recalibrated.tracks()) {
+            // SYS DLY: cable delay variation does not apply
+            let dt = new.data.refsys - old.data.refsys;
+            assert!((dt - (-5.0E-9 + 3.0E-9)).abs() < 1.0E-15);
+            let dt = new.data.refsv - old.data.refsv;
+            assert!((dt - (-5.0E-9 + 3.0E-9)).abs() < 1.0E-15);
+        }
+
+        // back to initial calibration
+        let restored = recalibrated.recalibrate(cggtts.delay.clone()).unwrap();
+        for (a, b) in cggtts.tracks().zip(restored.tracks()) {
+            assert!((a.data.refsys - b.data.refsys).abs() < 1.0E-15);
+        }
+
+        let mut delay = SystemDelay::new();
+        delay.delays.push((Code::P1, Delay::System(5.0)));
+        assert_eq!(
+            cggtts.recalibrate(delay).err(),
+            Some(Error::MissingDelay(Code::C1))
+        );
+    }
+    #[test]
+    fn differential_calibration() {
+        let calibrator = CGGTTS::from_file("../data/single/GZSY8259.506").unwrap();
+        // station delay is underestimated by 3.5 ns
+        let mut station = calibrator.clone();
+        for (index, trk) in station.tracks.iter_mut().enumerate() {
+            let noise = if index % 2 == 0 { 0.2E-9 } else { -0.2E-9 };
+            trk.data.refsys += 3.5E-9 + noise;
+        }
+
+        let calibration = station
+            .differential_calibration(&calibrator, "1234-2024")
+            .unwrap();
+        assert_eq!(calibration.codes.len(), 1);
+        let c1 = calibration.codes[0];
+        assert_eq!(c1.code, Code::C1);
+        assert_eq!(c1.nb_epochs, station.tracks.len());
+        assert!((c1.offset - 3.5).abs() < 0.2);
+        assert!(c1.std_dev > 0.0 && c1.uncertainty < c1.std_dev);
+        assert_eq!(calibration.delay.cal_id, Some("1234-2024".to_string()));
+        assert_eq!(
+            calibration.delay.delays[0].1.value(),
+            station.delay.delays[0].1.value() + c1.offset
+        );
+
+        // recalibrated station now agrees with calibrator
+        let station = station.recalibrate(calibration.delay).unwrap();
+        let cv = station.common_view(&calibrator);
+        let mean = cv.iter().map(|cv| cv.dt).sum::<f64>() / cv.len() as f64;
+        assert!(mean.abs() < 1.0E-15);
+
+        let other = CGGTTS::from_file("../data/dual/EZGTR60.258").unwrap();
+        assert_eq!(
+            station.differential_calibration(&other, "").err(),
+            Some(Error::NoCommonView)
+        );
+    }
+    #[test]
+    fn missing_delay() {
+        let mut cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        cggtts.delay.delays = vec![
+            (Code::C1, Delay::Internal(32.9)),
+            (Code::P1, Delay::Internal(32.9)),
+        ];
+        // this file also contains L2 tracks
+        assert_eq!(
+            cggtts.recalibrate(cggtts.delay.clone()).err(),
+            Some(Error::MissingDelay(Code::C2))
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Common view time transfer between two remote sites
+use crate::prelude::{Duration, Epoch, Track, SV};
+use crate::CGGTTS;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A common view measurement: the same SV, tracked at the same Epoch
+/// with the same signal, by two remote stations.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommonView {
+    /// Track (start) Epoch
+    pub epoch: Epoch,
+    /// SV in common view
+    pub sv: SV,
+    /// Carrier frequency code
+    pub frc: String,
+    /// Local clock - remote clock [s],
+    /// as the difference of both REFSYS
+    pub dt: f64,
+    /// Slope of the clock difference [s/s],
+    /// as the difference of both SRSYS
+    pub slope: f64,
+    /// True when the remote REFSYS was interpolated to this Epoch,
+    /// see [CGGTTS::common_view_interpolated]
+    pub interpolated: bool,
+}
+
+impl CommonView {
+    /* forms a common view measurement from two matching tracks */
+    pub(crate) fn from_tracks(local: &Track, remote: &Track) -> Self {
+        Self {
+            epoch: local.epoch,
+            sv: local.sv,
+            frc: local.frc.clone(),
+            dt: local.data.refsys - remote.data.refsys,
+            slope: local.data.srsys - remote.data.srsys,
+            interpolated: false,
+        }
+    }
+    /*
+     * forms a common view measurement by interpolating the remote REFSYS
+     * to the local track midpoint, using the REFSYS and SRSYS of neighboring tracks.
+     */
+    fn interpolate(local: &Track, before: Option<&Track>, after: Option<&Track>) -> Option<Self> {
+        let t = midpoint(local);
+        let (refsys, srsys) = match (before, after) {
+            (Some(before), Some(after)) => {
+                // cubic Hermite interpolation
+                let (t0, t1) = (midpoint(before), midpoint(after));
+                let h = (t1 - t0).to_seconds();
+                let s = (t - t0).to_seconds() / h;
+                let (y0, m0) = (before.data.refsys, before.data.srsys * h);
+                let (y1, m1) = (after.data.refsys, after.data.srsys * h);
+                let refsys = (2.0 * s.powi(3) - 3.0 * s.powi(2) + 1.0) * y0
+                    + (s.powi(3) - 2.0 * s.powi(2) + s) * m0
+                    + (-2.0 * s.powi(3) + 3.0 * s.powi(2)) * y1
+                    + (s.powi(3) - s.powi(2)) * m1;
+                let srsys = ((6.0 * s.powi(2) - 6.0 * s) * y0
+                    + (3.0 * s.powi(2) - 4.0 * s + 1.0) * m0
+                    + (-6.0 * s.powi(2) + 6.0 * s) * y1
+                    + (3.0 * s.powi(2) - 2.0 * s) * m1)
+                    / h;
+                (refsys, srsys)
+            },
+            (Some(trk), None) | (None, Some(trk)) => {
+                // linear extrapolation
+                let dt = (t - midpoint(trk)).to_seconds();
+                (trk.data.refsys + trk.data.srsys * dt, trk.data.srsys)
+            },
+            (None, None) => return None,
+        };
+        Some(Self {
+            epoch: local.epoch,
+            sv: local.sv,
+            frc: local.frc.clone(),
+            dt: local.data.refsys - refsys,
+            slope: local.data.srsys - srsys,
+            interpolated: true,
+        })
+    }
+}
+
+/* REFSYS and SRSYS are defined at track midpoint */
+fn midpoint(trk: &Track) -> Epoch {
+    trk.epoch + trk.duration / 2
+}
+
+impl CGGTTS {
+    /// Returns all common view measurements between Self (local clock)
+    /// and remote clock. Tracks are matched on Epoch, SV and carrier code.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let a = CGGTTS::from_file("../data/single/GZSY8259.506")
+    ///     .unwrap();
+    /// // comparing a clock to itself
+    /// for cv in a.common_view(&a) {
+    ///     assert_eq!(cv.dt, 0.0);
+    /// }
+    /// ```
+    pub fn common_view(&self, remote: &Self) -> Vec<CommonView> {
+        let mut ret = Vec::<CommonView>::new();
+        for local in self.tracks() {
+            let matching = remote
+                .tracks()
+                .find(|trk| trk.epoch == local.epoch && trk.sv == local.sv && trk.frc == local.frc);
+            if let Some(remote) = matching {
+                ret.push(CommonView::from_tracks(local, remote));
+            }
+        }
+        ret
+    }
+    /// Returns all common view measurements between Self (local clock)
+    /// and remote clock, like [CGGTTS::common_view]. When the remote station
+    /// did not track a SV at a local track Epoch, its REFSYS is interpolated
+    /// to the local track midpoint, using the REFSYS and SRSYS of the neighboring
+    /// tracks (same SV and signal) that lie within `max_span` of that midpoint.
+    /// Resulting measurements are flagged as `interpolated`.
+    /// ```
+    /// use cggtts::prelude::{Duration, CGGTTS};
+    /// let a = CGGTTS::from_file("../data/single/GZSY8259.506")
+    ///     .unwrap();
+    /// let cv = a.common_view_interpolated(&a, Duration::from_seconds(960.0));
+    /// assert_eq!(cv.len(), a.tracks.len());
+    /// ```
+    pub fn common_view_interpolated(&self, remote: &Self, max_span: Duration) -> Vec<CommonView> {
+        let mut ret = Vec::<CommonView>::new();
+        for local in self.tracks() {
+            let neighbors: Vec<&Track> = remote
+                .tracks()
+                .filter(|trk| trk.sv == local.sv && trk.frc == local.frc)
+                .collect();
+
+            if let Some(remote) = neighbors.iter().find(|trk| trk.epoch == local.epoch) {
+                ret.push(CommonView::from_tracks(local, remote));
+                continue;
+            }
+
+            let t = midpoint(local);
+
+            let before = neighbors
+                .iter()
+                .filter(|trk| midpoint(trk) < t && t - midpoint(trk) <= max_span)
+                .max_by_key(|trk| trk.epoch);
+
+            let after = neighbors
+                .iter()
+                .filter(|trk| midpoint(trk) > t && midpoint(trk) - t <= max_span)
+                .min_by_key(|trk| trk.epoch);
+
+            if let Some(cv) = CommonView::interpolate(local, before.copied(), after.copied()) {
+                ret.push(cv);
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::{Duration, Epoch, Track, TrackData, CGGTTS};
+    #[test]
+    fn common_view() {
+        let a = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        let cv = a.common_view(&a);
+        assert_eq!(cv.len(), a.tracks.len());
+        for cv in cv {
+            assert_eq!(cv.dt, 0.0);
+            assert_eq!(cv.slope, 0.0);
+        }
+
+        let b = CGGTTS::from_file("../data/dual/EZGTR60.258").unwrap();
+        assert!(a.common_view(&b).is_empty(), "no SV in common");
+    }
+    #[test]
+    fn interpolated_common_view() {
+        let t0 = Epoch::from_mjd_utc(60258.0);
+        let duration = Duration::from_seconds(780.0);
+        let track = |t: Epoch, refsys: f64, srsys: f64| Track {
+            epoch: t,
+            duration,
+            frc: "L1C".to_string(),
+            data: TrackData {
+                refsys,
+                srsys,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // remote clock: x(t) = 1E-12 t², tracked every 16'
+        let x = |t: Epoch| 1.0E-12 * ((t + duration / 2) - t0).to_seconds().powi(2);
+        let y = |t: Epoch| 2.0E-12 * ((t + duration / 2) - t0).to_seconds();
+        let mut remote = CGGTTS::default();
+        for i in 0..10 {
+            let t = t0 + i as f64 * Duration::from_seconds(960.0);
+            remote.tracks.push(track(t, x(t), y(t)));
+        }
+
+        // local tracks, shifted by 5', steered to remote clock
+        let mut local = CGGTTS::default();
+        for i in 0..12 {
+            let t = t0 + Duration::from_seconds(300.0) + i as f64 * Duration::from_seconds(960.0);
+            local.tracks.push(track(t, x(t), y(t)));
+        }
+
+        assert!(local.common_view(&remote).is_empty());
+
+        let cv = local.common_view_interpolated(&remote, Duration::from_seconds(960.0));
+        assert_eq!(cv.len(), 10, "last track is out of reach");
+        for cv in cv {
+            assert!(cv.interpolated);
+            if cv.epoch < t0 + Duration::from_seconds(9.0 * 960.0) {
+                // cubic Hermite interpolation is exact on quadratics
+                assert!(cv.dt.abs() < 1.0E-15, "{:?}", cv);
+                assert!(cv.slope.abs() < 1.0E-18, "{:?}", cv);
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! ECEF coordinates, geodetic conversions and baselines
+use crate::CGGTTS;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// ECEF coordinates [m]
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Coordinates {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Reference ellipsoids
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ellipsoid {
+    /// World Geodetic System 1984
+    #[default]
+    WGS84,
+    /// Geodetic Reference System 1980 (ITRF)
+    GRS80,
+}
+
+impl Ellipsoid {
+    /// Returns semi major axis [m]
+    pub fn semi_major_axis(&self) -> f64 {
+        6_378_137.0
+    }
+    /// Returns flattening
+    pub fn flattening(&self) -> f64 {
+        match self {
+            Self::WGS84 => 1.0 / 298.257_223_563,
+            Self::GRS80 => 1.0 / 298.257_222_101,
+        }
+    }
+    /// Returns first eccentricity squared
+    pub fn eccentricity_squared(&self) -> f64 {
+        let f = self.flattening();
+        f * (2.0 - f)
+    }
+}
+
+/// Baseline between two stations
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Baseline {
+    /// Baseline vector (remote - local), ECEF [m]
+    pub vector: Coordinates,
+    /// Baseline length [m]
+    pub length: f64,
+    /// Azimuth of the remote station, seen from the local station,
+    /// clockwise from North [°]
+    pub azimuth: f64,
+    /// Elevation of the remote station, seen from the local station [°]
+    pub elevation: f64,
+}
+
+impl std::ops::Sub for Coordinates {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl std::ops::Add for Coordinates {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Coordinates {
+    /// Returns the norm of Self [m]
+    pub fn norm(&self) -> f64 {
+        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+    }
+    /// Returns distance to other coordinates [m]
+    pub fn distance(&self, rhs: &Self) -> f64 {
+        (*rhs - *self).norm()
+    }
+    /// Converts Self to geodetic coordinates on given ellipsoid.
+    /// Returns (latitude [°], longitude [°], height [m]).
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::coordinates::Ellipsoid;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let (lat, lon, h) = cggtts.apc_coordinates.to_geodetic(Ellipsoid::GRS80);
+    /// assert!((lat - 50.1).abs() < 0.1);
+    /// assert!((lon - 14.4).abs() < 0.1);
+    /// ```
+    pub fn to_geodetic(&self, ellipsoid: Ellipsoid) -> (f64, f64, f64) {
+        let a = ellipsoid.semi_major_axis();
+        let e2 = ellipsoid.eccentricity_squared();
+        let p = (self.x.powi(2) + self.y.powi(2)).sqrt();
+        let lon = self.y.atan2(self.x);
+
+        if p < 1.0E-9 {
+            // on the polar axis
+            let b = a * (1.0 - ellipsoid.flattening());
+            let lat = 90.0_f64.copysign(self.z);
+            return (lat, lon.to_degrees(), self.z.abs() - b);
+        }
+
+        let mut lat = self.z.atan2(p * (1.0 - e2));
+        let mut h = 0.0;
+        for _ in 0..10 {
+            let n = a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+            // well conditioned at all latitudes
+            h = p * lat.cos() + self.z * lat.sin() - a * a / n;
+            let next = self.z.atan2(p * (1.0 - e2 * n / (n + h)));
+            let converged = (next - lat).abs() < 1.0E-14;
+            lat = next;
+            if converged {
+                break;
+            }
+        }
+        (lat.to_degrees(), lon.to_degrees(), h)
+    }
+    /// Builds ECEF coordinates from geodetic coordinates on given ellipsoid:
+    /// latitude [°], longitude [°] and height [m]
+    pub fn from_geodetic(latitude: f64, longitude: f64, height: f64, ellipsoid: Ellipsoid) -> Self {
+        let a = ellipsoid.semi_major_axis();
+        let e2 = ellipsoid.eccentricity_squared();
+        let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+        let n = a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        Self {
+            x: (n + height) * lat.cos() * lon.cos(),
+            y: (n + height) * lat.cos() * lon.sin(),
+            z: (n * (1.0 - e2) + height) * lat.sin(),
+        }
+    }
+    /// Expresses Self in the local East, North, Up frame
+    /// centered on `origin` (WGS84). Returns (east, north, up) [m].
+    pub fn to_enu(&self, origin: &Self) -> (f64, f64, f64) {
+        let (lat, lon, _) = origin.to_geodetic(Ellipsoid::WGS84);
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+        let d = *self - *origin;
+        let e = -lon.sin() * d.x + lon.cos() * d.y;
+        let n = -lat.sin() * lon.cos() * d.x - lat.sin() * lon.sin() * d.y + lat.cos() * d.z;
+        let u = lat.cos() * lon.cos() * d.x + lat.cos() * lon.sin() * d.y + lat.sin() * d.z;
+        (e, n, u)
+    }
+    /// Builds ECEF coordinates from East, North, Up [m]
+    /// coordinates in the local frame centered on `origin` (WGS84)
+    pub fn from_enu(east: f64, north: f64, up: f64, origin: &Self) -> Self {
+        let (lat, lon, _) = origin.to_geodetic(Ellipsoid::WGS84);
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+        *origin
+            + Self {
+                x: -lon.sin() * east - lat.sin() * lon.cos() * north + lat.cos() * lon.cos() * up,
+                y: lon.cos() * east - lat.sin() * lon.sin() * north + lat.cos() * lon.sin() * up,
+                z: lat.cos() * north + lat.sin() * up,
+            }
+    }
+    /// Returns (azimuth [°], elevation [°]) of `target` seen from Self
+    pub fn azimuth_elevation(&self, target: &Self) -> (f64, f64) {
+        let (e, n, u) = target.to_enu(self);
+        let azimuth = e.atan2(n).to_degrees().rem_euclid(360.0);
+        let elevation = u.atan2((e.powi(2) + n.powi(2)).sqrt()).to_degrees();
+        (azimuth, elevation)
+    }
+    /// Returns baseline from Self to `remote`
+    pub fn baseline(&self, remote: &Self) -> Baseline {
+        let (azimuth, elevation) = self.azimuth_elevation(remote);
+        Baseline {
+            vector: *remote - *self,
+            length: self.distance(remote),
+            azimuth,
+            elevation,
+        }
+    }
+}
+
+impl CGGTTS {
+    /// Returns baseline between the antenna of Self and the remote station antenna
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let a = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let b = CGGTTS::from_file("../data/dual/RZSY8257.000")
+    ///     .unwrap();
+    /// let baseline = a.baseline(&b);
+    /// assert!(baseline.length > 0.0);
+    /// ```
+    pub fn baseline(&self, remote: &Self) -> Baseline {
+        self.apc_coordinates.baseline(&remote.apc_coordinates)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Coordinates, Ellipsoid};
+    #[test]
+    fn geodetic() {
+        for (lat, lon, h) in [
+            (0.0, 0.0, 0.0),
+            (45.0, 7.5, 250.0),
+            (-33.9, 151.2, 40.0),
+            (89.9, -120.0, 3000.0),
+            (48.8, 2.3, -10.0),
+        ] {
+            for ellipsoid in [Ellipsoid::WGS84, Ellipsoid::GRS80] {
+                let ecef = Coordinates::from_geodetic(lat, lon, h, ellipsoid);
+                let (lat_, lon_, h_) = ecef.to_geodetic(ellipsoid);
+                assert!((lat - lat_).abs() < 1.0E-9, "{} {}", lat, lat_);
+                assert!((lon - lon_).abs() < 1.0E-9, "{} {}", lon, lon_);
+                assert!((h - h_).abs() < 1.0E-6, "{} {}", h, h_);
+            }
+        }
+        let equator = Coordinates::from_geodetic(0.0, 90.0, 0.0, Ellipsoid::WGS84);
+        assert!(equator.x.abs() < 1.0E-6);
+        assert!((equator.y - 6_378_137.0).abs() < 1.0E-6);
+        let pole = Coordinates {
+            x: 0.0,
+            y: 0.0,
+            z: 6_356_852.314,
+        };
+        let (lat, _, h) = pole.to_geodetic(Ellipsoid::WGS84);
+        assert_eq!(lat, 90.0);
+        assert!((h - 100.0).abs() < 1.0E-3);
+    }
+    #[test]
+    fn enu() {
+        let origin = Coordinates::from_geodetic(45.0, 7.5, 250.0, Ellipsoid::WGS84);
+        let up = Coordinates::from_geodetic(45.0, 7.5, 350.0, Ellipsoid::WGS84);
+        let (e, n, u) = up.to_enu(&origin);
+        assert!(e.abs() < 1.0E-6);
+        assert!(n.abs() < 1.0E-6);
+        assert!((u - 100.0).abs() < 1.0E-6);
+
+        let target = Coordinates::from_enu(100.0, -50.0, 20.0, &origin);
+        let (e, n, u) = target.to_enu(&origin);
+        assert!((e - 100.0).abs() < 1.0E-6);
+        assert!((n + 50.0).abs() < 1.0E-6);
+        assert!((u - 20.0).abs() < 1.0E-6);
+
+        let east = Coordinates::from_enu(1000.0, 0.0, 0.0, &origin);
+        let baseline = origin.baseline(&east);
+        assert!((baseline.length - 1000.0).abs() < 1.0E-6);
+        assert!((baseline.azimuth - 90.0).abs() < 1.0E-6);
+        assert!(baseline.elevation.abs() < 1.0E-6);
+    }
+}
@@ -131,22 +131,27 @@ impl SystemDelay {
             cal_id: Some(info.to_string()),
         }
     }
-    /// Returns total system delay for given carrier code
-    pub fn total_delay(&self, code: Code) -> Option<f64> {
-        for (k, v) in self.delays.iter() {
-            if *k == code {
-                return Some(v.value() + self.rf_cable_delay + self.ref_delay);
-            }
+    /* Total delay [ns] of given specified delay, as defined in CGGTTS 2E */
+    fn total(&self, delay: &Delay) -> f64 {
+        match delay {
+            Delay::Internal(v) => v + self.rf_cable_delay - self.ref_delay,
+            Delay::System(v) => v - self.ref_delay,
         }
-        None
+    }
+    /// Returns total system delay [ns] for given carrier code,
+    /// that is INT DLY + CAB DLY - REF DLY, or SYS DLY - REF DLY
+    pub fn total_delay(&self, code: Code) -> Option<f64> {
+        self.delays
+            .iter()
+            .find(|(k, _)| *k == code)
+            .map(|(_, v)| self.total(v))
     }
     /// Groups total system delay per carrier codes
     pub fn total_delays(&self) -> Vec<(Code, f64)> {
-        let mut res: Vec<(Code, f64)> = Vec::new();
-        for (k, v) in self.delays.iter() {
-            res.push((*k, v.value() + self.rf_cable_delay + self.ref_delay))
-        }
-        res
+        self.delays
+            .iter()
+            .map(|(k, v)| (*k, self.total(v)))
+            .collect()
     }
 }
 
@@ -181,11 +186,14 @@ mod delay {
         assert_eq!(delay.ref_delay, 20.0);
         let total = delay.total_delay(Code::C1);
         assert!(total.is_some());
-        assert_eq!(total.unwrap(), 80.0);
+        assert_eq!(total.unwrap(), 40.0);
         let totals = delay.total_delays();
         assert!(!totals.is_empty());
         assert_eq!(totals[0].0, Code::C1);
-        assert_eq!(totals[0].1, 80.0);
+        assert_eq!(totals[0].1, 40.0);
         assert!(delay.total_delay(Code::P1).is_none());
+        delay.delays.push((Code::P1, Delay::System(50.0)));
+        assert_eq!(delay.total_delay(Code::P1), Some(30.0));
+        assert_eq!(delay.total_delays()[1], (Code::P1, 30.0));
     }
 }
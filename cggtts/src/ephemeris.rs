@@ -0,0 +1,724 @@
+//! Broadcast ephemeris: SV position, velocity and clock at signal transmission time
+use crate::prelude::{Constellation, Duration, Epoch, TimeScale, SV};
+use crate::{
+    Coordinates, EARTH_GM_BEIDOU, EARTH_GM_GALILEO, EARTH_GM_GLONASS, EARTH_GM_GPS,
+    EARTH_ROTATION_RATE_BEIDOU, EARTH_ROTATION_RATE_GALILEO, EARTH_ROTATION_RATE_GLONASS,
+    EARTH_ROTATION_RATE_GPS, RELATIVISTIC_F, SPEED_OF_LIGHT,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Maximal integration step [s] of the GLONASS state propagation
+const GLONASS_STEP_SECONDS: f64 = 30.0;
+
+/// Time step [s] used in the numerical derivation of the Keplerian position
+const VELOCITY_STEP_SECONDS: f64 = 0.5;
+
+/// Periodic relativistic correction [s] of the SV clock, from broadcast
+/// Keplerian elements: F·e·sqrt(A)·sin(E), with `e` the eccentricity, `sqrt_a` the square
+/// root of the semi major axis [sqrt(m)] and `eccentric_anomaly` E [rad].
+/// ```
+/// use cggtts::ephemeris::relativistic_correction_kepler;
+/// // maximal correction, for a typical GPS orbit (e = 0.02)
+/// let dt = relativistic_correction_kepler(0.02, 5153.6, std::f64::consts::FRAC_PI_2);
+/// assert!((dt + 45.8E-9).abs() < 0.1E-9);
+/// ```
+pub fn relativistic_correction_kepler(e: f64, sqrt_a: f64, eccentric_anomaly: f64) -> f64 {
+    RELATIVISTIC_F * e * sqrt_a * eccentric_anomaly.sin()
+}
+
+/// SV state, as predicted by broadcast ephemeris
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SvState {
+    /// SV position, ECEF [m]
+    pub position: Coordinates,
+    /// SV velocity, ECEF [m/s]
+    pub velocity: Coordinates,
+    /// SV clock offset to GNSS system time [s].
+    /// For Keplerian ephemeris, this is the broadcast polynomial a0 + a1·dt + a2·dt²,
+    /// the relativistic effect is not included.
+    /// For GLONASS, this is -τn + γn·dt, which already accounts for relativistic effects.
+    pub clock: f64,
+    /// Broadcast group delays, see [GroupDelays::group_delay]
+    pub group_delays: GroupDelays,
+    /// Issue of Ephemeris, formatted as expected in [crate::track::TrackData::ioe]
+    pub ioe: u16,
+}
+
+/// Inter signal corrections [s] of the modernized navigation messages
+/// (GPS and QZSS CNAV, BeiDou B-CNAV1 and B-CNAV2), when broadcast
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InterSignalCorrections {
+    /// ISC L1C/A (GPS, QZSS)
+    pub l1ca: Option<f64>,
+    /// ISC L2C (GPS, QZSS)
+    pub l2c: Option<f64>,
+    /// ISC L5I5 (GPS, QZSS)
+    pub l5i5: Option<f64>,
+    /// ISC L5Q5 (GPS, QZSS)
+    pub l5q5: Option<f64>,
+    /// ISC B1Cd (BeiDou)
+    pub b1cd: Option<f64>,
+    /// ISC B2ad (BeiDou)
+    pub b2ad: Option<f64>,
+    /// TGD B1Cp (BeiDou)
+    pub tgd_b1cp: Option<f64>,
+    /// TGD B2ap (BeiDou)
+    pub tgd_b2ap: Option<f64>,
+}
+
+/// Broadcast group delays [s] of a SV
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GroupDelays {
+    /// TGD for GPS and QZSS, BGD(E5a/E1) for Galileo, TGD1 for BeiDou, 0 for GLONASS
+    pub tgd: f64,
+    /// BGD(E5b/E1) for Galileo, TGD2 for BeiDou, 0 otherwise
+    pub tgd2: f64,
+    /// Inter signal corrections, see [InterSignalCorrections]
+    pub isc: InterSignalCorrections,
+}
+
+impl GroupDelays {
+    /// Returns the group delay [s] to subtract from the broadcast SV clock offset,
+    /// for a pseudo-range measured on given frequency code.
+    /// - GPS and QZSS: TGD on L1 P(Y), γ·TGD on L2 P(Y) and L5, with γ the squared
+    ///   frequency ratio to L1. TGD - ISC applies to L1 C/A, L2C and L5 when inter signal
+    ///   corrections are broadcast
+    /// - Galileo: BGD(E5a/E1) on E1, scaled to E5a on E5a, BGD(E5b/E1) scaled to E5b on E5b
+    /// - BeiDou: TGD1 on B1I, TGD2 on B2I and 0 on B3I, the reference signal of the
+    ///   broadcast clock. TGD B1Cp (- ISC B1Cd) on B1C and TGD B2ap (- ISC B2ad) on B2a
+    ///
+    /// Group delays are 0 for other signals and constellations.
+    /// ```
+    /// use cggtts::ephemeris::GroupDelays;
+    /// use cggtts::prelude::Constellation;
+    /// let delays = GroupDelays {
+    ///     tgd: 2.0E-9,
+    ///     tgd2: -3.0E-9,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(delays.group_delay(Constellation::BeiDou, "L2I"), 2.0E-9);
+    /// assert_eq!(delays.group_delay(Constellation::BeiDou, "L7I"), -3.0E-9);
+    /// assert_eq!(delays.group_delay(Constellation::BeiDou, "L6I"), 0.0);
+    /// ```
+    pub fn group_delay(&self, constellation: Constellation, frc: &str) -> f64 {
+        let band = match frc {
+            "E1" => "L1",
+            "E5a" => "L5",
+            "E5b" => "L7",
+            "B1" => "L2",
+            "B2" => "L7",
+            "B3" => "L6",
+            _ => frc.get(0..2).unwrap_or(""),
+        };
+        let attribute = frc.get(2..3).unwrap_or("");
+        let isc = &self.isc;
+        // scales a group delay from L1 (E1) to given frequency [MHz]
+        let gamma = |mhz: f64| (1575.42 / mhz).powi(2);
+        let or_scaled = |isc: Option<f64>, mhz: f64| match isc {
+            Some(isc) => self.tgd - isc,
+            None => gamma(mhz) * self.tgd,
+        };
+        match constellation {
+            Constellation::GPS | Constellation::QZSS => match (band, attribute) {
+                ("L1", "C") => self.tgd - isc.l1ca.unwrap_or(0.0),
+                ("L1", _) => self.tgd,
+                ("L2", "S" | "L" | "X") => or_scaled(isc.l2c, 1227.60),
+                ("L2", _) => gamma(1227.60) * self.tgd,
+                ("L5", "I") => or_scaled(isc.l5i5, 1176.45),
+                ("L5", _) => or_scaled(isc.l5q5, 1176.45),
+                _ => 0.0,
+            },
+            Constellation::Galileo => match band {
+                "L1" => self.tgd,
+                "L5" => gamma(1176.45) * self.tgd,
+                "L7" => gamma(1207.14) * self.tgd2,
+                _ => 0.0,
+            },
+            Constellation::BeiDou => {
+                let b1cp = isc.tgd_b1cp.unwrap_or(0.0);
+                let b2ap = isc.tgd_b2ap.unwrap_or(0.0);
+                match (band, attribute) {
+                    ("L2", _) => self.tgd,
+                    ("L7", "I" | "Q" | "X" | "") => self.tgd2,
+                    ("L1", "D") => b1cp - isc.b1cd.unwrap_or(0.0),
+                    ("L1", "P" | "X") => b1cp,
+                    ("L5", "D") => b2ap - isc.b2ad.unwrap_or(0.0),
+                    ("L5", "P" | "X") => b2ap,
+                    _ => 0.0,
+                }
+            },
+            _ => 0.0,
+        }
+    }
+}
+
+/*
+ * (GM [m³/s²], Earth rotation rate [rad/s], system time) that apply
+ * to the Keplerian orbits of given constellation
+ */
+pub(crate) fn kepler_constants(constellation: Constellation) -> (f64, f64, TimeScale) {
+    match constellation {
+        Constellation::Galileo => (
+            EARTH_GM_GALILEO,
+            EARTH_ROTATION_RATE_GALILEO,
+            TimeScale::GST,
+        ),
+        Constellation::BeiDou => (EARTH_GM_BEIDOU, EARTH_ROTATION_RATE_BEIDOU, TimeScale::BDT),
+        _ => (EARTH_GM_GPS, EARTH_ROTATION_RATE_GPS, TimeScale::GPST),
+    }
+}
+
+/// Broadcast ephemeris
+pub trait Ephemeris {
+    /// SV described by these ephemeris
+    fn sv(&self) -> SV;
+    /// Returns the SV state at given Epoch
+    fn state(&self, t: Epoch) -> SvState;
+    /// Returns the SV state at signal transmission time, for a signal received
+    /// at Epoch `t` (in the GNSS system time) and measured with given raw pseudo-range [m]
+    fn transmission_state(&self, t: Epoch, pseudo_range: f64) -> SvState {
+        let t_tx = t - Duration::from_seconds(pseudo_range / SPEED_OF_LIGHT);
+        let clock = self.state(t_tx).clock;
+        self.state(t_tx - Duration::from_seconds(clock))
+    }
+}
+
+/// Keplerian broadcast ephemeris, as broadcast by GPS, Galileo, BeiDou and QZSS
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeplerEphemeris {
+    /// SV described by these ephemeris
+    pub sv: SV,
+    /// Time of clock
+    pub toc: Epoch,
+    /// Time of ephemeris
+    pub toe: Epoch,
+    /// Issue of data (IODE for GPS and QZSS, IODnav for Galileo, AODE for BeiDou)
+    pub iode: u16,
+    /// SV clock bias [s]
+    pub af0: f64,
+    /// SV clock drift [s/s]
+    pub af1: f64,
+    /// SV clock drift rate [s/s²]
+    pub af2: f64,
+    /// Broadcast group delay [s], see [GroupDelays::tgd]
+    pub tgd: f64,
+    /// Second broadcast group delay [s], see [GroupDelays::tgd2]
+    pub tgd2: f64,
+    /// Square root of the semi major axis [m^1/2]
+    pub sqrt_a: f64,
+    /// Eccentricity
+    pub e: f64,
+    /// Inclination at reference time [rad]
+    pub i0: f64,
+    /// Rate of inclination [rad/s]
+    pub idot: f64,
+    /// Longitude of ascending node at weekly epoch [rad]
+    pub omega0: f64,
+    /// Rate of right ascension [rad/s]
+    pub omega_dot: f64,
+    /// Argument of perigee [rad]
+    pub w: f64,
+    /// Mean anomaly at reference time [rad]
+    pub m0: f64,
+    /// Mean motion difference [rad/s]
+    pub delta_n: f64,
+    /// Argument of latitude harmonic corrections (cosine, sine) [rad]
+    pub cuc: f64,
+    /// See [Self::cuc]
+    pub cus: f64,
+    /// Orbit radius harmonic corrections (cosine, sine) [m]
+    pub crc: f64,
+    /// See [Self::crc]
+    pub crs: f64,
+    /// Inclination harmonic corrections (cosine, sine) [rad]
+    pub cic: f64,
+    /// See [Self::cic]
+    pub cis: f64,
+}
+
+impl KeplerEphemeris {
+    /* BeiDou geostationary SV use a dedicated frame rotation */
+    fn is_beidou_geo(&self) -> bool {
+        self.sv.constellation == Constellation::BeiDou
+            && (self.sv.prn <= 5 || (59..=63).contains(&self.sv.prn))
+    }
+    /// Returns the eccentric anomaly [rad] at given Epoch
+    pub fn eccentric_anomaly(&self, t: Epoch) -> f64 {
+        let (gm, _, _) = kepler_constants(self.sv.constellation);
+        let a = self.sqrt_a.powi(2);
+        let n = (gm / a.powi(3)).sqrt() + self.delta_n;
+        let m = self.m0 + n * (t - self.toe).to_seconds();
+        let mut e_k = m;
+        for _ in 0..10 {
+            e_k = m + self.e * e_k.sin();
+        }
+        e_k
+    }
+    /// Returns the SV position at given Epoch, ECEF [m]
+    pub fn position(&self, t: Epoch) -> Coordinates {
+        let (_, omega_e, timescale) = kepler_constants(self.sv.constellation);
+        let a = self.sqrt_a.powi(2);
+        let tk = (t - self.toe).to_seconds();
+
+        let e_k = self.eccentric_anomaly(t);
+        let nu = ((1.0 - self.e.powi(2)).sqrt() * e_k.sin()).atan2(e_k.cos() - self.e);
+        let phi = nu + self.w;
+        let (sin_2phi, cos_2phi) = (2.0 * phi).sin_cos();
+
+        let u = phi + self.cuc * cos_2phi + self.cus * sin_2phi;
+        let r = a * (1.0 - self.e * e_k.cos()) + self.crc * cos_2phi + self.crs * sin_2phi;
+        let i = self.i0 + self.idot * tk + self.cic * cos_2phi + self.cis * sin_2phi;
+
+        let (_, toe_nanos) = self.toe.to_time_scale(timescale).to_time_of_week();
+        let toe_seconds = toe_nanos as f64 * 1.0E-9;
+
+        let (x, y) = (r * u.cos(), r * u.sin());
+
+        if self.is_beidou_geo() {
+            // inertial orbit, then rotated into BDCS
+            let omega = self.omega0 + self.omega_dot * tk - omega_e * toe_seconds;
+            let (xg, yg, zg) = (
+                x * omega.cos() - y * i.cos() * omega.sin(),
+                x * omega.sin() + y * i.cos() * omega.cos(),
+                y * i.sin(),
+            );
+            let (sin_x, cos_x) = (-5.0_f64.to_radians()).sin_cos();
+            let (sin_z, cos_z) = (omega_e * tk).sin_cos();
+            let (y1, z1) = (yg * cos_x + zg * sin_x, -yg * sin_x + zg * cos_x);
+            return Coordinates {
+                x: xg * cos_z + y1 * sin_z,
+                y: -xg * sin_z + y1 * cos_z,
+                z: z1,
+            };
+        }
+
+        let omega = self.omega0 + (self.omega_dot - omega_e) * tk - omega_e * toe_seconds;
+        Coordinates {
+            x: x * omega.cos() - y * i.cos() * omega.sin(),
+            y: x * omega.sin() + y * i.cos() * omega.cos(),
+            z: y * i.sin(),
+        }
+    }
+    /// Returns the broadcast SV clock offset [s] at given Epoch,
+    /// relativistic effect excluded
+    pub fn clock(&self, t: Epoch) -> f64 {
+        let dt = (t - self.toc).to_seconds();
+        self.af0 + self.af1 * dt + self.af2 * dt.powi(2)
+    }
+    /// Returns the periodic relativistic SV clock correction [s] at given Epoch
+    pub fn relativistic_correction(&self, t: Epoch) -> f64 {
+        relativistic_correction_kepler(self.e, self.sqrt_a, self.eccentric_anomaly(t))
+    }
+    /// Returns the Issue of Ephemeris, as expected in CGGTTS:
+    /// the issue of data, except for BeiDou that uses the hour of the time of clock
+    pub fn ioe(&self) -> u16 {
+        match self.sv.constellation {
+            Constellation::BeiDou => {
+                let (_, nanos) = self.toc.to_time_scale(TimeScale::BDT).to_time_of_week();
+                ((nanos / 3_600_000_000_000) % 24) as u16
+            },
+            _ => self.iode,
+        }
+    }
+}
+
+impl Ephemeris for KeplerEphemeris {
+    fn sv(&self) -> SV {
+        self.sv
+    }
+    fn state(&self, t: Epoch) -> SvState {
+        let dt = Duration::from_seconds(VELOCITY_STEP_SECONDS);
+        let (before, after) = (self.position(t - dt), self.position(t + dt));
+        let scale = 1.0 / (2.0 * VELOCITY_STEP_SECONDS);
+        SvState {
+            position: self.position(t),
+            velocity: Coordinates {
+                x: (after.x - before.x) * scale,
+                y: (after.y - before.y) * scale,
+                z: (after.z - before.z) * scale,
+            },
+            clock: self.clock(t),
+            group_delays: GroupDelays {
+                tgd: self.tgd,
+                tgd2: self.tgd2,
+                isc: Default::default(),
+            },
+            ioe: self.ioe(),
+        }
+    }
+}
+
+/// GLONASS broadcast ephemeris: SV state vector in PZ-90, propagated
+/// by 4th order Runge-Kutta integration as described in the GLONASS ICD
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GlonassEphemeris {
+    /// SV described by these ephemeris
+    pub sv: SV,
+    /// Reference Epoch (tb)
+    pub toe: Epoch,
+    /// SV clock bias τn [s], SV clock offset is -τn + γn·dt
+    pub tau_n: f64,
+    /// Relative frequency bias γn
+    pub gamma_n: f64,
+    /// SV position at reference Epoch, ECEF [m]
+    pub position: Coordinates,
+    /// SV velocity at reference Epoch, ECEF [m/s]
+    pub velocity: Coordinates,
+    /// Luni-solar acceleration at reference Epoch, ECEF [m/s²]
+    pub acceleration: Coordinates,
+}
+
+impl GlonassEphemeris {
+    /// PZ-90 semi major axis [m]
+    const EARTH_RADIUS: f64 = 6_378_136.0;
+    /// Second zonal harmonic
+    const J2: f64 = 1.082_625_75E-3;
+
+    /* state derivative, in the rotating frame */
+    fn derivative(&self, state: &[f64; 6]) -> [f64; 6] {
+        let [x, y, z, vx, vy, vz] = *state;
+        let w = EARTH_ROTATION_RATE_GLONASS;
+        let r2 = x * x + y * y + z * z;
+        let r = r2.sqrt();
+        let mu = EARTH_GM_GLONASS / r.powi(3);
+        let j2 = 1.5 * Self::J2 * EARTH_GM_GLONASS * Self::EARTH_RADIUS.powi(2) / r.powi(5);
+        let z2 = 5.0 * z * z / r2;
+        [
+            vx,
+            vy,
+            vz,
+            -mu * x - j2 * x * (1.0 - z2) + w * w * x + 2.0 * w * vy + self.acceleration.x,
+            -mu * y - j2 * y * (1.0 - z2) + w * w * y - 2.0 * w * vx + self.acceleration.y,
+            -mu * z - j2 * z * (3.0 - z2) + self.acceleration.z,
+        ]
+    }
+    /* single Runge-Kutta step */
+    fn step(&self, state: &[f64; 6], h: f64) -> [f64; 6] {
+        let shift = |k: &[f64; 6], factor: f64| {
+            let mut s = *state;
+            for (s, k) in s.iter_mut().zip(k.iter()) {
+                *s += k * factor;
+            }
+            s
+        };
+        let k1 = self.derivative(state);
+        let k2 = self.derivative(&shift(&k1, h / 2.0));
+        let k3 = self.derivative(&shift(&k2, h / 2.0));
+        let k4 = self.derivative(&shift(&k3, h));
+        let mut s = *state;
+        for i in 0..6 {
+            s[i] += h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+        }
+        s
+    }
+    /// Returns the SV position and velocity at given Epoch, ECEF [m, m/s]
+    pub fn propagate(&self, t: Epoch) -> (Coordinates, Coordinates) {
+        let mut state = [
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.velocity.x,
+            self.velocity.y,
+            self.velocity.z,
+        ];
+        let mut remaining = (t - self.toe).to_seconds();
+        while remaining.abs() > 0.0 {
+            let h = remaining.signum() * remaining.abs().min(GLONASS_STEP_SECONDS);
+            state = self.step(&state, h);
+            remaining -= h;
+        }
+        let [x, y, z, vx, vy, vz] = state;
+        (
+            Coordinates { x, y, z },
+            Coordinates {
+                x: vx,
+                y: vy,
+                z: vz,
+            },
+        )
+    }
+    /// Returns the SV clock offset [s] at given Epoch
+    pub fn clock(&self, t: Epoch) -> f64 {
+        -self.tau_n + self.gamma_n * (t - self.toe).to_seconds()
+    }
+    /// Returns the Issue of Ephemeris, as expected in CGGTTS: the quarter of hour
+    /// of tb within the (Moscow) day, starting at 1 = 00h00m00s
+    pub fn ioe(&self) -> u16 {
+        let seconds = (self.toe.to_mjd_utc_days().fract() * 86_400.0).round() as u32;
+        (((seconds + 3 * 3600) % 86_400) / 900 + 1) as u16
+    }
+}
+
+impl Ephemeris for GlonassEphemeris {
+    fn sv(&self) -> SV {
+        self.sv
+    }
+    fn state(&self, t: Epoch) -> SvState {
+        let (position, velocity) = self.propagate(t);
+        SvState {
+            position,
+            velocity,
+            clock: self.clock(t),
+            group_delays: Default::default(),
+            ioe: self.ioe(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Ephemeris, GlonassEphemeris, GroupDelays, InterSignalCorrections, KeplerEphemeris,
+    };
+    use crate::prelude::{Constellation, Duration, Epoch, TimeScale, SV};
+    use crate::visibility::Almanac;
+    use crate::{Coordinates, EARTH_GM_GLONASS, EARTH_ROTATION_RATE_GLONASS, SPEED_OF_LIGHT};
+    use std::str::FromStr;
+
+    fn kepler(sv: &str, toe: Epoch) -> KeplerEphemeris {
+        KeplerEphemeris {
+            sv: SV::from_str(sv).unwrap(),
+            toc: toe,
+            toe,
+            iode: 42,
+            af0: 1.0E-4,
+            af1: 1.0E-11,
+            af2: 0.0,
+            tgd: -5.0E-9,
+            tgd2: 0.0,
+            sqrt_a: 5153.6,
+            e: 0.01,
+            i0: 0.96,
+            idot: 0.0,
+            omega0: 1.2,
+            omega_dot: -8.0E-9,
+            w: 0.5,
+            m0: 2.0,
+            delta_n: 0.0,
+            cuc: 0.0,
+            cus: 0.0,
+            crc: 0.0,
+            crs: 0.0,
+            cic: 0.0,
+            cis: 0.0,
+        }
+    }
+    #[test]
+    fn keplerian() {
+        let toe = Epoch::from_gregorian(2023, 11, 7, 2, 0, 0, 0, TimeScale::GPST);
+        let eph = kepler("G05", toe);
+        // unperturbed ephemeris match the almanac model
+        let almanac = Almanac {
+            sv: eph.sv,
+            toa: toe,
+            sqrt_a: eph.sqrt_a,
+            e: eph.e,
+            i0: eph.i0,
+            omega0: eph.omega0,
+            omega_dot: eph.omega_dot,
+            w: eph.w,
+            m0: eph.m0,
+        };
+        for minutes in [-60.0, 0.0, 30.0, 120.0] {
+            let t = toe + Duration::from_seconds(minutes * 60.0);
+            let position = eph.position(t);
+            assert!(position.distance(&almanac.position(t)) < 1.0E-3);
+            let state = eph.state(t);
+            // ECEF velocity of a MEO SV
+            let speed = state.velocity.norm();
+            assert!(speed > 2.5E3 && speed < 4.0E3, "{}", speed);
+            assert_eq!(state.ioe, 42);
+            assert_eq!(state.group_delays.tgd, -5.0E-9);
+            let expected = 1.0E-4 + 1.0E-11 * minutes * 60.0;
+            assert!((state.clock - expected).abs() < 1.0E-15);
+        }
+
+        // almanacs use the Earth constants and system time of their constellation
+        for (sv, timescale) in [("E11", TimeScale::GST), ("C19", TimeScale::BDT)] {
+            let toe = Epoch::from_gregorian(2023, 11, 7, 2, 0, 0, 0, timescale);
+            let eph = kepler(sv, toe);
+            let almanac = Almanac {
+                sv: eph.sv,
+                toa: toe,
+                ..almanac
+            };
+            for minutes in [-60.0, 30.0, 120.0] {
+                let t = toe + Duration::from_seconds(minutes * 60.0);
+                assert!(eph.position(t).distance(&almanac.position(t)) < 1.0E-3);
+            }
+        }
+
+        // harmonic corrections
+        let perturbed = KeplerEphemeris { crs: 100.0, ..eph };
+        let offset = perturbed.position(toe).norm() - eph.position(toe).norm();
+        assert!(offset.abs() > 0.0 && offset.abs() <= 100.0);
+
+        // maximal relativistic correction
+        let dt = eph.relativistic_correction(toe);
+        assert!(dt.abs() < 23.0E-9);
+    }
+    #[test]
+    fn beidou() {
+        let toe = Epoch::from_gregorian(2023, 11, 7, 13, 0, 0, 0, TimeScale::BDT);
+        let meo = kepler("C20", toe);
+        assert_eq!(meo.ioe(), 13);
+        assert_eq!(meo.state(toe).ioe, 13);
+
+        // geosynchronous orbit, broadcast in the frame tilted by 5°
+        let (gm, omega_e) = (3.986_004_418E14, 7.292_115E-5_f64);
+        let radius = (gm / omega_e.powi(2)).cbrt();
+        let geo = KeplerEphemeris {
+            sqrt_a: radius.sqrt(),
+            e: 0.0,
+            i0: 0.0,
+            w: 0.0,
+            omega_dot: 0.0,
+            ..kepler("C03", toe)
+        };
+        let origin = geo.position(toe);
+        let sidereal_day = 2.0 * std::f64::consts::PI / omega_e;
+        for hours in [1.0, 6.0, 12.0] {
+            let position = geo.position(toe + Duration::from_hours(hours));
+            assert!((position.norm() - radius).abs() < 1.0E-3);
+            // oscillates within ±5° of latitude
+            assert!(position.z.abs() <= radius * 5.0_f64.to_radians().sin() + 1.0E-3);
+        }
+        let position = geo.position(toe + Duration::from_seconds(sidereal_day));
+        assert!(position.distance(&origin) < 1.0, "{:?}", position);
+    }
+    #[test]
+    fn transmission_time() {
+        let toe = Epoch::from_gregorian(2023, 11, 7, 2, 0, 0, 0, TimeScale::GPST);
+        let eph = kepler("G05", toe);
+        let t = toe + Duration::from_seconds(600.0);
+        let pseudo_range = 22.0E6;
+        let state = eph.transmission_state(t, pseudo_range);
+        let t_tx = t - Duration::from_seconds(pseudo_range / SPEED_OF_LIGHT + state.clock);
+        assert!(state.position.distance(&eph.position(t_tx)) < 1.0E-3);
+        // about 75 ms of propagation
+        assert!(state.position.distance(&eph.position(t)) > 200.0);
+    }
+    #[test]
+    fn glonass() {
+        let toe = Epoch::from_gregorian_utc(2023, 11, 7, 21, 15, 0, 0);
+        // circular orbit, in the equatorial plane
+        let radius = 25_510_000.0_f64;
+        let speed = (EARTH_GM_GLONASS / radius).sqrt();
+        let eph = GlonassEphemeris {
+            sv: SV::from_str("R07").unwrap(),
+            toe,
+            tau_n: 1.0E-5,
+            gamma_n: 1.0E-12,
+            position: Coordinates {
+                x: radius,
+                y: 0.0,
+                z: 0.0,
+            },
+            // inertial velocity, expressed in the rotating frame
+            velocity: Coordinates {
+                x: 0.0,
+                y: speed - EARTH_ROTATION_RATE_GLONASS * radius,
+                z: 0.0,
+            },
+            acceleration: Coordinates::default(),
+        };
+        // 00:15 Moscow time
+        assert_eq!(eph.ioe(), 2);
+
+        let state = eph.state(toe);
+        assert_eq!(state.position, eph.position);
+        assert_eq!(state.clock, -1.0E-5);
+
+        let t = toe + Duration::from_seconds(900.0);
+        let state = eph.state(t);
+        assert_eq!(state.ioe, 2);
+        assert!((state.clock + 1.0E-5 - 900.0E-12).abs() < 1.0E-18);
+        // J2 perturbs the radius by a few km at most
+        assert!((state.position.norm() - radius).abs() < 5.0E3);
+        assert!(state.position.z.abs() < 1.0E-3);
+
+        // propagating back yields the initial state
+        let back = GlonassEphemeris {
+            toe: t,
+            position: state.position,
+            velocity: state.velocity,
+            ..eph
+        };
+        let (position, velocity) = back.propagate(toe);
+        assert!(position.distance(&eph.position) < 1.0E-2);
+        assert!(velocity.distance(&eph.velocity) < 1.0E-5);
+    }
+    #[test]
+    fn group_delays() {
+        let mut delays = GroupDelays {
+            tgd: 4.0E-9,
+            tgd2: -2.0E-9,
+            ..Default::default()
+        };
+        let gamma = |mhz: f64| (1575.42_f64 / mhz).powi(2);
+        for (constellation, frc, expected) in [
+            (Constellation::GPS, "L1C", 4.0E-9),
+            (Constellation::GPS, "L1W", 4.0E-9),
+            (Constellation::GPS, "L2W", gamma(1227.60) * 4.0E-9),
+            (Constellation::GPS, "L2L", gamma(1227.60) * 4.0E-9),
+            (Constellation::QZSS, "L5Q", gamma(1176.45) * 4.0E-9),
+            (Constellation::Galileo, "E1", 4.0E-9),
+            (Constellation::Galileo, "E5a", gamma(1176.45) * 4.0E-9),
+            (Constellation::Galileo, "E5b", gamma(1207.14) * -2.0E-9),
+            (Constellation::Galileo, "E6", 0.0),
+            (Constellation::BeiDou, "L2I", 4.0E-9),
+            (Constellation::BeiDou, "B1", 4.0E-9),
+            (Constellation::BeiDou, "L7Q", -2.0E-9),
+            (Constellation::BeiDou, "L6I", 0.0),
+            (Constellation::BeiDou, "L1P", 0.0),
+            (Constellation::Glonass, "L1C", 0.0),
+        ] {
+            let delay = delays.group_delay(constellation, frc);
+            assert!(
+                (delay - expected).abs() < 1.0E-18,
+                "{} {}",
+                constellation,
+                frc
+            );
+        }
+
+        delays.isc = InterSignalCorrections {
+            l1ca: Some(1.0E-9),
+            l2c: Some(-1.0E-9),
+            l5i5: Some(0.5E-9),
+            l5q5: Some(0.25E-9),
+            b1cd: Some(0.3E-9),
+            b2ad: Some(0.6E-9),
+            tgd_b1cp: Some(-3.0E-9),
+            tgd_b2ap: Some(-5.0E-9),
+        };
+        for (constellation, frc, expected) in [
+            (Constellation::GPS, "L1C", 3.0E-9),
+            (Constellation::GPS, "L1W", 4.0E-9),
+            (Constellation::GPS, "L2L", 5.0E-9),
+            (Constellation::GPS, "L2W", gamma(1227.60) * 4.0E-9),
+            (Constellation::GPS, "L5I", 3.5E-9),
+            (Constellation::GPS, "L5Q", 3.75E-9),
+            (Constellation::BeiDou, "L1D", -3.3E-9),
+            (Constellation::BeiDou, "L1P", -3.0E-9),
+            (Constellation::BeiDou, "L5D", -5.6E-9),
+            (Constellation::BeiDou, "L5P", -5.0E-9),
+            (Constellation::BeiDou, "L2I", 4.0E-9),
+        ] {
+            let delay = delays.group_delay(constellation, frc);
+            assert!(
+                (delay - expected).abs() < 1.0E-18,
+                "{} {}",
+                constellation,
+                frc
+            );
+        }
+    }
+}
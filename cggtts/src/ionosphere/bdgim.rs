@@ -0,0 +1,293 @@
+//! BeiDou-3 global broadcast ionospheric delay correction model (BDGIM),
+//! BDS-SIS-ICD B1C §7.2.6 and B2a §7.2.6
+use super::{seconds_of_day, IonosphericModel, L1_FREQUENCY};
+use crate::prelude::{Epoch, TimeScale};
+use crate::SPEED_OF_LIGHT;
+use std::f64::consts::PI;
+
+/// Degrees (n, m) of the broadcast spherical harmonics, in broadcast order
+const BROADCAST_TERMS: [(usize, i32); 9] = [
+    (0, 0),
+    (1, 0),
+    (1, 1),
+    (1, -1),
+    (2, 0),
+    (2, 1),
+    (2, -1),
+    (2, 2),
+    (2, -2),
+];
+
+/// Degrees (n, m) of the non broadcast spherical harmonics, in ICD order
+const PREDICTED_TERMS: [(usize, i32); 17] = [
+    (3, 0),
+    (3, 1),
+    (3, -1),
+    (3, 2),
+    (3, -2),
+    (3, 3),
+    (3, -3),
+    (4, 0),
+    (4, 1),
+    (4, -1),
+    (4, 2),
+    (4, -2),
+    (4, 3),
+    (4, -3),
+    (4, 4),
+    (4, -4),
+    (5, 0),
+];
+
+/// BeiDou-3 BDGIM model. Delays are expressed on B1C.
+/// `alpha` are broadcast in the navigation message and are all this model needs,
+/// see [Bdgim::from_broadcast]. `beta` are the non broadcast terms (degrees 3 to 5):
+/// this crate does not evaluate them from the ICD predicted coefficients table,
+/// they default to zero and may be provided by users who do.
+/// ```
+/// use cggtts::prelude::Epoch;
+/// use cggtts::ionosphere::{Bdgim, IonosphericModel};
+/// let model = Bdgim::from_broadcast(
+///     [16.25, -2.5, 1.375, 0.5, -4.75, 0.75, -0.25, 1.125, 0.0],
+/// );
+/// let t = Epoch::from_gregorian_utc(2023, 11, 10, 12, 0, 0, 0);
+/// let mdio = model.delay(t, 50.1, 14.4, 30.0, 180.0);
+/// assert!(mdio > 5.0E-9 && mdio < 100.0E-9);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Bdgim {
+    /// Broadcast coefficients [TECU]
+    pub alpha: [f64; 9],
+    /// Non broadcast coefficients [TECU]
+    pub beta: [f64; 17],
+}
+
+impl Bdgim {
+    /// Builds a BDGIM model from the broadcast coefficients only,
+    /// non broadcast terms are set to zero
+    pub fn from_broadcast(alpha: [f64; 9]) -> Self {
+        Self {
+            alpha,
+            beta: [0.0; 17],
+        }
+    }
+    /// Mean Earth radius [km]
+    const EARTH_RADIUS_KM: f64 = 6378.0;
+    /// Height of the ionospheric single layer [km]
+    const LAYER_HEIGHT_KM: f64 = 400.0;
+    /// Geomagnetic north pole latitude [°]
+    const POLE_LATITUDE: f64 = 80.27;
+    /// Geomagnetic north pole longitude [°]
+    const POLE_LONGITUDE: f64 = -72.58;
+    /// Returns the vertical electron content [TECU] at given geomagnetic
+    /// latitude and sun fixed longitude [rad]
+    fn vtec(&self, phi: f64, lambda: f64) -> f64 {
+        let x = phi.sin();
+        let broadcast = self
+            .alpha
+            .iter()
+            .zip(BROADCAST_TERMS)
+            .map(|(alpha, (n, m))| alpha * harmonic(n, m, x, lambda))
+            .sum::<f64>();
+        let predicted = self
+            .beta
+            .iter()
+            .zip(PREDICTED_TERMS)
+            .map(|(beta, (n, m))| beta * harmonic(n, m, x, lambda))
+            .sum::<f64>();
+        (broadcast + predicted).max(0.0)
+    }
+}
+
+/* (n)! */
+fn factorial(n: usize) -> f64 {
+    (1..=n).map(|k| k as f64).product()
+}
+
+/* associated Legendre function P_nm(x), without Condon-Shortley phase */
+fn legendre(n: usize, m: usize, x: f64) -> f64 {
+    // P_mm
+    let mut p_mm = 1.0;
+    let sqrt = (1.0 - x.powi(2)).max(0.0).sqrt();
+    for k in 1..=m {
+        p_mm *= (2 * k - 1) as f64 * sqrt;
+    }
+    if n == m {
+        return p_mm;
+    }
+    // upward recursion on n
+    let (mut previous, mut current) = (p_mm, x * (2 * m + 1) as f64 * p_mm);
+    for l in m + 2..=n {
+        let next =
+            ((2 * l - 1) as f64 * x * current - (l + m - 1) as f64 * previous) / (l - m) as f64;
+        previous = current;
+        current = next;
+    }
+    current
+}
+
+/* normalized spherical harmonic of degree n and order m */
+fn harmonic(n: usize, m: i32, x: f64, lambda: f64) -> f64 {
+    let order = m.unsigned_abs() as usize;
+    let delta = if order == 0 { 1.0 } else { 2.0 };
+    let norm = (factorial(n - order) * (2 * n + 1) as f64 * delta / factorial(n + order)).sqrt();
+    let p = norm * legendre(n, order, x);
+    if m >= 0 {
+        p * (order as f64 * lambda).cos()
+    } else {
+        p * (order as f64 * lambda).sin()
+    }
+}
+
+impl IonosphericModel for Bdgim {
+    fn reference_frequency(&self) -> f64 {
+        L1_FREQUENCY
+    }
+    fn delay(&self, t: Epoch, latitude: f64, longitude: f64, elevation: f64, azimuth: f64) -> f64 {
+        let (phi_u, lambda_u) = (latitude.to_radians(), longitude.to_radians());
+        let (e, a) = (elevation.to_radians(), azimuth.to_radians());
+        let ratio = Self::EARTH_RADIUS_KM / (Self::EARTH_RADIUS_KM + Self::LAYER_HEIGHT_KM);
+
+        // Earth central angle and ionospheric pierce point
+        let psi = PI / 2.0 - e - (ratio * e.cos()).asin();
+        let phi_g = (phi_u.sin() * psi.cos() + phi_u.cos() * psi.sin() * a.cos()).asin();
+        let lambda_g = lambda_u
+            + (psi.sin() * a.sin() * phi_u.cos()).atan2(psi.cos() - phi_u.sin() * phi_g.sin());
+
+        // geomagnetic coordinates
+        let (phi_p, lambda_p) = (
+            Self::POLE_LATITUDE.to_radians(),
+            Self::POLE_LONGITUDE.to_radians(),
+        );
+        let phi_m = (phi_p.sin() * phi_g.sin()
+            + phi_p.cos() * phi_g.cos() * (lambda_g - lambda_p).cos())
+        .asin();
+        let lambda_m = (phi_g.cos() * (lambda_g - lambda_p).sin() * phi_p.cos())
+            .atan2(phi_p.sin() * phi_m.sin() - phi_g.sin());
+
+        // sun fixed longitude
+        let sun = PI * (1.0 - 2.0 * seconds_of_day(t, TimeScale::BDT) / 86_400.0);
+        let sun_m = (sun - lambda_p)
+            .sin()
+            .atan2(phi_p.sin() * (sun - lambda_p).cos());
+        let lambda = lambda_m - sun_m;
+
+        let vtec = self.vtec(phi_m, lambda);
+        let mapping = 1.0 / (1.0 - (ratio * e.cos()).powi(2)).sqrt();
+        mapping * 40.28E16 * vtec / SPEED_OF_LIGHT / L1_FREQUENCY.powi(2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{harmonic, Bdgim};
+    use crate::ionosphere::IonosphericModel;
+    use crate::prelude::{Duration, Epoch, TimeScale};
+    use crate::SPEED_OF_LIGHT;
+
+    /* delay [s] of 1 TECU on B1C */
+    const TECU: f64 = 40.28E16 / SPEED_OF_LIGHT / (1575.42E6 * 1575.42E6);
+
+    #[test]
+    fn harmonics() {
+        // fully normalized: N_11·P_11(0) = √3, N_22·P_22(0) = √15/2
+        assert!((harmonic(1, 1, 0.0, 0.0) - 3.0_f64.sqrt()).abs() < 1.0E-12);
+        assert!((harmonic(2, 2, 0.0, 0.0) - 15.0_f64.sqrt() / 2.0).abs() < 1.0E-12);
+        assert!(
+            (harmonic(2, -2, 0.0, std::f64::consts::PI / 4.0) - 15.0_f64.sqrt() / 2.0).abs()
+                < 1.0E-12
+        );
+        // P_20(1) = 1
+        assert!((harmonic(2, 0, 1.0, 0.0) - 5.0_f64.sqrt()).abs() < 1.0E-12);
+        // P_50(x) = (63x⁵ - 70x³ + 15x)/8
+        let x = 0.3_f64;
+        let p50 = (63.0 * x.powi(5) - 70.0 * x.powi(3) + 15.0 * x) / 8.0;
+        assert!((harmonic(5, 0, x, 0.0) - 11.0_f64.sqrt() * p50).abs() < 1.0E-12);
+    }
+    #[test]
+    fn bdgim() {
+        let t = Epoch::from_gregorian(2023, 11, 10, 6, 0, 0, 0, TimeScale::BDT);
+
+        // constant term, anywhere at zenith
+        let mut model = Bdgim::default();
+        model.alpha[0] = 20.0;
+        for (latitude, longitude) in [(0.0, 0.0), (43.6, 1.44), (-33.9, 151.2)] {
+            let delay = model.delay(t, latitude, longitude, 90.0, 0.0);
+            assert!((delay - 20.0 * TECU).abs() < 1.0E-15, "{}", delay);
+        }
+
+        // first zonal term, below the geomagnetic pole
+        let mut model = Bdgim::default();
+        model.alpha[1] = 10.0;
+        let delay = model.delay(t, 80.27, -72.58, 90.0, 0.0);
+        assert!(
+            (delay - 3.0_f64.sqrt() * 10.0 * TECU).abs() < 1.0E-12,
+            "{}",
+            delay
+        );
+
+        // obliquity
+        let mut model = Bdgim::default();
+        model.alpha[0] = 20.0;
+        let ratio = 6378.0 / (6378.0 + 400.0) * 10.0_f64.to_radians().cos();
+        let slant = model.delay(t, 50.1, 14.4, 10.0, 0.0);
+        assert!((slant - 20.0 * TECU / (1.0 - ratio.powi(2)).sqrt()).abs() < 1.0E-15);
+
+        // daily periodicity
+        let model = Bdgim {
+            alpha: [16.25, -2.5, 1.375, 0.5, -4.75, 0.75, -0.25, 1.125, 0.0],
+            beta: [0.1; 17],
+        };
+        let delay = model.delay(t, 50.1, 14.4, 30.0, 180.0);
+        let next = model.delay(t + Duration::from_hours(24.0), 50.1, 14.4, 30.0, 180.0);
+        assert!((delay - next).abs() < 1.0E-15);
+
+        // negative content is clamped
+        let model = Bdgim::from_broadcast([-20.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(model.beta, [0.0; 17]);
+        assert_eq!(model.delay(t, 50.1, 14.4, 30.0, 180.0), 0.0);
+    }
+    /*
+     * BDGIM reference vectors (BDS-SIS-ICD examples, or a reference implementation).
+     * They are not distributed with this crate: set BDGIM_VALIDATION to a file
+     * holding one vector per line, then run with --ignored:
+     * alpha1..alpha9[TECU] year month day UT[h] lat[°] lon[°] elevation[°] azimuth[°] delay[m]
+     * The delay is expressed on B1C, predicted terms are not used.
+     */
+    #[test]
+    #[ignore]
+    fn reference_vectors() {
+        let path = std::env::var("BDGIM_VALIDATION")
+            .expect("BDGIM_VALIDATION should point to the reference vectors");
+        let vectors = std::fs::read_to_string(path).unwrap();
+        let mut nb = 0;
+        for line in vectors.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let v: Vec<f64> = line
+                .split_whitespace()
+                .map(|item| item.parse::<f64>().unwrap())
+                .collect();
+            assert_eq!(v.len(), 18, "invalid vector \"{}\"", line);
+            let mut alpha = [0.0; 9];
+            alpha.copy_from_slice(&v[..9]);
+            let model = Bdgim::from_broadcast(alpha);
+            let t = Epoch::from_gregorian(
+                v[9] as i32,
+                v[10] as u8,
+                v[11] as u8,
+                0,
+                0,
+                0,
+                0,
+                TimeScale::BDT,
+            ) + Duration::from_hours(v[12]);
+            let delay = model.delay(t, v[13], v[14], v[15], v[16]) * SPEED_OF_LIGHT;
+            assert!((delay - v[17]).abs() < 1.0E-2, "\"{}\": {} m", line, delay);
+            nb += 1;
+        }
+        assert!(nb > 0);
+    }
+}
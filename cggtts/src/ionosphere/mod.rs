@@ -0,0 +1,294 @@
+//! Ionospheric delay models, used to determine MDIO in single frequency contexts.
+//!
+//! Broadcast models are implemented for GPS / QZSS ([Klobuchar]), BeiDou-2 ([BdsKlobuchar]),
+//! Galileo ([NeQuickG]) and BeiDou-3 ([Bdgim]). NeQuick-G also relies on the
+//! CCIR and MODIP maps, that are not distributed with this crate and need to be provided.
+//! BDGIM is evaluated from its broadcast coefficients, the optional predicted
+//! terms are left to the user.
+//! Implement [IonosphericModel] to plug in other models.
+//!
+//! The modeled delay, scaled to the carrier in use, is what `processing::Params::mdio`
+//! expects. SMDI either results from the track fit or from [IonosphericModel::delay_and_slope].
+use crate::prelude::{Duration, Epoch, TimeScale};
+use std::f64::consts::PI;
+
+mod bdgim;
+pub use bdgim::Bdgim;
+
+mod nequick;
+pub use nequick::{NeQuickError, NeQuickG, NeQuickMaps};
+
+/// Time step [s] used in the numerical derivation of the delay
+const SLOPE_STEP_SECONDS: f64 = 30.0;
+
+/// GPS L1 frequency [Hz]
+const L1_FREQUENCY: f64 = 1575.42E6;
+
+/// BeiDou B1I frequency [Hz]
+const B1I_FREQUENCY: f64 = 1561.098E6;
+
+/// Ionospheric delay model
+pub trait IonosphericModel {
+    /// Returns the carrier frequency [Hz] delays are expressed on
+    fn reference_frequency(&self) -> f64;
+    /// Returns the slant ionospheric delay [s] on the reference frequency,
+    /// at Epoch `t`, for given receiver geodetic latitude and longitude [°],
+    /// and SV elevation and azimuth [°]
+    fn delay(&self, t: Epoch, latitude: f64, longitude: f64, elevation: f64, azimuth: f64) -> f64;
+    /// Returns the slant ionospheric delay [s] scaled to given carrier frequency [Hz],
+    /// see [Self::delay]
+    fn delay_on(
+        &self,
+        t: Epoch,
+        latitude: f64,
+        longitude: f64,
+        elevation: f64,
+        azimuth: f64,
+        frequency: f64,
+    ) -> f64 {
+        let ratio = self.reference_frequency() / frequency;
+        self.delay(t, latitude, longitude, elevation, azimuth) * ratio.powi(2)
+    }
+    /// Returns the slant ionospheric delay [s] on the reference frequency (MDIO)
+    /// and its slope [s/s] (SMDI), at Epoch `t`, for given receiver geodetic latitude
+    /// and longitude [°], SV elevation and azimuth [°] and their rates [°/s]
+    #[allow(clippy::too_many_arguments)]
+    fn delay_and_slope(
+        &self,
+        t: Epoch,
+        latitude: f64,
+        longitude: f64,
+        elevation: f64,
+        azimuth: f64,
+        elevation_rate: f64,
+        azimuth_rate: f64,
+    ) -> (f64, f64) {
+        let half_step = SLOPE_STEP_SECONDS / 2.0;
+        let delay = |dt: f64| {
+            self.delay(
+                t + Duration::from_seconds(dt),
+                latitude,
+                longitude,
+                elevation + elevation_rate * dt,
+                azimuth + azimuth_rate * dt,
+            )
+        };
+        (
+            delay(0.0),
+            (delay(half_step) - delay(-half_step)) / SLOPE_STEP_SECONDS,
+        )
+    }
+}
+
+/* seconds within the day of given timescale */
+fn seconds_of_day(t: Epoch, ts: TimeScale) -> f64 {
+    let (_, nanos) = t.to_time_scale(ts).to_time_of_week();
+    (nanos as f64 * 1.0E-9).rem_euclid(86_400.0)
+}
+
+/// GPS (and QZSS) broadcast Klobuchar model, IS-GPS-200 §20.3.3.5.2.5.
+/// Delays are expressed on L1.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Klobuchar {
+    /// Alpha coefficients [s, s/semicircle, s/semicircle², s/semicircle³]
+    pub alpha: [f64; 4],
+    /// Beta coefficients [s, s/semicircle, s/semicircle², s/semicircle³]
+    pub beta: [f64; 4],
+}
+
+impl IonosphericModel for Klobuchar {
+    fn reference_frequency(&self) -> f64 {
+        L1_FREQUENCY
+    }
+    /// ```
+    /// use cggtts::prelude::Epoch;
+    /// use cggtts::ionosphere::{IonosphericModel, Klobuchar};
+    /// let model = Klobuchar {
+    ///     alpha: [3.82E-8, 1.49E-8, -1.79E-7, 0.0],
+    ///     beta: [1.43E5, 0.0, -3.28E5, 1.13E5],
+    /// };
+    /// let t = Epoch::from_gregorian_utc(2023, 11, 10, 12, 0, 0, 0);
+    /// let mdio = model.delay(t, 50.1, 14.4, 30.0, 180.0);
+    /// assert!(mdio > 5.0E-9 && mdio < 100.0E-9);
+    /// ```
+    fn delay(&self, t: Epoch, latitude: f64, longitude: f64, elevation: f64, azimuth: f64) -> f64 {
+        // semicircles
+        let (phi_u, lambda_u) = (latitude / 180.0, longitude / 180.0);
+        let e = elevation / 180.0;
+        let a = azimuth.to_radians();
+
+        // Earth central angle
+        let psi = 0.0137 / (e + 0.11) - 0.022;
+
+        // ionospheric pierce point
+        let phi_i = (phi_u + psi * a.cos()).clamp(-0.416, 0.416);
+        let lambda_i = lambda_u + psi * a.sin() / (phi_i * PI).cos();
+
+        // geomagnetic latitude
+        let phi_m = phi_i + 0.064 * ((lambda_i - 1.617) * PI).cos();
+
+        // local time
+        let t = (4.32E4 * lambda_i + seconds_of_day(t, TimeScale::GPST)).rem_euclid(86_400.0);
+
+        let slant = 1.0 + 16.0 * (0.53 - e).powi(3);
+
+        let mut amplitude = 0.0;
+        let mut period = 0.0;
+        for n in 0..4 {
+            amplitude += self.alpha[n] * phi_m.powi(n as i32);
+            period += self.beta[n] * phi_m.powi(n as i32);
+        }
+        let amplitude = amplitude.max(0.0);
+        let period = period.max(72_000.0);
+
+        let x = 2.0 * PI * (t - 50_400.0) / period;
+        if x.abs() < 1.57 {
+            slant * (5.0E-9 + amplitude * (1.0 - x.powi(2) / 2.0 + x.powi(4) / 24.0))
+        } else {
+            slant * 5.0E-9
+        }
+    }
+}
+
+/// BeiDou-2 broadcast Klobuchar model, BDS-SIS-ICD B1I §5.2.4.7.
+/// Delays are expressed on B1I.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BdsKlobuchar {
+    /// Alpha coefficients [s, s/π, s/π², s/π³]
+    pub alpha: [f64; 4],
+    /// Beta coefficients [s, s/π, s/π², s/π³]
+    pub beta: [f64; 4],
+}
+
+impl BdsKlobuchar {
+    /// Mean Earth radius [km]
+    const EARTH_RADIUS_KM: f64 = 6378.0;
+    /// Height of the ionospheric single layer [km]
+    const LAYER_HEIGHT_KM: f64 = 375.0;
+}
+
+impl IonosphericModel for BdsKlobuchar {
+    fn reference_frequency(&self) -> f64 {
+        B1I_FREQUENCY
+    }
+    fn delay(&self, t: Epoch, latitude: f64, longitude: f64, elevation: f64, azimuth: f64) -> f64 {
+        let (phi_u, lambda_u) = (latitude.to_radians(), longitude.to_radians());
+        let (e, a) = (elevation.to_radians(), azimuth.to_radians());
+        let ratio = Self::EARTH_RADIUS_KM / (Self::EARTH_RADIUS_KM + Self::LAYER_HEIGHT_KM);
+
+        // Earth central angle and ionospheric pierce point
+        let psi = PI / 2.0 - e - (ratio * e.cos()).asin();
+        let phi_m = (phi_u.sin() * psi.cos() + phi_u.cos() * psi.sin() * a.cos()).asin();
+        let lambda_m = lambda_u + (psi.sin() * a.sin() / phi_m.cos()).asin();
+
+        // local time
+        let t = (seconds_of_day(t, TimeScale::BDT) + lambda_m * 43_200.0 / PI).rem_euclid(86_400.0);
+
+        let phi = (phi_m / PI).abs();
+        let mut amplitude = 0.0;
+        let mut period = 0.0;
+        for n in 0..4 {
+            amplitude += self.alpha[n] * phi.powi(n as i32);
+            period += self.beta[n] * phi.powi(n as i32);
+        }
+        let amplitude = amplitude.max(0.0);
+        let period = period.clamp(72_000.0, 172_800.0);
+
+        let zenith = if (t - 50_400.0).abs() < period / 4.0 {
+            5.0E-9 + amplitude * (2.0 * PI * (t - 50_400.0) / period).cos()
+        } else {
+            5.0E-9
+        };
+        zenith / (1.0 - (ratio * e.cos()).powi(2)).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BdsKlobuchar, IonosphericModel, Klobuchar, L1_FREQUENCY};
+    use crate::prelude::{Duration, Epoch, TimeScale};
+    const ALPHA: [f64; 4] = [3.82E-8, 1.49E-8, -1.79E-7, 0.0];
+    const BETA: [f64; 4] = [1.43E5, 0.0, -3.28E5, 1.13E5];
+    #[test]
+    fn klobuchar() {
+        let model = Klobuchar {
+            alpha: ALPHA,
+            beta: BETA,
+        };
+        let midnight = Epoch::from_gregorian(2023, 11, 10, 0, 0, 0, 0, TimeScale::GPST);
+
+        // night time: constant 5 ns vertical delay
+        let night = model.delay(midnight, 0.0, 0.0, 90.0, 0.0);
+        let slant = 1.0 + 16.0 * (0.53 - 0.5_f64).powi(3);
+        assert!((night - 5.0E-9 * slant).abs() < 1.0E-15);
+
+        // maximal delay at 14:00 local time
+        let t = midnight + Duration::from_hours(14.0);
+        let noon = model.delay(t, 0.0, 0.0, 90.0, 0.0);
+        assert!(noon > night);
+        for hours in [10.0, 12.0, 16.0, 18.0] {
+            let delay = model.delay(midnight + Duration::from_hours(hours), 0.0, 0.0, 90.0, 0.0);
+            assert!(delay < noon);
+            assert!(delay >= night);
+        }
+
+        // lower elevations have longer paths
+        let low = model.delay(t, 0.0, 0.0, 10.0, 0.0);
+        assert!(low > 2.5 * noon);
+
+        // local time follows longitude: 14:00 in 90°E is 08:00 GPST
+        let east = model.delay(midnight + Duration::from_hours(8.0), 0.0, 90.0, 90.0, 0.0);
+        for hours in [6.0, 10.0] {
+            let delay = model.delay(midnight + Duration::from_hours(hours), 0.0, 90.0, 90.0, 0.0);
+            assert!(delay < east);
+        }
+
+        // frequency scaling
+        let l2 = model.delay_on(t, 0.0, 0.0, 90.0, 0.0, 1227.60E6);
+        assert!((l2 - noon * (L1_FREQUENCY / 1227.60E6).powi(2)).abs() < 1.0E-15);
+        assert_eq!(model.delay_on(t, 0.0, 0.0, 90.0, 0.0, L1_FREQUENCY), noon);
+    }
+    #[test]
+    fn bds_klobuchar() {
+        let model = BdsKlobuchar {
+            alpha: ALPHA,
+            beta: BETA,
+        };
+        let midnight = Epoch::from_gregorian(2023, 11, 10, 0, 0, 0, 0, TimeScale::BDT);
+
+        // night time: 5 ns at zenith
+        let night = model.delay(midnight, 0.0, 0.0, 90.0, 0.0);
+        assert!((night - 5.0E-9).abs() < 1.0E-15);
+
+        // peak at 14:00 local time
+        let t = midnight + Duration::from_hours(14.0);
+        let peak = model.delay(t, 0.0, 0.0, 90.0, 0.0);
+        assert!((peak - 5.0E-9 - ALPHA[0]).abs() < 1.0E-15);
+
+        // obliquity factor
+        let low = model.delay(t, 0.0, 0.0, 10.0, 0.0);
+        assert!(low > 2.0 * peak);
+    }
+    #[test]
+    fn delay_and_slope() {
+        let model = Klobuchar {
+            alpha: ALPHA,
+            beta: BETA,
+        };
+        let t = Epoch::from_gregorian(2023, 11, 10, 10, 0, 0, 0, TimeScale::GPST);
+
+        // static SV: morning rise of the delay
+        let (mdio, smdi) = model.delay_and_slope(t, 0.0, 0.0, 45.0, 90.0, 0.0, 0.0);
+        assert_eq!(mdio, model.delay(t, 0.0, 0.0, 45.0, 90.0));
+        assert!(smdi > 0.0);
+        let later = model.delay(t + Duration::from_seconds(10.0), 0.0, 0.0, 45.0, 90.0);
+        assert!((mdio + 10.0 * smdi - later).abs() < 1.0E-13);
+
+        // setting SV at night: the obliquity factor drives the slope
+        let midnight = t - Duration::from_hours(10.0);
+        let (mdio, smdi) = model.delay_and_slope(midnight, 0.0, 0.0, 30.0, 0.0, -0.01, 0.0);
+        assert!(smdi > 0.0);
+        let later = model.delay(midnight + Duration::from_seconds(10.0), 0.0, 0.0, 29.9, 0.0);
+        assert!((mdio + 10.0 * smdi - later).abs() < 1.0E-13);
+    }
+}
@@ -0,0 +1,863 @@
+//! Galileo NeQuick-G ionospheric model, as defined in "Ionospheric Correction Algorithm
+//! for Galileo Single Frequency Users" (European GNSS Open Service, issue 1.2)
+use super::{IonosphericModel, L1_FREQUENCY};
+use crate::prelude::Epoch;
+use crate::SPEED_OF_LIGHT;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Number of foF2 coefficients (spatial, time) of a CCIR map
+const F2_SIZE: (usize, usize) = (76, 13);
+
+/// Number of M(3000)F2 coefficients (spatial, time) of a CCIR map
+const FM3_SIZE: (usize, usize) = (49, 9);
+
+/// Number of values of a CCIR file: foF2 then M(3000)F2 coefficients,
+/// for solar activities of 0 and 100 (12 month smoothed sunspot number)
+const CCIR_SIZE: usize = 2 * (F2_SIZE.0 * F2_SIZE.1 + FM3_SIZE.0 * FM3_SIZE.1);
+
+/// Number of rows (latitudes) and columns (longitudes) of the MODIP grid
+const MODIP_SIZE: usize = 39;
+
+/// Number of sin(MODIP) powers per longitude order, in the foF2 expansion
+const F2_ORDERS: [usize; 9] = [12, 12, 9, 5, 2, 1, 1, 1, 1];
+
+/// Number of sin(MODIP) powers per longitude order, in the M(3000)F2 expansion
+const FM3_ORDERS: [usize; 7] = [7, 8, 6, 3, 2, 1, 1];
+
+/// Mean Earth radius [km]
+const EARTH_RADIUS_KM: f64 = 6371.2;
+
+/// Nominal Galileo SV height [km]: the ray is integrated up to this height
+const SV_HEIGHT_KM: f64 = 23_222.0;
+
+/// Zenith angle [°] at which the effective zenith angle starts to differ
+const ZENITH_ANGLE_0: f64 = 86.232_927_962_116_15;
+
+/// Earth rotation, in local time [rad/h]
+const PI_12: f64 = std::f64::consts::PI / 12.0;
+
+/// Maximal recursion depth of the ray integration
+const MAX_INTEGRATION_DEPTH: u32 = 50;
+
+/// Gauss-Kronrod G7-K15 abscissae (Kronrod nodes, Gauss nodes are the odd ones)
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+
+/// Gauss-Kronrod G7-K15 Kronrod weights
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+
+/// Gauss-Kronrod G7-K15 Gauss weights
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// NeQuick-G errors
+#[derive(Debug, Error)]
+pub enum NeQuickError {
+    #[error("failed to read file")]
+    IoError(#[from] std::io::Error),
+    #[error("failed to parse number \"{0}\"")]
+    NumberParsing(String),
+    #[error("invalid CCIR map for month {0}")]
+    InvalidCcirMap(usize),
+    #[error("invalid MODIP grid")]
+    InvalidModipGrid,
+}
+
+/// Non broadcast maps NeQuick-G relies on: one CCIR map per month
+/// (foF2 and M(3000)F2 coefficients) and the modified dip latitude (MODIP) grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeQuickMaps {
+    /* CCIR coefficients, January first */
+    ccir: Vec<Vec<f64>>,
+    /* MODIP [°], row major */
+    modip: Vec<f64>,
+}
+
+/* whitespace separated numbers */
+fn parse_numbers(content: &str) -> Result<Vec<f64>, NeQuickError> {
+    content
+        .split_whitespace()
+        .map(|item| {
+            item.replace(['D', 'd'], "E")
+                .parse::<f64>()
+                .map_err(|_| NeQuickError::NumberParsing(item.to_string()))
+        })
+        .collect()
+}
+
+/* third order interpolation of 4 regularly spaced values, at x in [0, 1]
+ * between the second and third value */
+fn interpolate(z: [f64; 4], x: f64) -> f64 {
+    if x.abs() < 1.0E-10 {
+        return z[1];
+    }
+    let delta = 2.0 * x - 1.0;
+    let (g1, g2) = (z[2] + z[1], z[2] - z[1]);
+    let (g3, g4) = (z[3] + z[0], (z[3] - z[0]) / 3.0);
+    let a0 = 9.0 * g1 - g3;
+    let a1 = 9.0 * g2 - g4;
+    let a2 = g3 - g1;
+    let a3 = g4 - g2;
+    (a0 + delta * (a1 + delta * (a2 + delta * a3))) / 16.0
+}
+
+/* exponential, clipped to avoid overflows */
+fn clip_exp(x: f64) -> f64 {
+    if x > 80.0 {
+        5.5406E34
+    } else if x < -80.0 {
+        1.8049E-35
+    } else {
+        x.exp()
+    }
+}
+
+/* smooth transition from f2 (x < 0) to f1 (x > 0) */
+fn join(f1: f64, f2: f64, alpha: f64, x: f64) -> f64 {
+    let ee = clip_exp(alpha * x);
+    (f1 * ee + f2) / (ee + 1.0)
+}
+
+/* Epstein function of peak amplitude x, thickness y, peak height z, at height w */
+fn epstein(x: f64, y: f64, z: f64, w: f64) -> f64 {
+    let ee = clip_exp((w - z) / y);
+    x * ee / (1.0 + ee).powi(2)
+}
+
+/* Gauss-Kronrod G7-K15 adaptive integration of f over [a, b] */
+fn integrate<F: Fn(f64) -> f64>(f: &F, a: f64, b: f64, tolerance: f64, depth: u32) -> f64 {
+    let (center, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    let (mut kronrod, mut gauss) = (0.0, 0.0);
+    for (i, (x, w)) in KRONROD_NODES.iter().zip(KRONROD_WEIGHTS.iter()).enumerate() {
+        let values = if i == KRONROD_NODES.len() - 1 {
+            [f(center), 0.0]
+        } else {
+            [f(center - half * x), f(center + half * x)]
+        };
+        kronrod += w * (values[0] + values[1]);
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * (values[0] + values[1]);
+        }
+    }
+    let (kronrod, gauss) = (kronrod * half, gauss * half);
+    if (kronrod - gauss).abs() <= tolerance * kronrod.abs() || depth >= MAX_INTEGRATION_DEPTH {
+        kronrod
+    } else {
+        integrate(f, a, center, tolerance, depth + 1)
+            + integrate(f, center, b, tolerance, depth + 1)
+    }
+}
+
+impl NeQuickMaps {
+    /// Builds maps from the CCIR coefficients of each month (January first)
+    /// and the MODIP grid [°].
+    /// Each CCIR map contains 2858 values: the foF2 coefficients [2][76][13]
+    /// followed by the M(3000)F2 coefficients [2][49][9], for 12 month smoothed
+    /// sunspot numbers of 0 and 100, as in the standard `ccir11.asc` to `ccir22.asc` files.
+    /// The MODIP grid is a row major 39x39 grid: rows are latitudes from -95° to 95°
+    /// by 5° steps and columns are longitudes from -190° to 190° by 10° steps,
+    /// as in the standard `modipNeQG_wrapped.asc` file.
+    pub fn new(ccir: Vec<Vec<f64>>, modip: Vec<f64>) -> Result<Self, NeQuickError> {
+        if ccir.len() != 12 {
+            return Err(NeQuickError::InvalidCcirMap(ccir.len() + 1));
+        }
+        if let Some(month) = ccir.iter().position(|map| map.len() != CCIR_SIZE) {
+            return Err(NeQuickError::InvalidCcirMap(month + 1));
+        }
+        if modip.len() != MODIP_SIZE * MODIP_SIZE {
+            return Err(NeQuickError::InvalidModipGrid);
+        }
+        Ok(Self { ccir, modip })
+    }
+    /// Loads the standard `ccir11.asc` (January) to `ccir22.asc` (December)
+    /// and `modipNeQG_wrapped.asc` files, from given directory. See [Self::new].
+    pub fn from_directory<P: AsRef<Path>>(path: P) -> Result<Self, NeQuickError> {
+        let read = |name: &str| -> Result<Vec<f64>, NeQuickError> {
+            let content = std::fs::read_to_string(path.as_ref().join(name))?;
+            parse_numbers(&content)
+        };
+        let mut ccir = Vec::with_capacity(12);
+        for month in 1..=12 {
+            ccir.push(read(&format!("ccir{}.asc", month + 10))?);
+        }
+        Self::new(ccir, read("modipNeQG_wrapped.asc")?)
+    }
+    /// Returns the modified dip latitude [°] at given geographic latitude and longitude [°]
+    pub fn modip(&self, latitude: f64, longitude: f64) -> f64 {
+        if latitude >= 90.0 {
+            return 90.0;
+        }
+        if latitude <= -90.0 {
+            return -90.0;
+        }
+        let longitude = (longitude + 180.0).rem_euclid(360.0) - 180.0;
+        let (row, col) = ((latitude + 95.0) / 5.0, (longitude + 190.0) / 10.0);
+        let (i, j) = (row.floor() as usize, col.floor() as usize);
+        let grid = |i: usize, j: usize| self.modip[i * MODIP_SIZE + j];
+        let along_longitude = |i: usize| {
+            interpolate(
+                [grid(i, j - 1), grid(i, j), grid(i, j + 1), grid(i, j + 2)],
+                col - j as f64,
+            )
+        };
+        interpolate(
+            [
+                along_longitude(i - 1),
+                along_longitude(i),
+                along_longitude(i + 1),
+                along_longitude(i + 2),
+            ],
+            row - i as f64,
+        )
+    }
+}
+
+/// Galileo NeQuick-G broadcast model. Delays are expressed on E1.
+/// The ray is integrated from the receiver up to the nominal Galileo SV height.
+/// ```no_run
+/// use std::sync::Arc;
+/// use cggtts::prelude::Epoch;
+/// use cggtts::ionosphere::{IonosphericModel, NeQuickG, NeQuickMaps};
+/// // standard CCIR and MODIP files
+/// let maps = NeQuickMaps::from_directory("nequick")
+///     .unwrap();
+/// let model = NeQuickG::new([236.831, 0.0, 0.0015], Arc::new(maps))
+///     .with_height(78.1);
+/// let t = Epoch::from_gregorian_utc(2023, 11, 10, 12, 0, 0, 0);
+/// let mdio = model.delay(t, 50.1, 14.4, 30.0, 180.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeQuickG {
+    /// Broadcast effective ionisation level coefficients:
+    /// ai0 [sfu], ai1 [sfu/°] and ai2 [sfu/°²]
+    pub ai: [f64; 3],
+    /// Receiver height [m]
+    pub height: f64,
+    /// CCIR and MODIP maps
+    pub maps: Arc<NeQuickMaps>,
+}
+
+/* model state that only depends on time and solar activity */
+struct Context<'a> {
+    maps: &'a NeQuickMaps,
+    /* month [1:12] */
+    month: usize,
+    /* universal time [h] */
+    ut: f64,
+    /* effective ionisation level [sfu] */
+    az: f64,
+    /* effective sunspot number */
+    az_r: f64,
+    /* sine and cosine of the solar declination */
+    declination: (f64, f64),
+    /* foF2 and M(3000)F2 spatial coefficients at this time */
+    cf2: Vec<f64>,
+    cm3: Vec<f64>,
+}
+
+/* electron density profile parameters at a given location */
+struct Profile {
+    hme: f64,
+    hmf1: f64,
+    hmf2: f64,
+    nmf2: f64,
+    b2bot: f64,
+    b1top: f64,
+    b1bot: f64,
+    betop: f64,
+    amplitudes: [f64; 3],
+    /* topside thickness [km] */
+    h0: f64,
+}
+
+/* Fourier time series of given coefficients, at time angle t [rad] */
+fn time_series(coefs: &[f64], t: f64) -> f64 {
+    let mut value = coefs[0];
+    for k in 1..=coefs.len() / 2 {
+        let (sin, cos) = (k as f64 * t).sin_cos();
+        value += coefs[2 * k - 1] * sin + coefs[2 * k] * cos;
+    }
+    value
+}
+
+/* spatial expansion of given coefficients, from sin(MODIP) powers,
+ * cos(latitude) powers and longitude harmonics */
+fn spatial_series(
+    coefs: &[f64],
+    orders: &[usize],
+    latitude: f64,
+    longitude: f64,
+    modip: f64,
+) -> f64 {
+    let (sin_mu, cos_phi) = (modip.to_radians().sin(), latitude.to_radians().cos());
+    let powers = |x: f64, n: usize| -> Vec<f64> {
+        (0..n)
+            .scan(1.0, |p, _| {
+                let value = *p;
+                *p *= x;
+                Some(value)
+            })
+            .collect()
+    };
+    let m = powers(sin_mu, orders[0]);
+    let mut value: f64 = (0..orders[0]).map(|k| coefs[k] * m[k]).sum();
+    let mut index = orders[0];
+    for (n, order) in orders.iter().enumerate().skip(1) {
+        let (sin, cos) = (n as f64 * longitude.to_radians()).sin_cos();
+        let p = cos_phi.powi(n as i32);
+        for mk in m.iter().take(*order) {
+            value += (coefs[index] * cos + coefs[index + 1] * sin) * mk * p;
+            index += 2;
+        }
+    }
+    value
+}
+
+impl<'a> Context<'a> {
+    fn new(model: &'a NeQuickG, t: Epoch, latitude: f64, longitude: f64) -> Self {
+        let (_, month, _, hours, minutes, seconds, nanos) = t.to_gregorian_utc();
+        let ut = hours as f64
+            + minutes as f64 / 60.0
+            + (seconds as f64 + nanos as f64 * 1.0E-9) / 3600.0;
+        let month = month as usize;
+
+        // effective ionisation level, at the receiver location
+        let mu = model.maps.modip(latitude, longitude);
+        let [ai0, ai1, ai2] = model.ai;
+        let az = if ai0 == 0.0 && ai1 == 0.0 && ai2 == 0.0 {
+            63.7
+        } else {
+            (ai0 + ai1 * mu + ai2 * mu.powi(2)).clamp(0.0, 400.0)
+        };
+        let az_r = (167_273.0 + (az - 63.7) * 1_123.6).sqrt() - 408.99;
+
+        // solar declination, mid month
+        let doy = 30.5 * month as f64 - 15.0;
+        let t_days = doy + (18.0 - ut) / 24.0;
+        let am = (0.9856 * t_days - 3.289).to_radians();
+        let al = am + (1.916 * am.sin() + 0.020 * (2.0 * am).sin() + 282.634).to_radians();
+        let sin_dec = 0.39782 * al.sin();
+        let declination = (sin_dec, (1.0 - sin_dec.powi(2)).sqrt());
+
+        // CCIR coefficients, interpolated in solar activity, then in time
+        let ccir = &model.maps.ccir[month - 1];
+        let time_angle = (15.0 * ut - 180.0).to_radians();
+        let (f2, fm3) = ccir.split_at(2 * F2_SIZE.0 * F2_SIZE.1);
+        let coefficients = |values: &[f64], (n, m): (usize, usize)| -> Vec<f64> {
+            (0..n)
+                .map(|i| {
+                    let series: Vec<f64> = (0..m)
+                        .map(|j| {
+                            values[i * m + j] * (1.0 - az_r / 100.0)
+                                + values[(n + i) * m + j] * az_r / 100.0
+                        })
+                        .collect();
+                    time_series(&series, time_angle)
+                })
+                .collect()
+        };
+
+        Self {
+            maps: &model.maps,
+            month,
+            ut,
+            az,
+            az_r,
+            declination,
+            cf2: coefficients(f2, F2_SIZE),
+            cm3: coefficients(fm3, FM3_SIZE),
+        }
+    }
+    /* electron density profile parameters at given location [°] */
+    fn profile(&self, latitude: f64, longitude: f64) -> Profile {
+        let phi = latitude.to_radians();
+        let modip = self.maps.modip(latitude, longitude);
+
+        // effective solar zenith angle
+        let local_time = self.ut + longitude / 15.0;
+        let (sin_dec, cos_dec) = self.declination;
+        let cos_chi =
+            phi.sin() * sin_dec + phi.cos() * cos_dec * (PI_12 * (12.0 - local_time)).cos();
+        let chi = (1.0 - cos_chi.powi(2))
+            .max(0.0)
+            .sqrt()
+            .atan2(cos_chi)
+            .to_degrees();
+        let chi_eff = join(
+            90.0 - 0.24 * clip_exp(20.0 - 0.2 * chi),
+            chi,
+            12.0,
+            chi - ZENITH_ANGLE_0,
+        );
+
+        // E layer
+        let season = match self.month {
+            1 | 2 | 11 | 12 => -1.0,
+            3 | 4 | 9 | 10 => 0.0,
+            _ => 1.0,
+        };
+        let ee = clip_exp(0.3 * latitude);
+        let seasp = season * (ee - 1.0) / (ee + 1.0);
+        let fo_e = ((1.112 - 0.019 * seasp).powi(2)
+            * self.az.sqrt()
+            * chi_eff.to_radians().cos().max(0.0).powf(0.6)
+            + 0.49)
+            .sqrt();
+        let nme = 0.124 * fo_e.powi(2);
+
+        // F2 layer
+        let fo_f2 = spatial_series(&self.cf2, &F2_ORDERS, latitude, longitude, modip);
+        let m3000 = spatial_series(&self.cm3, &FM3_ORDERS, latitude, longitude, modip);
+        let nmf2 = 0.124 * fo_f2.powi(2);
+
+        // F1 layer
+        let fo_f1 = join(1.4 * fo_e, 0.0, 1000.0, fo_e - 2.0);
+        let fo_f1 = join(fo_f1, 0.85 * fo_f2, 60.0, 0.85 * fo_f2 - fo_f1);
+        let fo_f1 = if fo_f1 < 1.0E-6 { 0.0 } else { fo_f1 };
+        let nmf1 = if fo_f1 <= 0.0 && fo_e > 2.0 {
+            0.124 * (fo_e + 0.5).powi(2)
+        } else {
+            0.124 * fo_f1.powi(2)
+        };
+
+        // peak heights [km]
+        let mf = m3000 * ((0.0196 * m3000.powi(2) + 1.0) / (1.2967 * m3000.powi(2) - 1.0)).sqrt();
+        let ratio = fo_f2 / fo_e;
+        let rho = join(ratio, 1.75, 20.0, ratio - 1.75);
+        let delta_m = 0.253 / (rho - 1.215) - 0.012;
+        let hmf2 = 1490.0 * mf / (m3000 + delta_m) - 176.0;
+        let hme = 120.0;
+        let hmf1 = (hmf2 + hme) / 2.0;
+
+        // thickness parameters [km]
+        let b2bot = 0.385 * nmf2
+            / (0.01 * clip_exp(-3.467 + 0.857 * fo_f2.powi(2).ln() + 2.02 * m3000.ln()));
+        let b1top = 0.3 * (hmf2 - hmf1);
+        let b1bot = 0.5 * (hmf1 - hme);
+        let betop = b1bot.max(7.0);
+
+        // Epstein amplitudes
+        let a1 = 4.0 * nmf2;
+        let (a2, a3) = if fo_f1 < 0.5 {
+            (0.0, 4.0 * (nme - epstein(a1, b2bot, hmf2, hme)))
+        } else {
+            let (mut a2, mut a3) = (0.0, 4.0 * nme);
+            for _ in 0..5 {
+                a2 = 4.0 * (nmf1 - epstein(a1, b2bot, hmf2, hmf1) - epstein(a3, betop, hme, hmf1));
+                a2 = join(a2, 0.8 * nmf1, 1.0, a2 - 0.8 * nmf1);
+                a3 = 4.0 * (nme - epstein(a2, b1bot, hmf1, hme) - epstein(a1, b2bot, hmf2, hme));
+            }
+            (a2, a3)
+        };
+        let a3 = join(a3, 0.05, 60.0, a3 - 0.005);
+
+        // topside shape parameter
+        let ka = if (4..=9).contains(&self.month) {
+            6.705 - 0.014 * self.az_r - 0.008 * hmf2
+        } else {
+            -7.77 + 0.097 * (hmf2 / b2bot).powi(2) + 0.153 * nmf2
+        };
+        let kb = join(ka, 2.0, 1.0, ka - 2.0);
+        let k = join(8.0, kb, 1.0, kb - 8.0);
+
+        Profile {
+            hme,
+            hmf1,
+            hmf2,
+            nmf2,
+            b2bot,
+            b1top,
+            b1bot,
+            betop,
+            amplitudes: [a1, a2, a3],
+            h0: b2bot * k,
+        }
+    }
+}
+
+impl Profile {
+    /* electron density [m⁻³] at given height [km] */
+    fn density(&self, height: f64) -> f64 {
+        if height > self.hmf2 {
+            return self.topside(height);
+        }
+        let h = height.max(100.0);
+        let be = if h > self.hme { self.betop } else { 5.0 };
+        let bf1 = if h > self.hmf1 {
+            self.b1top
+        } else {
+            self.b1bot
+        };
+        let factor = (10.0 / (1.0 + (h - self.hmf2).abs())).exp();
+        let thickness = [self.b2bot, bf1, be];
+        let alphas = [
+            (h - self.hmf2) / self.b2bot,
+            (h - self.hmf1) / bf1 * factor,
+            (h - self.hme) / be * factor,
+        ];
+        let mut sum = 0.0;
+        let mut weighted = 0.0;
+        for ((amplitude, alpha), b) in self.amplitudes.iter().zip(alphas).zip(thickness) {
+            if alpha.abs() > 25.0 {
+                continue;
+            }
+            let ee = alpha.exp();
+            let s = amplitude * ee / (1.0 + ee).powi(2);
+            sum += s;
+            weighted += s * (1.0 - ee) / (1.0 + ee) / b;
+        }
+        if height >= 100.0 {
+            sum * 1.0E11
+        } else {
+            // Chapman like decay below 100 km
+            let bc = 1.0 - 10.0 * weighted / sum;
+            let z = (height - 100.0) / 10.0;
+            sum * clip_exp(1.0 - bc * z - clip_exp(-z)) * 1.0E11
+        }
+    }
+    /* electron density [m⁻³] above the F2 peak */
+    fn topside(&self, height: f64) -> f64 {
+        let (g, r) = (0.125, 100.0);
+        let dh = height - self.hmf2;
+        let z = dh / (self.h0 * (1.0 + r * g * dh / (r * self.h0 + g * dh)));
+        let ee = clip_exp(z);
+        let n = if ee > 1.0E11 {
+            4.0 * self.nmf2 / ee
+        } else {
+            4.0 * self.nmf2 * ee / (1.0 + ee).powi(2)
+        };
+        n * 1.0E11
+    }
+}
+
+impl NeQuickG {
+    /// Builds a NeQuick-G model from the broadcast coefficients (ai0, ai1, ai2)
+    /// and given maps, for a receiver located at sea level
+    pub fn new(ai: [f64; 3], maps: Arc<NeQuickMaps>) -> Self {
+        Self {
+            ai,
+            height: 0.0,
+            maps,
+        }
+    }
+    /// Returns Self for a receiver located at given height [m]
+    pub fn with_height(&self, height: f64) -> Self {
+        let mut s = self.clone();
+        s.height = height;
+        s
+    }
+    /// Returns the slant total electron content [m⁻²] between the receiver and the SV,
+    /// see [IonosphericModel::delay]
+    pub fn stec(
+        &self,
+        t: Epoch,
+        latitude: f64,
+        longitude: f64,
+        elevation: f64,
+        azimuth: f64,
+    ) -> f64 {
+        let context = Context::new(self, t, latitude, longitude);
+
+        // straight ray, above a spherical Earth [km]
+        let (phi, lambda) = (latitude.to_radians(), longitude.to_radians());
+        let (e, a) = (elevation.to_radians(), azimuth.to_radians());
+        let radius = EARTH_RADIUS_KM + self.height * 1.0E-3;
+        let up = [
+            phi.cos() * lambda.cos(),
+            phi.cos() * lambda.sin(),
+            phi.sin(),
+        ];
+        let east = [-lambda.sin(), lambda.cos(), 0.0];
+        let north = [
+            -phi.sin() * lambda.cos(),
+            -phi.sin() * lambda.sin(),
+            phi.cos(),
+        ];
+        let direction: Vec<f64> = (0..3)
+            .map(|i| e.cos() * a.sin() * east[i] + e.cos() * a.cos() * north[i] + e.sin() * up[i])
+            .collect();
+        let b = radius * e.sin();
+        // distance along the ray [km] to given height [km]
+        let distance = |height: f64| {
+            let r = EARTH_RADIUS_KM + height;
+            -b + (b.powi(2) - radius.powi(2) + r.powi(2)).max(0.0).sqrt()
+        };
+
+        let density = |s: f64| {
+            let p: Vec<f64> = (0..3).map(|i| radius * up[i] + s * direction[i]).collect();
+            let r = (p[0].powi(2) + p[1].powi(2) + p[2].powi(2)).sqrt();
+            let latitude = (p[2] / r).asin().to_degrees();
+            let longitude = p[1].atan2(p[0]).to_degrees();
+            context
+                .profile(latitude, longitude)
+                .density(r - EARTH_RADIUS_KM)
+        };
+
+        let height = self.height * 1.0E-3;
+        let mut stec = 0.0;
+        let mut start = 0.0;
+        for (top, tolerance) in [(1000.0, 1.0E-3), (2000.0, 1.0E-2), (SV_HEIGHT_KM, 1.0E-2)] {
+            if height >= top {
+                continue;
+            }
+            let end = distance(top);
+            stec += integrate(&density, start, end, tolerance, 0);
+            start = end;
+        }
+        // [km] to [m]
+        stec * 1.0E3
+    }
+}
+
+impl IonosphericModel for NeQuickG {
+    fn reference_frequency(&self) -> f64 {
+        L1_FREQUENCY
+    }
+    fn delay(&self, t: Epoch, latitude: f64, longitude: f64, elevation: f64, azimuth: f64) -> f64 {
+        let stec = self.stec(t, latitude, longitude, elevation, azimuth);
+        40.3 * stec / L1_FREQUENCY.powi(2) / SPEED_OF_LIGHT
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        integrate, interpolate, Context, NeQuickError, NeQuickG, NeQuickMaps, CCIR_SIZE, F2_SIZE,
+        FM3_SIZE, MODIP_SIZE,
+    };
+    use crate::ionosphere::IonosphericModel;
+    use crate::prelude::Epoch;
+    use std::sync::Arc;
+
+    /* MODIP grid of given function of latitude and longitude */
+    fn modip_grid(f: impl Fn(f64, f64) -> f64) -> Vec<f64> {
+        let mut grid = Vec::with_capacity(MODIP_SIZE * MODIP_SIZE);
+        for i in 0..MODIP_SIZE {
+            for j in 0..MODIP_SIZE {
+                grid.push(f(-95.0 + 5.0 * i as f64, -190.0 + 10.0 * j as f64));
+            }
+        }
+        grid
+    }
+
+    /* CCIR map with constant foF2 (solar activities 0 and 100) and M(3000)F2 */
+    fn ccir_map(fo_f2: (f64, f64), m3000: f64) -> Vec<f64> {
+        let mut map = vec![0.0; CCIR_SIZE];
+        let (f2_size, fm3_size) = (F2_SIZE.0 * F2_SIZE.1, FM3_SIZE.0 * FM3_SIZE.1);
+        map[0] = fo_f2.0;
+        map[f2_size] = fo_f2.1;
+        map[2 * f2_size] = m3000;
+        map[2 * f2_size + fm3_size] = m3000;
+        map
+    }
+
+    fn maps(fo_f2: (f64, f64)) -> Arc<NeQuickMaps> {
+        let ccir = vec![ccir_map(fo_f2, 3.0); 12];
+        let modip = modip_grid(|latitude, _| latitude.clamp(-90.0, 90.0));
+        Arc::new(NeQuickMaps::new(ccir, modip).unwrap())
+    }
+
+    #[test]
+    fn third_order_interpolation() {
+        let cubic = |x: f64| 1.0 + 2.0 * x - 0.5 * x.powi(2) + 0.25 * x.powi(3);
+        let z = [cubic(-1.0), cubic(0.0), cubic(1.0), cubic(2.0)];
+        for x in [0.0, 0.1, 0.5, 0.75, 1.0] {
+            assert!((interpolate(z, x) - cubic(x)).abs() < 1.0E-12);
+        }
+    }
+    #[test]
+    fn modip() {
+        let grid = modip_grid(|latitude, longitude| 0.8 * latitude + 0.05 * longitude);
+        let maps = NeQuickMaps::new(vec![vec![0.0; CCIR_SIZE]; 12], grid).unwrap();
+        for (latitude, longitude) in [(0.0, 0.0), (43.6, 1.44), (-33.9, 151.2), (-87.0, -179.0)] {
+            let expected = 0.8 * latitude + 0.05 * longitude;
+            assert!((maps.modip(latitude, longitude) - expected).abs() < 1.0E-9);
+        }
+        // longitude is wrapped
+        assert!((maps.modip(10.0, 190.0) - maps.modip(10.0, -170.0)).abs() < 1.0E-9);
+        assert_eq!(maps.modip(90.0, 0.0), 90.0);
+        assert_eq!(maps.modip(-90.0, 0.0), -90.0);
+    }
+    #[test]
+    fn invalid_maps() {
+        let modip = vec![0.0; MODIP_SIZE * MODIP_SIZE];
+        let mut ccir = vec![vec![0.0; CCIR_SIZE]; 12];
+        ccir[4].pop();
+        assert!(matches!(
+            NeQuickMaps::new(ccir, modip.clone()),
+            Err(NeQuickError::InvalidCcirMap(5))
+        ));
+        let ccir = vec![vec![0.0; CCIR_SIZE]; 12];
+        assert!(matches!(
+            NeQuickMaps::new(ccir, modip[1..].to_vec()),
+            Err(NeQuickError::InvalidModipGrid)
+        ));
+    }
+    #[test]
+    fn from_directory() {
+        let dir = std::env::temp_dir().join("cggtts-nequick");
+        std::fs::create_dir_all(&dir).unwrap();
+        let ccir: Vec<String> = ccir_map((6.0, 10.0), 3.0)
+            .iter()
+            .map(|v| format!("{:.8E}", v))
+            .collect();
+        for month in 11..=22 {
+            std::fs::write(dir.join(format!("ccir{}.asc", month)), ccir.join(" ")).unwrap();
+        }
+        let modip: Vec<String> = modip_grid(|latitude, _| latitude.clamp(-90.0, 90.0))
+            .chunks(MODIP_SIZE)
+            .map(|row| {
+                row.iter()
+                    .map(|v| format!("{:.3}", v))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        std::fs::write(dir.join("modipNeQG_wrapped.asc"), modip.join("\n")).unwrap();
+        let loaded = NeQuickMaps::from_directory(&dir).unwrap();
+        assert_eq!(loaded, *maps((6.0, 10.0)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn kronrod_integration() {
+        // Epstein layer: 4·N·B over the whole profile
+        let (n, b, h) = (1.0E12, 40.0, 350.0);
+        let layer = |x: f64| {
+            let ee = ((x - h) / b).exp();
+            4.0 * n * ee / (1.0 + ee).powi(2)
+        };
+        let integral = integrate(&layer, -2000.0, 3000.0, 1.0E-6, 0);
+        assert!((integral / (4.0 * n * b) - 1.0).abs() < 1.0E-6);
+    }
+    #[test]
+    fn profile() {
+        let model = NeQuickG::new([100.0, 0.0, 0.0], maps((10.0, 10.0)));
+        let t = Epoch::from_gregorian_utc(2023, 3, 21, 12, 0, 0, 0);
+        let context = Context::new(&model, t, 0.0, 0.0);
+        let profile = context.profile(0.0, 0.0);
+        // F2 peak
+        let nmf2 = 0.124 * 10.0_f64.powi(2) * 1.0E11;
+        assert!((profile.nmf2 * 1.0E11 - nmf2).abs() < 1.0E-6 * nmf2);
+        assert!(
+            profile.hmf2 > 200.0 && profile.hmf2 < 500.0,
+            "{}",
+            profile.hmf2
+        );
+        let peak = profile.density(profile.hmf2);
+        assert!((peak / nmf2 - 1.0).abs() < 0.05, "{}", peak / nmf2);
+        // decreasing away from the peak
+        let mut previous = peak;
+        for height in [50.0, 250.0, 650.0, 1650.0, 9650.0] {
+            let density = profile.density(profile.hmf2 + height);
+            assert!(density < previous);
+            previous = density;
+        }
+        let mut previous = peak;
+        for height in [200.0_f64, 150.0, 100.0, 90.0, 60.0] {
+            let density = profile.density(height.min(profile.hmf2));
+            assert!(density <= previous);
+            previous = density;
+        }
+        // continuous at 100 km
+        let (below, above) = (profile.density(100.0 - 1.0E-6), profile.density(100.0));
+        assert!((below - above).abs() < 1.0E-4 * above);
+    }
+    #[test]
+    fn nequick_g() {
+        let t = Epoch::from_gregorian_utc(2023, 11, 10, 12, 0, 0, 0);
+        let model = NeQuickG::new([100.0, 0.0, 0.0], maps((6.0, 10.0)));
+
+        let zenith = model.delay(t, 45.0, 5.0, 90.0, 0.0);
+        // a few TECU up to a few hundred TECU
+        assert!(zenith > 0.5E-9 && zenith < 150.0E-9, "{}", zenith);
+
+        // obliquity
+        let mut previous = zenith;
+        for elevation in [60.0, 30.0, 15.0, 5.0] {
+            let delay = model.delay(t, 45.0, 5.0, elevation, 120.0);
+            assert!(delay > previous, "{} {}", elevation, delay);
+            previous = delay;
+        }
+
+        // higher solar activity, higher foF2
+        let active = NeQuickG::new([200.0, 0.0, 0.0], model.maps.clone());
+        assert!(active.delay(t, 45.0, 5.0, 90.0, 0.0) > zenith);
+
+        // receiver height
+        let above = model.with_height(1500.0E3);
+        assert!(above.delay(t, 45.0, 5.0, 90.0, 0.0) < zenith);
+
+        // frequency scaling
+        let e5a = model.delay_on(t, 45.0, 5.0, 90.0, 0.0, 1176.45E6);
+        assert!((e5a / zenith - (1575.42_f64 / 1176.45).powi(2)).abs() < 1.0E-9);
+    }
+    /*
+     * ESA NeQuick-G validation vectors (Galileo Ionospheric Correction Algorithm,
+     * validation tables). Neither the official maps nor the vectors are distributed
+     * with this crate: set NEQUICK_G_VALIDATION to a directory holding the maps
+     * (see NeQuickMaps::from_directory) and a "vectors.txt" file, then run with --ignored.
+     * One vector per line:
+     * ai0 ai1 ai2 month UT[h] rx_lon[°] rx_lat[°] rx_h[m] sv_lon[°] sv_lat[°] sv_h[m] STEC[TECU]
+     * The ray is integrated up to the nominal Galileo height, not to the SV.
+     */
+    #[test]
+    #[ignore]
+    fn esa_validation() {
+        use crate::coordinates::{Coordinates, Ellipsoid};
+        let dir = std::env::var("NEQUICK_G_VALIDATION")
+            .expect("NEQUICK_G_VALIDATION should point to the validation data");
+        let dir = std::path::Path::new(&dir);
+        let maps = Arc::new(NeQuickMaps::from_directory(dir).unwrap());
+        let vectors = std::fs::read_to_string(dir.join("vectors.txt")).unwrap();
+        let mut nb = 0;
+        for line in vectors.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let v: Vec<f64> = line
+                .split_whitespace()
+                .map(|item| item.parse::<f64>().unwrap())
+                .collect();
+            assert_eq!(v.len(), 12, "invalid vector \"{}\"", line);
+            let t = Epoch::from_gregorian_utc(2023, v[3] as u8, 1, 0, 0, 0, 0)
+                + crate::prelude::Duration::from_hours(v[4]);
+            let rx = Coordinates::from_geodetic(v[6], v[5], v[7], Ellipsoid::WGS84);
+            let sv = Coordinates::from_geodetic(v[9], v[8], v[10], Ellipsoid::WGS84);
+            let (azimuth, elevation) = rx.azimuth_elevation(&sv);
+            let model = NeQuickG::new([v[0], v[1], v[2]], maps.clone()).with_height(v[7]);
+            let stec = model.stec(t, v[6], v[5], elevation, azimuth) * 1.0E-16;
+            assert!(
+                (stec - v[11]).abs() < 1.0E-2 * v[11],
+                "\"{}\": {} TECU",
+                line,
+                stec
+            );
+            nb += 1;
+        }
+        assert!(nb > 0);
+    }
+}
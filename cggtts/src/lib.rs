@@ -123,8 +123,29 @@ mod version;
 #[cfg(test)]
 mod tests;
 
+pub mod calibration;
+pub mod common_view;
+pub mod coordinates;
 pub mod delay;
+pub mod ephemeris;
+pub mod ionosphere;
+
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+pub mod qc;
+
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+pub mod processing;
+
+#[cfg(feature = "rinex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rinex")))]
+pub mod rinex;
+
+pub mod timeseries;
 pub mod track;
+pub mod troposphere;
+pub mod visibility;
 
 extern crate gnss_rs as gnss;
 
@@ -134,6 +155,8 @@ use std::str::FromStr;
 use strum_macros::EnumString;
 use thiserror::Error;
 
+pub use crate::coordinates::Coordinates;
+
 use crate::delay::{Delay, SystemDelay};
 use crate::track::CommonViewClass;
 use crate::track::Track;
@@ -148,17 +171,11 @@ use scan_fmt::scan_fmt;
 #[macro_use]
 extern crate serde;
 
-#[derive(PartialEq, Debug, Clone, Copy, Default)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct Coordinates {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
-
 pub mod prelude {
+    pub use crate::common_view::CommonView;
     pub use crate::rcvr::Rcvr;
     pub use crate::reference_time::ReferenceTime;
+    pub use crate::timeseries::{ClockFit, ClockModel, TimeSeries};
     pub use crate::track::{CommonViewClass, IonosphericData, Track, TrackData};
     pub use crate::version::Version;
     pub use crate::CGGTTS;
@@ -174,6 +191,36 @@ use serde::{Deserialize, Serialize};
 /// Latest CGGTTS release : only version we truly support
 pub const CURRENT_RELEASE: &str = "2E";
 
+/// Speed of light in [m/s]
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// Earth gravitational constant used by GPS (WGS84) [m³/s²]
+pub const EARTH_GM_GPS: f64 = 3.986_005E14;
+
+/// Earth gravitational constant used by Galileo (GTRF) [m³/s²]
+pub const EARTH_GM_GALILEO: f64 = 3.986_004_418E14;
+
+/// Earth gravitational constant used by BeiDou (CGCS2000) [m³/s²]
+pub const EARTH_GM_BEIDOU: f64 = 3.986_004_418E14;
+
+/// Earth gravitational constant used by Glonass (PZ-90) [m³/s²]
+pub const EARTH_GM_GLONASS: f64 = 3.986_004_418E14;
+
+/// Earth rotation rate used by GPS (WGS84) [rad/s]
+pub const EARTH_ROTATION_RATE_GPS: f64 = 7.292_115_146_7E-5;
+
+/// Earth rotation rate used by Galileo (GTRF) [rad/s]
+pub const EARTH_ROTATION_RATE_GALILEO: f64 = 7.292_115_146_7E-5;
+
+/// Earth rotation rate used by BeiDou (CGCS2000) [rad/s]
+pub const EARTH_ROTATION_RATE_BEIDOU: f64 = 7.292_115E-5;
+
+/// Earth rotation rate used by Glonass (PZ-90) [rad/s]
+pub const EARTH_ROTATION_RATE_GLONASS: f64 = 7.292_115E-5;
+
+/// Relativistic constant F = -2·sqrt(GM)/c² of the GPS ICD [s/sqrt(m)]
+pub const RELATIVISTIC_F: f64 = -4.442_807_633E-10;
+
 #[derive(Clone, Copy, PartialEq, Debug, EnumString)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default)]
@@ -871,13 +918,13 @@ impl std::fmt::Display for CGGTTS {
             match value {
                 Delay::Internal(v) => {
                     content.push_str(&format!(
-                        "INT DLY = {:.1} ns ({:X} {})\n",
+                        "INT DLY = {:.1} ns ({:E} {})\n",
                         v, constellation, code
                     ));
                 },
                 Delay::System(v) => {
                     content.push_str(&format!(
-                        "SYS DLY = {:.1} ns ({:X} {})\n",
+                        "SYS DLY = {:.1} ns ({:E} {})\n",
                         v, constellation, code
                     ));
                 },
@@ -894,7 +941,7 @@ impl std::fmt::Display for CGGTTS {
             match v1 {
                 Delay::Internal(_) => {
                     content.push_str(&format!(
-                        "INT DLY = {:.1} ns ({:X} {}), {:.1} ns ({:X} {})\n",
+                        "INT DLY = {:.1} ns ({:E} {}), {:.1} ns ({:E} {})\n",
                         v1.value(),
                         constellation,
                         c1,
@@ -905,7 +952,7 @@ impl std::fmt::Display for CGGTTS {
                 },
                 Delay::System(_) => {
                     content.push_str(&format!(
-                        "SYS DLY = {:.1} ns ({:X} {}), {:.1} ns ({:X} {})\n",
+                        "SYS DLY = {:.1} ns ({:E} {}), {:.1} ns ({:E} {})\n",
                         v1.value(),
                         constellation,
                         c1,
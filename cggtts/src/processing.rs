@@ -1,310 +1,486 @@
-//! Set of methods to compute CGGTTS data and
-//! produce tracks
-
-/// Speed of light in [m/s]
-const SPEED_OF_LIGHT: f64 = 300_000_000.0_f64;
-
-/// SAGNAC correction associated with Earth rotation
-const SAGNAC_CORRECTION: f64 = 0.0_f64;
-
-/// Refractivity Index @ seal level
-const NS: f64 = 324.8_f64;
-
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub struct Vec3D {
-    x: f64,
-    y: f64,
-    z: f64,
-}
-
-impl Default for Vec3D {
-    fn default() -> Self {
-        Self {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-        }
-    }
-}
-
-impl Vec3D {
-    pub fn norm(&self) -> f64 {
-        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).sqrt()
-    }
-}
-
-impl std::ops::Sub<Vec3D> for Vec3D {
-    type Output = Vec3D;
-    fn sub(self, rhs: Vec3D) -> Vec3D {
-        Vec3D {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
-}
-
-pub enum Policy {
-    /// Simple straight forward processing,
-    /// see [p6: Data processing paragraph]
-    Simple,
-    /// Use n tap smoothing.   
-    /// This feature is not needed when using a
-    /// modern GNSS receiver
-    Smoothing(u32),
+//! CGGTTS production: from raw pseudo-range to REFSV, REFSYS, MDTR and MDIO,
+//! following the CGGTTS 2E guidelines
+use crate::coordinates::{Coordinates, Ellipsoid};
+use crate::delay::SystemDelay;
+use crate::ephemeris::SvState;
+use crate::prelude::Constellation;
+use crate::track::FitData;
+use crate::troposphere::{Nato, TroposphericModel};
+use crate::{Code, EARTH_ROTATION_RATE_GPS};
+use thiserror::Error;
+
+pub use crate::ephemeris::relativistic_correction_kepler;
+pub use crate::SPEED_OF_LIGHT;
+
+/// Processing errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+    /// The system delay does not describe the code in use
+    #[error("no delay specified for {0}")]
+    MissingDelay(Code),
+    /// SV is below the horizon
+    #[error("sv below horizon")]
+    BelowHorizon,
+    /// Both pseudo-ranges were measured on the same carrier frequency
+    #[error("pseudo-ranges share the same carrier frequency")]
+    SameCarrier,
 }
 
+/// Measurement of a single SV, at a given Epoch
+#[derive(Debug, Clone, Default)]
 pub struct Params {
-    /// Raw measurements
-    pr: f64,
-    /// Current elevation [°]
-    e: f64,
-    /// Current altitude [km]
-    h: f64,
-    /// Current Sv vector
-    x_sat: Vec3D,
-    /// Broadcast satellite clock offset
-    t_sat: f64,
-    /// reference timescale
-    t_ref: f64,
-    /// Current Rcvr vector
-    x_rec: Vec3D,
-    /// Carrier dependent delay
-    delay: f64,
-    /// RF delay
-    rf_delay: f64,
-    /// REF delay
-    ref_delay: f64,
-    /// Group delay
-    grp_delay: f64,
-}
-
-/// Computes dn constant
-fn dn() -> f64 {
-    -7.32 * (0.005577 * NS).exp()
-}
-
-fn nslog() -> f64 {
-    (NS + dn() / 105.0).ln()
+    /// Raw pseudo-range [m]
+    pub pseudo_range: f64,
+    /// Code the pseudo-range was measured on
+    pub code: Code,
+    /// SV position at signal transmission time, ECEF [m]
+    pub sv_position: Coordinates,
+    /// SV velocity at signal transmission time, ECEF [m/s]
+    pub sv_velocity: Coordinates,
+    /// Receiver antenna position, ECEF [m]
+    pub rcvr_position: Coordinates,
+    /// Broadcast SV clock offset to GNSS system time, a0 + a1·dt + a2·dt² [s]
+    pub sv_clock: f64,
+    /// Broadcast group delay for the signal in use [s],
+    /// see [crate::ephemeris::GroupDelays::group_delay]
+    pub tgd: f64,
+    /// Modeled ionospheric delay for the code in use [s],
+    /// 0 when not modeled
+    pub mdio: f64,
 }
 
-/// Computes R_h quantity [eq(8)] Tropospheric delay at zenith,
-/// from space vehicule altitude in [km]
-fn r_h(altitude: f64) -> f64 {
-    let dn = dn();
-    let nslog = nslog();
-    if altitude < 1.0 {
-        (2162.0 + NS * (1.0 - altitude) + 0.5 * dn * (1.0 - altitude.powf(2.0))) * 10E-3
-            / SPEED_OF_LIGHT
-    } else {
-        let frac = (NS + dn) / nslog;
-        let e_1 = (-nslog).exp();
-        let e_2 = (0.125 * (1.0 - altitude) * nslog).exp();
-        (732.0 - (8.0 * frac * (e_1 - e_2))) * 10E-3 / SPEED_OF_LIGHT
+impl Params {
+    /// Returns Params with SV position, velocity, clock and group delay
+    /// taken from given broadcast ephemeris state, see [crate::ephemeris::Ephemeris::transmission_state].
+    /// The group delay is the one that applies to given frequency code `frc`.
+    /// The relativistic effect is already part of the GLONASS broadcast clock,
+    /// so the SV velocity is not used for GLONASS.
+    pub fn with_sv_state(&self, constellation: Constellation, frc: &str, state: &SvState) -> Self {
+        let mut s = self.clone();
+        s.sv_position = state.position;
+        s.sv_velocity = match constellation {
+            Constellation::Glonass => Coordinates::default(),
+            _ => state.velocity,
+        };
+        s.sv_clock = state.clock;
+        s.tgd = state.group_delays.group_delay(constellation, frc);
+        s
     }
 }
 
-/// Computes f_e
-/// - e: elevation [°]
-fn f_e(e: f64) -> f64 {
-    1.0 / (e.sin() + 0.00143 / (e.tan() + 0.0455))
+/// Earth rotation (Sagnac) correction [m], to add to the geometric range
+/// between the SV position at transmission time and the receiver position,
+/// both expressed in ECEF at their own epoch. The Earth rotates during signal propagation,
+/// this correction can reach about 40 m (133 ns).
+/// ```
+/// use cggtts::coordinates::Coordinates;
+/// use cggtts::processing::sagnac_correction;
+/// let rcvr = Coordinates { x: 6_378_137.0, y: 0.0, z: 0.0 };
+/// // SV located eastward
+/// let sv = Coordinates { x: 20.0E6, y: 15.0E6, z: 0.0 };
+/// assert!(sagnac_correction(&sv, &rcvr) < 0.0);
+/// ```
+pub fn sagnac_correction(sv: &Coordinates, rcvr: &Coordinates) -> f64 {
+    EARTH_ROTATION_RATE_GPS * (sv.x * rcvr.y - sv.y * rcvr.x) / SPEED_OF_LIGHT
 }
 
-/// Relativistic delay
-fn dt_rel() -> f64 {
-    0.0
+/// Periodic relativistic correction [s] of the SV clock, due to orbit eccentricity,
+/// from SV ECEF position [m] and velocity [m/s]: -2·(r·v)/c².
+/// It is to be added to the broadcast SV clock offset.
+pub fn relativistic_correction(position: &Coordinates, velocity: &Coordinates) -> f64 {
+    let dot = position.x * velocity.x + position.y * velocity.y + position.z * velocity.z;
+    -2.0 * dot / SPEED_OF_LIGHT.powi(2)
 }
 
-/// Ionospheric delay
-fn dt_iono() -> f64 {
-    0.0
-}
-
-/// Inputs:
-/// - pr: raw measurement
-/// - x_sat: current Sv vector
-/// - x_rec: rcvr estimate
-/// - h: altitude in km
-/// - e: elevation in °
+/// Processes a single measurement and returns the data to latch
+/// into the [crate::track::SVTracker]. MDTR is modeled with the [Nato] model.
+/// The pseudo-range is compensated for the system delay of the code in use,
+/// the geometric range accounts for Earth rotation, the SV clock for the relativistic effect
+/// and the broadcast group delay. REFSYS is REFSV corrected for the broadcast SV clock.
+/// ```
+/// use cggtts::Code;
+/// use cggtts::coordinates::{Coordinates, Ellipsoid};
+/// use cggtts::delay::{Delay, SystemDelay};
+/// use cggtts::processing::{process, Params};
 ///
-/// Returns
-/// - dt_sat : [eq(2)]
-/// - dt_ref : [eq(7)]
-/// - dt_tropo : [eq(6)]
-/// - dt_iono : [eq(5)]
-pub fn process(data: Params) -> (f64, f64, f64) {
-    // compensation
-    let p = data.pr - SPEED_OF_LIGHT * (data.delay + data.rf_delay - data.ref_delay);
-    let fe = f_e(data.e);
-    let rh = r_h(data.h);
-    let dt_tropo = fe * rh;
-    let d_tclk_tsat = 1.0 / SPEED_OF_LIGHT
-        * (p - (data.x_sat - data.x_rec).norm() - SAGNAC_CORRECTION)
-        + dt_rel()
-        - dt_iono()
-        - dt_tropo
-        - data.grp_delay;
-    let d_tclk_tref = d_tclk_tsat + data.t_sat - data.t_ref;
-    (d_tclk_tsat, d_tclk_tref, dt_tropo)
-}
-
-/*
-    /// Computes f(elevation) [eq(7)] neded by NATO hydrostatic model
-    fn f_elev (elevation: f64) -> {
-        1.0 / (0.000143 / (e.tan() +0.0455) + e.sin())
+/// let rcvr_position = Coordinates::from_geodetic(45.0, 5.0, 200.0, Ellipsoid::WGS84);
+/// let sv_position = Coordinates::from_enu(0.0, 10.0E6, 20.0E6, &rcvr_position);
+/// let delay = SystemDelay {
+///     delays: vec![(Code::C1, Delay::System(0.0))],
+///     ..SystemDelay::new()
+/// };
+/// let params = Params {
+///     pseudo_range: rcvr_position.distance(&sv_position),
+///     code: Code::C1,
+///     sv_position,
+///     rcvr_position,
+///     ..Default::default()
+/// };
+/// let data = process(&params, &delay).unwrap();
+/// assert!(data.elevation > 60.0);
+/// assert!(data.mdtr > 0.0);
+/// ```
+pub fn process(params: &Params, delay: &SystemDelay) -> Result<FitData, Error> {
+    let system_delay = delay
+        .total_delay(params.code)
+        .ok_or(Error::MissingDelay(params.code))?;
+
+    let (azimuth, elevation) = params.rcvr_position.azimuth_elevation(&params.sv_position);
+    if elevation <= 0.0 {
+        return Err(Error::BelowHorizon);
     }
 
-    /// Computes delta troposphere using NATO hydrostatic model [eq(6)]
-    fn d_tropo (elevation: f64, altitude: f64) -> {
-        f_elev(elevation) * R_h(altitude)
-    }
-
-    /// Call this once per cycle
-    /// to process a new symbol.
-    /// Compensations & computations are then performed internally
-    ///
-    /// # Input:
-    /// - pr: raw pseudo range
-    /// - x_sat: 3D vehicule position estimate in IRTF system (must be IRTF!)
-    /// - x_rec: 3D receiver position estimate in IRTF system (must be IRTF!)
-    /// - dt_rel_corr : relativistic clock correction for space vehicule
-    /// redshift along its orbit
-    /// - iono_dt: carrier dependent ionospheric delay
-    /// - dtropo: troposphere induced delay
-    /// - grp_delay: broadcast group delay
-    pub fn process (&mut self, pr: f64) {
-        let p = symbol - SPEED_OF_LIGHT * (self.delay.value() + self.cab_delay - self.ref_delay);
-        self.buffer.push(p)
-    }
+    let (_, _, height) = params.rcvr_position.to_geodetic(Ellipsoid::WGS84);
+    let mdtr = Nato.delay(height, elevation);
 
-    pub fn run (&mut self, elevation: f64,
-            x_sat: (f64,f64,f64), x_rec: (f64,f64,f64), dt_rel_corr: f64,
-                iono_dt: f64, dtropo: f64, grp_delay: f64)
-    {
-    }
+    // compensated pseudo-range
+    let p = params.pseudo_range - SPEED_OF_LIGHT * system_delay * 1.0E-9;
 
-    pub fn next()
-        self.buffer.push(p);
-        dt =
-}
+    let rho = params.rcvr_position.distance(&params.sv_position)
+        + sagnac_correction(&params.sv_position, &params.rcvr_position);
 
-/*
-pub struct Scheduler {
-    now: chrono::NaiveDateTime;
-    pub trk_duration: std::time::Duration,
-}
+    let dt_rel = relativistic_correction(&params.sv_position, &params.sv_velocity);
 
-impl Iterator for Scheduler {
-    type Item = bool;
-}
+    let refsv = (p - rho) / SPEED_OF_LIGHT - mdtr - params.mdio + dt_rel - params.tgd;
+    let refsys = refsv + params.sv_clock;
 
-pub struct Scheduler {
-    /// TrackGen policy
-    pub processing : Policy,
-    /// Current work date
-    day: chrono::NaiveDate,
-    /// should match BIPM recommendations,
-    /// but custom values can be used (shortest tracking in particular)
-    trk_duration: std::time::Duration,
-    /// Scheduled events for today
-    events: Vec<chrono::NaiveTime>,
-    /// System delays
-    /// Only single frequency generation supported @ the moment
-    delay: delay::SystemDelay,
-    /// Internal data buffer
-    p : Vec<f64>,
+    Ok(FitData {
+        refsv,
+        refsys,
+        mdtr,
+        elevation,
+        azimuth,
+        mdio: Some(params.mdio),
+        msio: None,
+    })
 }
 
-impl Scheduler {
-    /// Builds a new measurement scheduler,
-    /// Inputs:
-    ///   day: optionnal work day, otherwise uses `now()`
-    ///
-    ///   trk_duration: optionnal custom tracking duration,
-    ///   defaults to `BIPM_RECOMMENDED_TRACKING`
-    pub fn new (day: chrono::NaiveDate, trk_duration: std::time::Duration) -> Self {
-        let day = day.unwrap_or(chrono::Utc::now().naive_utc().date());
-        let duration = trk_duration.unwrap_or(BIPM_RECOMMENDED_TRACKING);
-        //let events = Scheduler::events(day, duration);
-        Self {
-            day,
-            trk_duration,
-            events: Vec::new(),
-        }
+/// Measured ionospheric delay (MSIO) [s] on the carrier `frequency` [Hz] of `params`,
+/// from simultaneous pseudo-ranges of the same SV on two carriers, for example C1C and C2W.
+/// Both pseudo-ranges are compensated for the system delay of their code
+/// and for their broadcast group delay (TGD or inter signal correction), see [Params::tgd].
+/// Only the pseudo-range, code and group delay of each [Params] are used.
+/// The returned value is to be latched as [FitData::msio]: the tracker then determines
+/// MSIO, SMSI and ISG of the resulting track.
+/// ```
+/// use cggtts::Code;
+/// use cggtts::delay::{Delay, SystemDelay};
+/// use cggtts::processing::{msio, Params, SPEED_OF_LIGHT};
+///
+/// let delay = SystemDelay {
+///     delays: vec![(Code::C1, Delay::System(0.0)), (Code::P2, Delay::System(0.0))],
+///     ..SystemDelay::new()
+/// };
+/// let (f1, f2) = (1575.42E6_f64, 1227.60E6_f64);
+/// // 10 ns ionospheric delay on L1
+/// let iono = 10.0E-9;
+/// let l1 = Params {
+///     pseudo_range: 2.0E7 + SPEED_OF_LIGHT * iono,
+///     code: Code::C1,
+///     ..Default::default()
+/// };
+/// let l2 = Params {
+///     pseudo_range: 2.0E7 + SPEED_OF_LIGHT * iono * (f1 / f2).powi(2),
+///     code: Code::P2,
+///     ..Default::default()
+/// };
+/// let msio = msio(&l1, f1, &l2, f2, &delay).unwrap();
+/// assert!((msio - iono).abs() < 1.0E-15);
+/// ```
+pub fn msio(
+    params: &Params,
+    frequency: f64,
+    rhs: &Params,
+    rhs_frequency: f64,
+    delay: &SystemDelay,
+) -> Result<f64, Error> {
+    if frequency == rhs_frequency {
+        return Err(Error::SameCarrier);
     }
+    let compensated = |params: &Params| -> Result<f64, Error> {
+        let system_delay = delay
+            .total_delay(params.code)
+            .ok_or(Error::MissingDelay(params.code))?;
+        Ok(params.pseudo_range / SPEED_OF_LIGHT - system_delay * 1.0E-9 - params.tgd)
+    };
+    let gamma = (frequency / rhs_frequency).powi(2);
+    Ok((compensated(rhs)? - compensated(params)?) / (gamma - 1.0))
+}
 
-/*
-    /// Returns scheduled measurements for given day,
-    /// if date is not provided, we use now()
-    pub fn scheduled_events (&self, date: Option<chrono::NaiveDate>) -> Vec<chrono::NaiveDateTime> {
-        let mut res : Vec<chrono::NaiveDateTime> = Vec::new();
-
-    /// Call this once day has changed to reset internal FSM
-    pub fn new_day (&mut self) {
-        //self.day = chrono::Utc::now().naive_utc().date();
-        //self.events = Scheduler::events(self.day, self.duration);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::delay::Delay;
+    use crate::ephemeris::GroupDelays;
+    use crate::{EARTH_GM_GPS, RELATIVISTIC_F};
+    #[test]
+    fn relativistic_constant() {
+        let f = -2.0 * EARTH_GM_GPS.sqrt() / SPEED_OF_LIGHT.powi(2);
+        assert!((f - RELATIVISTIC_F).abs() < 1.0E-18);
     }
-
-    /// Updates tracking duration to new value
-    pub fn update_trk_duration (&mut self, new: std::time::Duration) {
-        self.duration = new
+    #[test]
+    fn relativistic_corrections() {
+        // Keplerian orbit, in its orbital plane
+        let (sqrt_a, e) = (5153.6_f64, 0.015_f64);
+        let a = sqrt_a.powi(2);
+        let n = (EARTH_GM_GPS / a.powi(3)).sqrt();
+        for ecc_anomaly in [0.0_f64, 0.3, 1.2, 2.5, 4.0, 5.5] {
+            let (sin_e, cos_e) = ecc_anomaly.sin_cos();
+            let e_dot = n / (1.0 - e * cos_e);
+            let position = Coordinates {
+                x: a * (cos_e - e),
+                y: a * (1.0 - e * e).sqrt() * sin_e,
+                z: 0.0,
+            };
+            let velocity = Coordinates {
+                x: -a * sin_e * e_dot,
+                y: a * (1.0 - e * e).sqrt() * cos_e * e_dot,
+                z: 0.0,
+            };
+            let dt = relativistic_correction(&position, &velocity);
+            let expected = relativistic_correction_kepler(e, sqrt_a, ecc_anomaly);
+            assert!((dt - expected).abs() < 1.0E-13, "{} {}", dt, expected);
+        }
+        // circular orbit
+        let position = Coordinates {
+            x: 26.56E6,
+            y: 0.0,
+            z: 0.0,
+        };
+        let velocity = Coordinates {
+            x: 0.0,
+            y: 3.87E3,
+            z: 0.0,
+        };
+        assert_eq!(relativistic_correction(&position, &velocity), 0.0);
     }
-
-    /// Returns scheduled measurements for given day,
-    /// if date is not provided, we use now()
-    pub fn events (&self, date: Option<chrono::NaiveDate>) -> Vec<chrono::NaiveDateTime> {
-        /*let mut res : Vec<chrono::NaiveDateTime> = Vec::new();
->>>>>>> Stashed changes
-        let mjd_ref = ModifiedJulianDay::new(REFERENCE_MJD).inner();
-        let date = date.unwrap_or(chrono::Utc::now().naive_utc().date());
-        let mjd = ModifiedJulianDay::from(date).inner();
-        for i in 1..self.tracks_in_24h()-1 {
-            let offset = Scheduler::time_ref(self.n) as i32 - 4*(mjd_ref - mjd)/60;
-            if offset > 0 {
-                let h = offset / 3600;
-                let m = (offset - h*3600)/60;
-                let s = offset -h*3600 - m*60;
-                res.push(
-                    chrono::NaiveDate::from_ymd(date.year(), date.month(), date.day())
-                        .and_hms(h as u32 ,m as u32,s as u32));
-            }
+    #[test]
+    fn sagnac() {
+        let rcvr = Coordinates::from_geodetic(48.8, 2.3, 50.0, Ellipsoid::WGS84);
+        for (e, n, u) in [
+            (10.0E6, 0.0, 15.0E6),
+            (-10.0E6, 5.0E6, 18.0E6),
+            (0.0, 0.0, 20.0E6),
+            (3.0E6, -12.0E6, 16.0E6),
+        ] {
+            let sv = Coordinates::from_enu(e, n, u, &rcvr);
+            // rotate the SV by the Earth rotation during propagation
+            let tau = rcvr.distance(&sv) / SPEED_OF_LIGHT;
+            let (sin, cos) = (EARTH_ROTATION_RATE_GPS * tau).sin_cos();
+            let rotated = Coordinates {
+                x: cos * sv.x + sin * sv.y,
+                y: -sin * sv.x + cos * sv.y,
+                z: sv.z,
+            };
+            let expected = rcvr.distance(&rotated) - rcvr.distance(&sv);
+            let correction = sagnac_correction(&sv, &rcvr);
+            assert!(
+                (correction - expected).abs() < 1.0E-3,
+                "{} {}",
+                correction,
+                expected
+            );
+            assert!(correction.abs() / SPEED_OF_LIGHT < 133.0E-9);
         }
-        res*/
-        Vec::new()
+        // SV and receiver on the same meridian
+        let sv = Coordinates::from_geodetic(10.0, 2.3, 20.0E6, Ellipsoid::WGS84);
+        assert!(sagnac_correction(&sv, &rcvr).abs() < 1.0E-9);
     }
-*/
-    /// Returns duration (time interval) between given date
-    /// and next scheduled measurement
-    pub fn time_to_next (&self, datetime: chrono::NaiveDateTime) -> std::time::Duration {
-        //let offset = Scheduler::time_ref(self.n);
-        std::time::Duration::from_secs(10)
+    #[test]
+    fn refsv_refsys() {
+        let rcvr_position = Coordinates::from_geodetic(48.8, 2.3, 50.0, Ellipsoid::WGS84);
+        let sv_position = Coordinates::from_enu(5.0E6, -8.0E6, 19.0E6, &rcvr_position);
+        let sv_velocity = Coordinates {
+            x: 1.0E3,
+            y: -2.5E3,
+            z: 0.8E3,
+        };
+        let delay = SystemDelay {
+            rf_cable_delay: 150.0,
+            ref_delay: 25.0,
+            delays: vec![(Code::C1, Delay::Internal(30.0))],
+            cal_id: None,
+        };
+
+        // build the pseudo-range from a known reference clock offset
+        let (dt_ref, sv_clock, tgd, mdio) = (2.0E-6, -1.0E-4, 5.0E-9, 8.0E-9);
+        let (_, elevation) = rcvr_position.azimuth_elevation(&sv_position);
+        let mdtr = Nato.delay(50.0, elevation);
+        let rho =
+            rcvr_position.distance(&sv_position) + sagnac_correction(&sv_position, &rcvr_position);
+        let dt_rel = relativistic_correction(&sv_position, &sv_velocity);
+        let dt_sv = sv_clock + dt_rel - tgd;
+        let pseudo_range = rho
+            + SPEED_OF_LIGHT * (dt_ref - dt_sv + mdtr + mdio)
+            + SPEED_OF_LIGHT * (30.0 + 150.0 - 25.0) * 1.0E-9;
+
+        let params = Params {
+            pseudo_range,
+            code: Code::C1,
+            sv_position,
+            sv_velocity,
+            rcvr_position,
+            sv_clock,
+            tgd,
+            mdio,
+        };
+        let data = process(&params, &delay).unwrap();
+        assert!((data.refsys - dt_ref).abs() < 1.0E-12, "{}", data.refsys);
+        assert!((data.refsv - dt_ref + sv_clock).abs() < 1.0E-12);
+        assert!((data.mdtr - mdtr).abs() < 1.0E-15);
+        assert_eq!(data.mdio, Some(mdio));
+        assert!((data.elevation - elevation).abs() < 1.0E-9);
+
+        let params = Params {
+            code: Code::P2,
+            ..params
+        };
+        assert_eq!(
+            process(&params, &delay).err(),
+            Some(Error::MissingDelay(Code::P2))
+        );
     }
-
-    /// Returns offset in seconds during the course of `MJD_REF`
-    /// reference Modified Julian Day (defined in standards),
-    /// for given nth observation within that day.
-    ///
-    /// Input:
-    ///  - observation: observation counter
-    fn time_ref (observation: u32) -> u32 {
-        60 * 2 + (observation -1)*16*60
+    #[test]
+    fn sv_state() {
+        let state = SvState {
+            position: Coordinates {
+                x: 1.0E7,
+                y: 2.0E7,
+                z: 0.5E7,
+            },
+            velocity: Coordinates {
+                x: 1.0E3,
+                y: 0.0,
+                z: -2.0E3,
+            },
+            clock: 1.0E-4,
+            group_delays: GroupDelays {
+                tgd: -2.0E-9,
+                tgd2: 3.0E-9,
+                ..Default::default()
+            },
+            ioe: 42,
+        };
+        let params = Params::default().with_sv_state(Constellation::GPS, "L1C", &state);
+        assert_eq!(params.sv_position, state.position);
+        assert_eq!(params.sv_velocity, state.velocity);
+        assert_eq!(params.sv_clock, 1.0E-4);
+        assert_eq!(params.tgd, -2.0E-9);
+
+        let params = params.with_sv_state(Constellation::BeiDou, "L7I", &state);
+        assert_eq!(params.tgd, 3.0E-9);
+
+        let params = params.with_sv_state(Constellation::Glonass, "L1C", &state);
+        assert_eq!(params.sv_velocity, Coordinates::default());
+        assert_eq!(params.tgd, 0.0);
     }
-
-    /// Returns number of measurements to perform within 24hours
-    fn tracks_in_24h (&self) -> u64 {
-        24 * 3600 / self.duration.as_secs()
+    #[test]
+    fn beidou_galileo_group_delays() {
+        let delay = SystemDelay {
+            delays: vec![
+                (Code::B1, Delay::System(10.0)),
+                (Code::B2, Delay::System(20.0)),
+                (Code::E1, Delay::System(30.0)),
+                (Code::E5, Delay::System(40.0)),
+            ],
+            ..SystemDelay::new()
+        };
+        let state = SvState {
+            group_delays: GroupDelays {
+                tgd: 6.0E-9,
+                tgd2: -3.0E-9,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (range, iono) = (2.5E7, 15.0E-9);
+        let e5b = -3.0E-9 * (1575.42_f64 / 1207.14).powi(2);
+        for (constellation, (frc1, code1, f1, tgd1), (frc2, code2, f2, tgd2)) in [
+            (
+                Constellation::BeiDou,
+                ("L2I", Code::B1, 1561.098E6_f64, 6.0E-9),
+                ("L7I", Code::B2, 1207.14E6_f64, -3.0E-9),
+            ),
+            (
+                Constellation::Galileo,
+                ("E1", Code::E1, 1575.42E6, 6.0E-9),
+                ("E5b", Code::E5, 1207.14E6, e5b),
+            ),
+        ] {
+            // pseudo-ranges affected by the ionosphere, SV group delays and system delays
+            let params = |frc: &str, code: Code, f: f64, tgd: f64| {
+                let system = delay.total_delay(code).unwrap() * 1.0E-9;
+                Params {
+                    pseudo_range: range + SPEED_OF_LIGHT * (iono * (f1 / f).powi(2) + tgd + system),
+                    code,
+                    ..Default::default()
+                }
+                .with_sv_state(constellation, frc, &state)
+            };
+            let p1 = params(frc1, code1, f1, tgd1);
+            let p2 = params(frc2, code2, f2, tgd2);
+            assert!((p1.tgd - tgd1).abs() < 1.0E-18);
+            assert!((p2.tgd - tgd2).abs() < 1.0E-18);
+            let msio = msio(&p1, f1, &p2, f2, &delay).unwrap();
+            assert!((msio - iono).abs() < 1.0E-15, "{} {}", constellation, msio);
+        }
+        // B3I is the reference of the BeiDou broadcast clock
+        let b3i = Params::default().with_sv_state(Constellation::BeiDou, "L6I", &state);
+        assert_eq!(b3i.tgd, 0.0);
     }
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    use chrono::{NaiveDate, NaiveDateTime};
     #[test]
-    fn test_scheduler_basic() {
-        let t0 = chrono::NaiveDate::from_ymd(2022, 07, 05)
-            .and_hms(00, 00, 00);
-        let scheduler = Scheduler::new(Some(t0), None);
+    fn dual_frequency_msio() {
+        let delay = SystemDelay {
+            rf_cable_delay: 100.0,
+            ref_delay: 20.0,
+            delays: vec![
+                (Code::C1, Delay::Internal(25.0)),
+                (Code::P2, Delay::Internal(32.0)),
+            ],
+            cal_id: None,
+        };
+        let (f1, f2) = (1575.42E6_f64, 1227.60E6_f64);
+        let gamma = (f1 / f2).powi(2);
+        let (iono, tgd) = (12.0E-9, -4.0E-9);
+        let range = 2.2E7;
+
+        // pseudo-ranges affected by the ionosphere, group delays and hardware delays
+        let l1 = Params {
+            pseudo_range: range + SPEED_OF_LIGHT * (iono + tgd + (25.0 + 100.0 - 20.0) * 1.0E-9),
+            code: Code::C1,
+            tgd,
+            ..Default::default()
+        };
+        let l2 = Params {
+            pseudo_range: range
+                + SPEED_OF_LIGHT * (gamma * (iono + tgd) + (32.0 + 100.0 - 20.0) * 1.0E-9),
+            code: Code::P2,
+            tgd: gamma * tgd,
+            ..Default::default()
+        };
+        let msio_l1 = msio(&l1, f1, &l2, f2, &delay).unwrap();
+        assert!((msio_l1 - iono).abs() < 1.0E-15, "{}", msio_l1);
+
+        // expressed on the other carrier
+        let msio_l2 = msio(&l2, f2, &l1, f1, &delay).unwrap();
+        assert!((msio_l2 - gamma * iono).abs() < 1.0E-15, "{}", msio_l2);
+
+        // uncompensated group delays bias the measurement
+        let biased = Params {
+            tgd: 0.0,
+            ..l2.clone()
+        };
+        let msio_l1 = msio(&l1, f1, &biased, f2, &delay).unwrap();
+        assert!((msio_l1 - iono).abs() > 1.0E-9);
+
+        assert_eq!(msio(&l1, f1, &l1, f1, &delay), Err(Error::SameCarrier));
+        let l5 = Params {
+            code: Code::E5,
+            ..l2
+        };
+        assert_eq!(
+            msio(&l1, f1, &l5, 1176.45E6, &delay),
+            Err(Error::MissingDelay(Code::E5))
+        );
     }
-}*/
-*/
+}
@@ -0,0 +1,279 @@
+//! Data quality: completeness and gap reports
+use crate::prelude::{Constellation, Duration, Epoch, SV};
+use crate::track::Scheduler;
+use crate::CGGTTS;
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Completeness of a single MJD
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DailyReport {
+    /// MJD (UTC)
+    pub mjd: u32,
+    /// Number of scheduled tracks for that day
+    pub expected: usize,
+    /// Scheduled slots for which no track was found
+    pub missing_slots: Vec<Epoch>,
+    /// Number of tracks per constellation and frequency code
+    pub tracks: BTreeMap<(Constellation, String), usize>,
+    /// Number of tracks per SV (all codes)
+    pub sv_tracks: BTreeMap<SV, usize>,
+}
+
+impl DailyReport {
+    /// Returns the ratio of scheduled slots that contain at least one track
+    pub fn completeness(&self) -> f64 {
+        if self.expected == 0 {
+            return 0.0;
+        }
+        (self.expected - self.missing_slots.len()) as f64 / self.expected as f64
+    }
+}
+
+/// Completeness and gap report of a [CGGTTS]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QcReport {
+    /// Station name
+    pub station: String,
+    /// Per day completeness
+    pub days: Vec<DailyReport>,
+    /// Tracks that appear several times (Epoch, SV, code)
+    pub duplicates: Vec<(Epoch, SV, String)>,
+    /// Tracks whose start time is not on the track schedule (Epoch, SV, code)
+    pub off_schedule: Vec<(Epoch, SV, String)>,
+    /// Tracks whose duration does not follow BIPM specifications (Epoch, SV, code, duration)
+    pub non_standard_durations: Vec<(Epoch, SV, String, Duration)>,
+    /// Epochs of tracks that are older than the preceding track, in file order
+    pub non_monotonic_epochs: Vec<Epoch>,
+}
+
+impl QcReport {
+    /// Returns true if no anomaly was found
+    pub fn is_clean(&self) -> bool {
+        self.duplicates.is_empty()
+            && self.off_schedule.is_empty()
+            && self.non_standard_durations.is_empty()
+            && self.non_monotonic_epochs.is_empty()
+            && self.days.iter().all(|day| day.missing_slots.is_empty())
+    }
+}
+
+impl std::fmt::Display for QcReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "STATION \"{}\"", self.station)?;
+        for day in &self.days {
+            writeln!(
+                f,
+                "MJD {}: {}/{} scheduled tracks ({:.1} %)",
+                day.mjd,
+                day.expected - day.missing_slots.len(),
+                day.expected,
+                day.completeness() * 100.0
+            )?;
+            for ((constellation, code), n) in &day.tracks {
+                writeln!(f, "    {:x} {}: {} tracks", constellation, code, n)?;
+            }
+            for t in &day.missing_slots {
+                writeln!(f, "    missing slot {}", t)?;
+            }
+        }
+        for (t, sv, code) in &self.duplicates {
+            writeln!(f, "duplicate track {} {} {}", t, sv, code)?;
+        }
+        for (t, sv, code) in &self.off_schedule {
+            writeln!(f, "off schedule track {} {} {}", t, sv, code)?;
+        }
+        for (t, sv, code, duration) in &self.non_standard_durations {
+            writeln!(f, "non standard track {} {} {}: {}", t, sv, code, duration)?;
+        }
+        for t in &self.non_monotonic_epochs {
+            writeln!(f, "non monotonic epoch {}", t)?;
+        }
+        Ok(())
+    }
+}
+
+impl CGGTTS {
+    /// Generates a completeness and gap report, against given track schedule.
+    /// Every scheduled slot that fits within a day is expected to contain
+    /// a track: that is 89 tracks per day with the BIPM 16' schedule.
+    /// A track contributes to the slot it starts in, tracks that do not start on
+    /// the schedule are also reported.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::track::Scheduler;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let report = cggtts.qc_report(&Scheduler::default());
+    /// assert_eq!(report.days.len(), 1);
+    /// assert_eq!(report.days[0].expected, 89);
+    /// println!("{}", report);
+    /// ```
+    pub fn qc_report(&self, scheduler: &Scheduler) -> QcReport {
+        let period = scheduler.period;
+        let mut days = Vec::<DailyReport>::new();
+
+        let mjds = self
+            .tracks()
+            .map(|trk| trk.epoch.to_mjd_utc_days().floor() as u32)
+            .unique()
+            .sorted();
+
+        for mjd in mjds {
+            let midnight = Epoch::from_mjd_utc(mjd as f64);
+            let next_midnight = Epoch::from_mjd_utc((mjd + 1) as f64);
+
+            let slots: Vec<_> = scheduler
+                .tracks_for_mjd(mjd)
+                .map(|(start, _, _)| start)
+                .collect();
+
+            let day_tracks: Vec<_> = self
+                .tracks()
+                .filter(|trk| trk.epoch >= midnight && trk.epoch < next_midnight)
+                .collect();
+
+            let missing_slots = slots
+                .iter()
+                .filter(|slot| {
+                    !day_tracks
+                        .iter()
+                        .any(|trk| trk.epoch >= **slot && trk.epoch < **slot + period)
+                })
+                .copied()
+                .collect();
+
+            let mut tracks = BTreeMap::<(Constellation, String), usize>::new();
+            let mut sv_tracks = BTreeMap::<SV, usize>::new();
+            for trk in &day_tracks {
+                *tracks
+                    .entry((trk.sv.constellation, trk.frc.clone()))
+                    .or_default() += 1;
+                *sv_tracks.entry(trk.sv).or_default() += 1;
+            }
+
+            days.push(DailyReport {
+                mjd,
+                expected: slots.len(),
+                missing_slots,
+                tracks,
+                sv_tracks,
+            });
+        }
+
+        let duplicates = self
+            .tracks()
+            .map(|trk| (trk.epoch, trk.sv, trk.frc.clone()))
+            .duplicates()
+            .collect();
+
+        let off_schedule = self
+            .tracks()
+            .filter(|trk| !scheduler.is_scheduled(trk.epoch))
+            .map(|trk| (trk.epoch, trk.sv, trk.frc.clone()))
+            .collect();
+
+        let non_standard_durations = self
+            .tracks()
+            .filter(|trk| !trk.follows_bipm_specs())
+            .map(|trk| (trk.epoch, trk.sv, trk.frc.clone(), trk.duration))
+            .collect();
+
+        let non_monotonic_epochs = self
+            .tracks
+            .windows(2)
+            .filter(|pair| pair[1].epoch < pair[0].epoch)
+            .map(|pair| pair[1].epoch)
+            .collect();
+
+        QcReport {
+            station: self.station.clone(),
+            days,
+            duplicates,
+            off_schedule,
+            non_standard_durations,
+            non_monotonic_epochs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::{Duration, Epoch, CGGTTS};
+    use crate::track::Scheduler;
+    #[test]
+    fn qc_report() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        let report = cggtts.qc_report(&Scheduler::default());
+        assert_eq!(report.station, "LAB");
+        assert_eq!(report.days.len(), 1);
+        let day = &report.days[0];
+        assert_eq!(day.mjd, 60258);
+        assert_eq!(day.expected, 89);
+        // 10:18 track is missing in this file
+        assert_eq!(
+            day.missing_slots,
+            vec![Epoch::from_gregorian_utc(2023, 11, 10, 10, 10, 0, 0)]
+        );
+        assert_eq!(day.completeness(), 88.0 / 89.0);
+        assert_eq!(day.tracks.values().sum::<usize>(), cggtts.tracks.len());
+        assert_eq!(day.sv_tracks.values().sum::<usize>(), cggtts.tracks.len());
+        assert!(report.duplicates.is_empty());
+        // this receiver does not follow the BIPM schedule (00:02 on MJD 60258)
+        assert_eq!(report.off_schedule.len(), cggtts.tracks.len());
+        assert!(report.non_standard_durations.is_empty());
+        assert!(report.non_monotonic_epochs.is_empty());
+        assert!(!report.is_clean());
+
+        // introduce anomalies
+        let mut cggtts = cggtts.clone();
+        let first = cggtts.tracks[0].epoch;
+        cggtts.tracks.retain(|trk| {
+            trk.epoch < first + Duration::from_hours(1.0)
+                || trk.epoch > first + Duration::from_hours(2.0)
+        });
+        let duplicate = cggtts.tracks[0].clone();
+        cggtts.tracks.push(duplicate);
+        cggtts.tracks[1].duration = Duration::from_seconds(600.0);
+
+        let report = cggtts.qc_report(&Scheduler::default());
+        assert_eq!(report.days[0].missing_slots.len(), 5);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.non_standard_durations.len(), 1);
+        assert_eq!(report.non_monotonic_epochs, vec![first]);
+    }
+    #[test]
+    fn schedule_conformance() {
+        let scheduler = Scheduler::default();
+        for file in [
+            "GZSY8259.506",
+            "GZSY8259.507",
+            "GZSY8259.508",
+            "GZSY8259.509",
+            "GZSY8259.565",
+            "GZSY8259.568",
+        ] {
+            let cggtts = CGGTTS::from_file(&format!("../data/single/{}", file)).unwrap();
+            // first track of the day is on the schedule
+            assert!(scheduler.is_scheduled(cggtts.tracks[0].epoch), "{}", file);
+        }
+
+        // this receiver loses the schedule at 12:46
+        let cggtts = CGGTTS::from_file("../data/single/GZSY8259.506").unwrap();
+        let report = cggtts.qc_report(&scheduler);
+        assert_eq!(report.off_schedule.len(), 38);
+        assert_eq!(
+            report.off_schedule[0].0,
+            Epoch::from_gregorian_utc(2021, 10, 19, 12, 46, 0, 0)
+        );
+
+        let cggtts = CGGTTS::from_file("../data/dual/RZSY8257.000").unwrap();
+        let report = cggtts.qc_report(&scheduler);
+        assert_eq!(report.off_schedule.len(), cggtts.tracks.len());
+    }
+}
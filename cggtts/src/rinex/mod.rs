@@ -0,0 +1,477 @@
+//! CGGTTS production from RINEX observation and navigation files
+use crate::calibration::delay_code;
+use crate::coordinates::Ellipsoid;
+use crate::delay::SystemDelay;
+use crate::ionosphere::{IonosphericModel, NeQuickMaps};
+use crate::prelude::{Constellation, Duration, Epoch, TimeScale, SV};
+use crate::processing::{msio, process, Params};
+use crate::track::{carrier_frequency, GlonassChannel, Observation, Scheduler, TrackGenerator};
+use crate::{Coordinates, CGGTTS};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+mod navigation;
+mod observation;
+
+pub use navigation::Navigation;
+pub use observation::Observations;
+
+/// Default sampling period [s], when it cannot be deduced from the observations
+const DEFAULT_SAMPLING_PERIOD_SECONDS: f64 = 30.0;
+
+/// RINEX parsing errors
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read file")]
+    IoError(#[from] std::io::Error),
+    #[error("non supported RINEX revision \"{0}\"")]
+    NonSupportedVersion(String),
+    #[error("unexpected RINEX file type")]
+    FileTypeMismatch,
+    #[error("failed to parse epoch \"{0}\"")]
+    EpochParsing(String),
+    #[error("failed to parse sv \"{0}\"")]
+    SVParsing(String),
+    #[error("failed to parse number \"{0}\"")]
+    NumberParsing(String),
+    #[error("incomplete navigation record for {0}")]
+    IncompleteRecord(SV),
+}
+
+/* "2023 11 08 00 00  0.0000000" */
+fn parse_epoch(content: &str, timescale: TimeScale) -> Result<Epoch, Error> {
+    let err = || Error::EpochParsing(content.to_string());
+    let items: Vec<&str> = content.split_whitespace().collect();
+    if items.len() != 6 {
+        return Err(err());
+    }
+    let year = items[0].parse::<i32>().map_err(|_| err())?;
+    let mut fields = [0_u8; 4];
+    for (field, item) in fields.iter_mut().zip(items[1..5].iter()) {
+        *field = item.parse::<u8>().map_err(|_| err())?;
+    }
+    let seconds = items[5].parse::<f64>().map_err(|_| err())?;
+    if !(0.0..60.0).contains(&seconds) {
+        return Err(err());
+    }
+    let nanos = ((seconds - seconds.floor()) * 1.0E9).round() as u32;
+    Epoch::maybe_from_gregorian(
+        year,
+        fields[0],
+        fields[1],
+        fields[2],
+        fields[3],
+        seconds.floor() as u8,
+        nanos,
+        timescale,
+    )
+    .map_err(|_| err())
+}
+
+/* "G01", "G 1" */
+fn parse_sv(content: &str) -> Result<SV, Error> {
+    SV::from_str(&content.trim().replace(' ', "0"))
+        .map_err(|_| Error::SVParsing(content.to_string()))
+}
+
+/// Returns the CGGTTS frequency code for given RINEX pseudo-range observable,
+/// for example "L1C" for GPS "C1C", "E5a" for Galileo "C5Q"
+pub fn frequency_code(constellation: Constellation, observable: &str) -> Option<String> {
+    let band = observable.get(1..2)?;
+    let attribute = observable.get(2..3)?;
+    match constellation {
+        Constellation::Galileo => match band {
+            "1" => Some("E1".to_string()),
+            "5" => Some("E5a".to_string()),
+            "7" => Some("E5b".to_string()),
+            "8" => Some("E5".to_string()),
+            "6" => Some("E6".to_string()),
+            _ => None,
+        },
+        _ => Some(format!("L{}{}", band, attribute)),
+    }
+}
+
+impl CGGTTS {
+    /// Produces CGGTTS from RINEX observation and navigation data,
+    /// one per MJD. Pseudo-ranges are compensated with given [SystemDelay] and
+    /// processed against broadcast ephemeris, at given antenna position.
+    /// Each pseudo-range is corrected for the broadcast group delay of its signal,
+    /// see [crate::ephemeris::GroupDelays::group_delay]. MDIO is modeled with the broadcast Klobuchar models (GPS, QZSS and BeiDou),
+    /// and with NeQuick-G for Galileo, when its `nequick_maps` are provided.
+    /// It is 0 for other constellations. When an SV is observed on two carriers,
+    /// the ionospheric delay is also measured (MSIO, SMSI and ISG), see [crate::processing::msio].
+    /// Tracks are then formed with a [TrackGenerator] that follows given [Scheduler].
+    /// Observables that have no delay specified in [SystemDelay] are not processed.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::delay::{Delay, SystemDelay};
+    /// use cggtts::rinex::{Navigation, Observations};
+    /// use cggtts::track::Scheduler;
+    /// use cggtts::{Code, Coordinates};
+    ///
+    /// let obs = Observations::from_file("../data/rinex/LAB00FRA_R_20233120000_40M_30S_MO.rnx")
+    ///     .unwrap();
+    /// let nav = Navigation::from_file("../data/rinex/LAB00FRA_R_20233120000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let apc = Coordinates {
+    ///     x: 4_500_540.9649,
+    ///     y: 393_746.3142,
+    ///     z: 4_487_489.8302,
+    /// };
+    /// let delay = SystemDelay {
+    ///     delays: vec![(Code::C1, Delay::System(0.0))],
+    ///     ..SystemDelay::new()
+    /// };
+    /// let cggtts = CGGTTS::from_rinex(&obs, &nav, apc, &delay, &Scheduler::default(), None);
+    /// assert_eq!(cggtts.len(), 1);
+    /// let cggtts = cggtts[0].station("LAB");
+    /// assert!(cggtts.tracks.len() > 0);
+    /// ```
+    pub fn from_rinex(
+        observations: &Observations,
+        navigation: &Navigation,
+        apc_coordinates: Coordinates,
+        delay: &SystemDelay,
+        scheduler: &Scheduler,
+        nequick_maps: Option<Arc<NeQuickMaps>>,
+    ) -> Vec<Self> {
+        let sampling_period = observations
+            .sampling_period()
+            .unwrap_or(Duration::from_seconds(DEFAULT_SAMPLING_PERIOD_SECONDS));
+        let mut generator = TrackGenerator::new(scheduler.clone(), sampling_period);
+
+        let (latitude, longitude, height) = apc_coordinates.to_geodetic(Ellipsoid::WGS84);
+        let nequick = nequick_maps
+            .and_then(|maps| navigation.nequick_g(maps))
+            .map(|model| model.with_height(height));
+
+        let mut tracks = Vec::new();
+        for (t, measurements) in &observations.pseudo_ranges {
+            // measurement, prior processing
+            let mut pending = Vec::<(Observation, Params, Option<f64>)>::new();
+            for (sv, observable, pseudo_range) in measurements {
+                let constellation = sv.constellation;
+                let frc = match frequency_code(constellation, observable) {
+                    Some(frc) => frc,
+                    None => continue,
+                };
+                let code = match delay_code(constellation, &frc) {
+                    Some(code) => code,
+                    None => continue,
+                };
+                let ephemeris = match navigation.ephemeris(*sv, *t) {
+                    Some(ephemeris) => ephemeris,
+                    None => continue,
+                };
+                let fr = match constellation {
+                    Constellation::Glonass => navigation
+                        .glonass_channels
+                        .get(sv)
+                        .copied()
+                        .unwrap_or_default(),
+                    _ => GlonassChannel::Unknown,
+                };
+
+                let mut state = ephemeris.transmission_state(*t, *pseudo_range);
+                if let Some(isc) = navigation.inter_signal_corrections(*sv, *t) {
+                    state.group_delays.isc = isc;
+                }
+                let (azimuth, elevation) = apc_coordinates.azimuth_elevation(&state.position);
+
+                let frequency = carrier_frequency(constellation, &frc, fr);
+                let mdio = match (constellation, frequency) {
+                    (Constellation::GPS | Constellation::QZSS, Some(f)) => {
+                        navigation.klobuchar.map(|model| {
+                            model.delay_on(*t, latitude, longitude, elevation, azimuth, f)
+                        })
+                    },
+                    (Constellation::BeiDou, Some(f)) => navigation.bds_klobuchar.map(|model| {
+                        model.delay_on(*t, latitude, longitude, elevation, azimuth, f)
+                    }),
+                    (Constellation::Galileo, Some(f)) => nequick.as_ref().map(|model| {
+                        model.delay_on(*t, latitude, longitude, elevation, azimuth, f)
+                    }),
+                    _ => None,
+                };
+
+                let params = Params {
+                    pseudo_range: *pseudo_range,
+                    code,
+                    rcvr_position: apc_coordinates,
+                    mdio: mdio.unwrap_or(0.0),
+                    ..Default::default()
+                }
+                .with_sv_state(constellation, &frc, &state);
+
+                let obs = Observation {
+                    sv: *sv,
+                    frc,
+                    ioe: state.ioe,
+                    hc: 0,
+                    fr,
+                    data: Default::default(),
+                };
+                pending.push((obs, params, frequency));
+            }
+
+            let mut obs = Vec::<Observation>::with_capacity(pending.len());
+            for (observation, params, frequency) in &pending {
+                let mut data = match process(params, delay) {
+                    Ok(data) => data,
+                    Err(_) => continue,
+                };
+                // measured on the first other carrier of this SV, if any
+                if let Some(f) = frequency {
+                    data.msio = pending
+                        .iter()
+                        .filter(|(rhs, _, _)| rhs.sv == observation.sv)
+                        .filter_map(|(_, rhs, rhs_f)| msio(params, *f, rhs, (*rhs_f)?, delay).ok())
+                        .next();
+                }
+                obs.push(Observation {
+                    data,
+                    ..observation.clone()
+                });
+            }
+            tracks.append(&mut generator.latch(*t, &obs));
+        }
+
+        let mut days = BTreeMap::<u32, CGGTTS>::new();
+        for track in tracks {
+            let mjd = track.epoch.to_mjd_utc_days().floor() as u32;
+            let cggtts = days.entry(mjd).or_insert_with(|| {
+                let mut cggtts = CGGTTS::default().apc_coordinates(apc_coordinates);
+                cggtts.delay = delay.clone();
+                cggtts
+            });
+            cggtts.tracks.push(track);
+        }
+        days.into_values()
+            .map(|mut cggtts| {
+                cggtts.tracks.sort_by_key(|trk| (trk.epoch, trk.sv));
+                cggtts
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{frequency_code, parse_epoch, Error, Navigation, Observations};
+    use crate::delay::{Delay, SystemDelay};
+    use crate::ionosphere::NeQuickMaps;
+    use crate::prelude::{Constellation, Duration, Epoch, TimeScale, CGGTTS, SV};
+    use crate::track::{GlonassChannel, Scheduler};
+    use crate::{Code, Coordinates};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    const OBS: &str = "../data/rinex/LAB00FRA_R_20233120000_40M_30S_MO.rnx";
+    const NAV: &str = "../data/rinex/LAB00FRA_R_20233120000_01D_MN.rnx";
+    const NAV_V4: &str = "../data/rinex/v4/LAB00FRA_R_20233120000_01D_MN.rnx";
+
+    #[test]
+    fn frequency_codes() {
+        for (constellation, observable, expected) in [
+            (Constellation::GPS, "C1C", Some("L1C")),
+            (Constellation::GPS, "C2W", Some("L2W")),
+            (Constellation::Galileo, "C1C", Some("E1")),
+            (Constellation::Galileo, "C5Q", Some("E5a")),
+            (Constellation::Galileo, "C7Q", Some("E5b")),
+            (Constellation::BeiDou, "C2I", Some("L2I")),
+            (Constellation::GPS, "C1", None),
+        ] {
+            assert_eq!(
+                frequency_code(constellation, observable).as_deref(),
+                expected
+            );
+        }
+    }
+    #[test]
+    fn epoch_parsing() {
+        let t = parse_epoch("2023 11 08 00 00  0.5000000", TimeScale::GPST).unwrap();
+        assert_eq!(
+            t,
+            Epoch::from_gregorian(2023, 11, 8, 0, 0, 0, 500_000_000, TimeScale::GPST)
+        );
+        for content in [
+            "2023 13 08 00 00  0.0000000",
+            "2023 02 30 00 00  0.0000000",
+            "2023 11 08 25 00  0.0000000",
+            "2023 11 08 00 00 61.0000000",
+            "2023 11 08 00 300 0.0000000",
+            "2023 11 08 00 00",
+        ] {
+            match parse_epoch(content, TimeScale::GPST) {
+                Err(Error::EpochParsing(s)) => assert_eq!(s, content),
+                other => panic!("{}: {:?}", content, other),
+            }
+        }
+    }
+    #[test]
+    fn observations() {
+        let obs = Observations::from_file(OBS).unwrap();
+        assert_eq!(obs.timescale, TimeScale::GPST);
+        assert_eq!(obs.pseudo_ranges.len(), 81);
+        assert_eq!(obs.sampling_period(), Some(Duration::from_seconds(30.0)));
+        let (t, first) = obs.pseudo_ranges.iter().next().unwrap();
+        assert_eq!(
+            *t,
+            Epoch::from_gregorian(2023, 11, 8, 0, 0, 0, 0, TimeScale::GPST)
+        );
+        // phase observations are dropped
+        assert_eq!(first.len(), 6);
+        let (sv, code, pr) = &first[0];
+        assert_eq!(*sv, SV::from_str("G05").unwrap());
+        assert_eq!(code, "C1C");
+        assert_eq!(*pr, 20245619.869);
+
+        assert!(Observations::from_file(NAV).is_err());
+    }
+    #[test]
+    fn navigation() {
+        let nav = Navigation::from_file(NAV).unwrap();
+        assert!(nav.klobuchar.is_none());
+        assert_eq!(nav.kepler.len(), 6);
+        assert_eq!(nav.glonass.len(), 1);
+        assert!(nav.inter_signal_corrections.is_empty());
+
+        let g05 = SV::from_str("G05").unwrap();
+        let toe = Epoch::from_gregorian(2023, 11, 8, 0, 0, 0, 0, TimeScale::GPST);
+        for (t, expected) in [
+            (toe + Duration::from_seconds(1800.0), Some(42)),
+            (toe + Duration::from_seconds(6600.0), Some(43)),
+            (toe + Duration::from_hours(7.0), None),
+        ] {
+            let ioe = nav.ephemeris(g05, t).map(|eph| eph.state(t).ioe);
+            assert_eq!(ioe, expected);
+        }
+        let eph = &nav.kepler[0];
+        assert_eq!(eph.toe, toe);
+        assert_eq!(eph.toc, toe);
+        assert_eq!(eph.af0, 1.0E-5);
+        assert_eq!(eph.sqrt_a, 5153.6);
+
+        let r07 = SV::from_str("R07").unwrap();
+        let glonass = &nav.glonass[0];
+        assert_eq!(glonass.tau_n, 1.2E-5);
+        assert_eq!(glonass.position.x, -14.0E6);
+        assert_eq!(glonass.velocity.y, -2.5E3);
+        assert_eq!(
+            nav.glonass_channels.get(&r07),
+            Some(&GlonassChannel::ChanNum(5))
+        );
+        assert!(nav.ephemeris(r07, glonass.toe).is_some());
+
+        // group delays
+        let (e11, c19) = (&nav.kepler[4], &nav.kepler[5]);
+        assert_eq!(e11.sv, SV::from_str("E11").unwrap());
+        assert_eq!(e11.tgd, 3.259629011154E-09);
+        assert_eq!(e11.tgd2, 3.725290298462E-09);
+        assert_eq!(c19.sv, SV::from_str("C19").unwrap());
+        assert_eq!(c19.tgd, -5.5E-9);
+        assert_eq!(c19.tgd2, 2.3E-9);
+        // IODC is not a group delay
+        assert_eq!(nav.kepler[0].tgd2, 0.0);
+
+        let v4 = Navigation::from_file(NAV_V4).unwrap();
+        // modernized messages only provide inter signal corrections
+        assert_eq!(v4.kepler, [nav.kepler[0], nav.kepler[4], nav.kepler[5]]);
+        assert_eq!(v4.inter_signal_corrections.len(), 2);
+        let isc = v4.inter_signal_corrections(g05, toe).unwrap();
+        assert_eq!(isc.l1ca, Some(1.5E-9));
+        assert_eq!(isc.l2c, Some(-2.0E-9));
+        assert_eq!(isc.l5i5, Some(2.5E-9));
+        assert_eq!(isc.l5q5, Some(3.0E-9));
+        assert_eq!(isc.b1cd, None);
+        let c19_toc = Epoch::from_gregorian(2023, 11, 8, 0, 0, 0, 0, TimeScale::BDT);
+        let isc = v4
+            .inter_signal_corrections(c19.sv, c19_toc + Duration::from_hours(1.0))
+            .unwrap();
+        assert_eq!(isc.b1cd, Some(0.8E-9));
+        assert_eq!(isc.b2ad, Some(1.1E-9));
+        assert_eq!(isc.tgd_b1cp, Some(-1.2E-9));
+        assert_eq!(isc.tgd_b2ap, Some(-2.4E-9));
+        assert!(v4
+            .inter_signal_corrections(c19.sv, c19_toc + Duration::from_hours(5.0))
+            .is_none());
+        assert!(v4.inter_signal_corrections(e11.sv, c19_toc).is_none());
+        assert_eq!(v4.glonass, nav.glonass);
+        let klobuchar = v4.klobuchar.unwrap();
+        assert_eq!(klobuchar.alpha[0], 1.1176E-8);
+        assert_eq!(klobuchar.beta[3], -6.5536E4);
+        assert_eq!(v4.bds_klobuchar.unwrap().beta[0], 1.2E5);
+
+        // Galileo NeQuick-G
+        let ai = [81.25, 0.23438, 0.016479];
+        assert_eq!(nav.nequick, Some(ai));
+        assert_eq!(v4.nequick, Some(ai));
+        let maps =
+            Arc::new(NeQuickMaps::new(vec![vec![0.0; 2858]; 12], vec![0.0; 39 * 39]).unwrap());
+        assert_eq!(nav.nequick_g(maps.clone()).unwrap().ai, ai);
+        assert!(Navigation::default().nequick_g(maps).is_none());
+    }
+    #[test]
+    fn from_rinex() {
+        let obs = Observations::from_file(OBS).unwrap();
+        let nav = Navigation::from_file(NAV).unwrap();
+        let apc = Coordinates {
+            x: 4_500_540.964_9,
+            y: 393_746.314_2,
+            z: 4_487_489.830_2,
+        };
+        let delay = SystemDelay {
+            rf_cable_delay: 100.0,
+            ref_delay: 0.0,
+            delays: vec![
+                (Code::C1, Delay::Internal(25.0)),
+                (Code::P2, Delay::Internal(30.0)),
+            ],
+            cal_id: None,
+        };
+        let cggtts = CGGTTS::from_rinex(&obs, &nav, apc, &delay, &Scheduler::default(), None);
+        assert_eq!(cggtts.len(), 1);
+        let cggtts = &cggtts[0];
+        assert_eq!(cggtts.delay, delay);
+        assert_eq!(cggtts.apc_coordinates, apc);
+
+        // 3 SV, 2 codes, 00:10 and 00:26 UTC tracks
+        assert_eq!(cggtts.tracks.len(), 12);
+        let t0 = Epoch::from_gregorian(2023, 11, 8, 0, 0, 0, 0, TimeScale::GPST);
+        for trk in cggtts.tracks() {
+            assert!(trk.follows_bipm_specs());
+            assert_eq!(trk.data.ioe, 42);
+            assert_eq!(trk.data.mdio, 0.0);
+            assert!(trk.data.mdtr > 0.0);
+            assert!(trk.elevation > 25.0);
+            assert!((0.0..360.0).contains(&trk.azimuth));
+            // simulated reference clock, minus the uncompensated delays
+            let midpoint = trk.epoch + trk.duration / 2;
+            let dt_ref = 150.0E-9 + 2.0E-13 * (midpoint - t0).to_seconds();
+            let expected = match trk.frc.as_str() {
+                "L1C" => dt_ref - 125.0E-9,
+                "L2W" => dt_ref - 130.0E-9,
+                _ => panic!("unexpected code {}", trk.frc),
+            };
+            // no ionosphere was simulated: MSIO only reflects the L1/L2 delay mismatch
+            let gamma = (1575.42_f64 / 1227.60).powi(2);
+            let expected_msio = match trk.frc.as_str() {
+                "L1C" => -5.0E-9 / (gamma - 1.0),
+                _ => -5.0E-9 * gamma / (gamma - 1.0),
+            };
+            let iono = trk.iono.unwrap();
+            assert!((iono.msio - expected_msio).abs() < 0.01E-9, "{}", iono.msio);
+            assert!(iono.isg < 0.01E-9);
+            assert!(
+                (trk.data.refsys - expected).abs() < 0.1E-9,
+                "{} {} {}",
+                trk.sv,
+                trk.frc,
+                trk.data.refsys
+            );
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use super::{parse_epoch, parse_sv, Error};
+use crate::ephemeris::{Ephemeris, GlonassEphemeris, InterSignalCorrections, KeplerEphemeris};
+use crate::ionosphere::{BdsKlobuchar, Klobuchar, NeQuickG, NeQuickMaps};
+use crate::prelude::{Constellation, Duration, Epoch, TimeScale, SV};
+use crate::track::GlonassChannel;
+use crate::Coordinates;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+/// Maximal distance [h] to the time of ephemeris, for Keplerian ephemeris to be used
+const KEPLER_VALIDITY_HOURS: f64 = 4.0;
+
+/// Maximal distance [h] to the time of ephemeris, for GLONASS ephemeris to be used
+const GLONASS_VALIDITY_HOURS: f64 = 1.0;
+
+/// Broadcast ephemeris and ionospheric models,
+/// read from a RINEX 3 or 4 navigation file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Navigation {
+    /// GPS Klobuchar model, if provided
+    pub klobuchar: Option<Klobuchar>,
+    /// BeiDou Klobuchar model, if provided
+    pub bds_klobuchar: Option<BdsKlobuchar>,
+    /// Galileo NeQuick-G broadcast coefficients (ai0, ai1, ai2), if provided,
+    /// see [Navigation::nequick_g]
+    pub nequick: Option<[f64; 3]>,
+    /// GPS, Galileo, BeiDou and QZSS ephemeris
+    pub kepler: Vec<KeplerEphemeris>,
+    /// GLONASS ephemeris
+    pub glonass: Vec<GlonassEphemeris>,
+    /// GLONASS frequency channels
+    pub glonass_channels: BTreeMap<SV, GlonassChannel>,
+    /// Inter signal corrections of the modernized messages (RINEX 4 only),
+    /// per SV and time of clock
+    pub inter_signal_corrections: BTreeMap<(SV, Epoch), InterSignalCorrections>,
+}
+
+/* navigation record, prior interpretation */
+struct Record {
+    /* EPH, ION, STO, EOP */
+    kind: String,
+    /* message type, empty in RINEX 3 */
+    message: String,
+    sv: SV,
+    /* time of clock or transmission time */
+    epoch: Epoch,
+    values: Vec<f64>,
+}
+
+/* values of a navigation record, 19 characters wide */
+fn parse_values(line: &str, offset: usize, values: &mut Vec<f64>) -> Result<(), Error> {
+    let mut start = offset;
+    while start < line.len() {
+        let end = (start + 19).min(line.len());
+        let field = line.get(start..end).unwrap_or("").trim();
+        if field.is_empty() {
+            values.push(0.0);
+        } else {
+            let value = field
+                .replace(['D', 'd'], "E")
+                .parse::<f64>()
+                .map_err(|_| Error::NumberParsing(field.to_string()))?;
+            values.push(value);
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/* Epoch from week number and seconds within week */
+fn from_time_of_week(week: f64, seconds: f64, timescale: TimeScale) -> Epoch {
+    Epoch::from_time_of_week(week as u32, (seconds * 1.0E9).round() as u64, timescale)
+}
+
+impl Navigation {
+    /// Parses a local RINEX navigation file. Only the legacy messages are interpreted
+    /// (GPS and QZSS LNAV, Galileo I/NAV and F/NAV, BeiDou D1/D2, GLONASS FDMA).
+    /// Modernized messages (GPS and QZSS CNAV, BeiDou CNV1 and CNV2) only provide
+    /// their inter signal corrections.
+    /// ```
+    /// use cggtts::rinex::Navigation;
+    /// let nav = Navigation::from_file("../data/rinex/LAB00FRA_R_20233120000_01D_MN.rnx")
+    ///     .unwrap();
+    /// assert_eq!(nav.kepler.len(), 6);
+    /// assert_eq!(nav.glonass.len(), 1);
+    /// ```
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let fd = std::fs::File::open(path)?;
+        let reader = BufReader::new(fd);
+
+        let mut ret = Self::default();
+        let mut header = true;
+
+        let mut record: Option<Record> = None;
+        // RINEX 4 record header (type, SV, message)
+        let mut header_line: Option<(String, SV, String)> = None;
+        let mut skipping = false;
+
+        for line in reader.lines() {
+            let line = line?;
+            if header {
+                let label = line.get(60..).unwrap_or("").trim();
+                match label {
+                    "RINEX VERSION / TYPE" => {
+                        let version = line.get(0..9).unwrap_or("").trim();
+                        let major = version.split('.').next().unwrap_or("");
+                        if major != "3" && major != "4" {
+                            return Err(Error::NonSupportedVersion(version.to_string()));
+                        }
+                        if line.get(20..21) != Some("N") {
+                            return Err(Error::FileTypeMismatch);
+                        }
+                    },
+                    "IONOSPHERIC CORR" => {
+                        let mut coefs = [0.0_f64; 4];
+                        let fields = line.get(5..53).unwrap_or("").as_bytes().chunks(12);
+                        for (coef, field) in coefs.iter_mut().zip(fields) {
+                            let field = String::from_utf8_lossy(field)
+                                .trim()
+                                .replace(['D', 'd'], "E");
+                            *coef = field
+                                .parse::<f64>()
+                                .map_err(|_| Error::NumberParsing(field.to_string()))?;
+                        }
+                        match line.get(0..4).unwrap_or("").trim() {
+                            "GAL" => ret.nequick = Some([coefs[0], coefs[1], coefs[2]]),
+                            "GPSA" => {
+                                ret.klobuchar.get_or_insert_with(Default::default).alpha = coefs
+                            },
+                            "GPSB" => {
+                                ret.klobuchar.get_or_insert_with(Default::default).beta = coefs
+                            },
+                            "BDSA" => {
+                                ret.bds_klobuchar.get_or_insert_with(Default::default).alpha = coefs
+                            },
+                            "BDSB" => {
+                                ret.bds_klobuchar.get_or_insert_with(Default::default).beta = coefs
+                            },
+                            _ => {},
+                        }
+                    },
+                    "END OF HEADER" => header = false,
+                    _ => {},
+                }
+                continue;
+            }
+
+            if let Some(content) = line.strip_prefix('>') {
+                // RINEX 4 record header, for example "> EPH G01 LNAV"
+                if let Some(record) = record.take() {
+                    ret.push(record)?;
+                }
+                let mut items = content.split_whitespace();
+                let kind = items.next().unwrap_or("").to_string();
+                let sv = parse_sv(items.next().unwrap_or(""))?;
+                let message = items.next().unwrap_or("").to_string();
+                // system time and Earth orientation records are not used
+                skipping = kind != "EPH" && kind != "ION";
+                header_line = Some((kind, sv, message));
+                continue;
+            }
+
+            if skipping || line.trim().is_empty() {
+                continue;
+            }
+
+            if !line.starts_with("    ") || record.is_none() {
+                // first line of a record: "G01 2023 11 08 00 00 00 ..."
+                // or "    2023 11 08 00 00 00 ..." for RINEX 4 system records
+                if let Some(record) = record.take() {
+                    ret.push(record)?;
+                }
+                let (kind, message, sv) = match header_line.take() {
+                    Some((kind, sv, message)) => (kind, message, sv),
+                    None => (
+                        "EPH".to_string(),
+                        String::new(),
+                        parse_sv(line.get(0..3).unwrap_or(""))?,
+                    ),
+                };
+                let timescale = sv.constellation.timescale().unwrap_or(TimeScale::GPST);
+                let epoch = parse_epoch(line.get(4..23).unwrap_or(""), timescale)?;
+                let mut values = Vec::new();
+                parse_values(&line, 23, &mut values)?;
+                record = Some(Record {
+                    kind,
+                    message,
+                    sv,
+                    epoch,
+                    values,
+                });
+            } else if let Some(record) = record.as_mut() {
+                parse_values(&line, 4, &mut record.values)?;
+            }
+        }
+        if let Some(record) = record.take() {
+            ret.push(record)?;
+        }
+        Ok(ret)
+    }
+    /* interprets a complete record */
+    fn push(&mut self, record: Record) -> Result<(), Error> {
+        let (sv, toc, v) = (record.sv, record.epoch, record.values);
+        let (kind, msg) = (record.kind.as_str(), record.message.as_str());
+
+        if kind == "ION" {
+            if msg == "IFNV" && v.len() >= 3 {
+                self.nequick = Some([v[0], v[1], v[2]]);
+            }
+            if v.len() < 8 {
+                return Ok(());
+            }
+            let alpha = [v[0], v[1], v[2], v[3]];
+            let beta = [v[4], v[5], v[6], v[7]];
+            match msg {
+                "LNAV" => self.klobuchar = Some(Klobuchar { alpha, beta }),
+                "D1D2" => self.bds_klobuchar = Some(BdsKlobuchar { alpha, beta }),
+                _ => {},
+            }
+            return Ok(());
+        }
+        if kind != "EPH" {
+            return Ok(());
+        }
+        if matches!(msg, "CNAV" | "CNV1" | "CNV2") {
+            if v.len() < 31 {
+                return Err(Error::IncompleteRecord(sv));
+            }
+            let isc = self.inter_signal_corrections.entry((sv, toc)).or_default();
+            match (sv.constellation, msg) {
+                (Constellation::GPS | Constellation::QZSS, "CNAV") => {
+                    isc.l1ca = Some(v[27]);
+                    isc.l2c = Some(v[28]);
+                    isc.l5i5 = Some(v[29]);
+                    isc.l5q5 = Some(v[30]);
+                },
+                (Constellation::BeiDou, "CNV1" | "CNV2") => {
+                    if msg == "CNV1" {
+                        isc.b1cd = Some(v[27]);
+                    } else {
+                        isc.b2ad = Some(v[28]);
+                    }
+                    isc.tgd_b1cp = Some(v[29]);
+                    isc.tgd_b2ap = Some(v[30]);
+                },
+                _ => {},
+            }
+            return Ok(());
+        }
+        if !matches!(msg, "" | "LNAV" | "INAV" | "FNAV" | "D1" | "D2" | "FDMA") {
+            return Ok(());
+        }
+        match sv.constellation {
+            Constellation::Glonass => {
+                if v.len() < 15 {
+                    return Err(Error::IncompleteRecord(sv));
+                }
+                self.glonass.push(GlonassEphemeris {
+                    sv,
+                    toe: toc,
+                    tau_n: -v[0],
+                    gamma_n: v[1],
+                    position: Coordinates {
+                        x: v[3] * 1.0E3,
+                        y: v[7] * 1.0E3,
+                        z: v[11] * 1.0E3,
+                    },
+                    velocity: Coordinates {
+                        x: v[4] * 1.0E3,
+                        y: v[8] * 1.0E3,
+                        z: v[12] * 1.0E3,
+                    },
+                    acceleration: Coordinates {
+                        x: v[5] * 1.0E3,
+                        y: v[9] * 1.0E3,
+                        z: v[13] * 1.0E3,
+                    },
+                });
+                // negative frequency numbers are not described in CGGTTS
+                let channel = match v[10] as i8 {
+                    k if k > 0 => GlonassChannel::ChanNum(k as u8),
+                    _ => GlonassChannel::Unknown,
+                };
+                self.glonass_channels.insert(sv, channel);
+            },
+            Constellation::GPS
+            | Constellation::Galileo
+            | Constellation::BeiDou
+            | Constellation::QZSS => {
+                if v.len() < 27 {
+                    return Err(Error::IncompleteRecord(sv));
+                }
+                // Galileo weeks are aligned to GPS weeks in RINEX
+                let toe = match sv.constellation {
+                    Constellation::BeiDou => from_time_of_week(v[21], v[11], TimeScale::BDT),
+                    _ => from_time_of_week(v[21], v[11], TimeScale::GPST),
+                };
+                self.kepler.push(KeplerEphemeris {
+                    sv,
+                    toc,
+                    toe,
+                    iode: v[3] as u16,
+                    af0: v[0],
+                    af1: v[1],
+                    af2: v[2],
+                    tgd: v[25],
+                    // IODC for GPS and QZSS
+                    tgd2: match sv.constellation {
+                        Constellation::Galileo | Constellation::BeiDou => v[26],
+                        _ => 0.0,
+                    },
+                    sqrt_a: v[10],
+                    e: v[8],
+                    i0: v[15],
+                    idot: v[19],
+                    omega0: v[13],
+                    omega_dot: v[18],
+                    w: v[17],
+                    m0: v[6],
+                    delta_n: v[5],
+                    cuc: v[7],
+                    cus: v[9],
+                    crc: v[16],
+                    crs: v[4],
+                    cic: v[12],
+                    cis: v[14],
+                });
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+    /// Returns the Galileo NeQuick-G model, if its broadcast coefficients
+    /// were provided. The CCIR and MODIP maps are not part of the navigation message.
+    pub fn nequick_g(&self, maps: Arc<NeQuickMaps>) -> Option<NeQuickG> {
+        self.nequick.map(|ai| NeQuickG::new(ai, maps))
+    }
+    /// Returns the inter signal corrections to use for given SV at Epoch `t`:
+    /// the ones broadcast the closest to `t`, within the validity of Keplerian ephemeris
+    pub fn inter_signal_corrections(&self, sv: SV, t: Epoch) -> Option<InterSignalCorrections> {
+        let distance = |toc: Epoch| (t - toc).abs();
+        self.inter_signal_corrections
+            .iter()
+            .filter(|((isc_sv, toc), _)| {
+                *isc_sv == sv && distance(*toc) <= Duration::from_hours(KEPLER_VALIDITY_HOURS)
+            })
+            .min_by_key(|((_, toc), _)| distance(*toc))
+            .map(|(_, isc)| *isc)
+    }
+    /// Returns the ephemeris to use for given SV at Epoch `t`:
+    /// the valid ephemeris whose reference time is the closest to `t`
+    pub fn ephemeris(&self, sv: SV, t: Epoch) -> Option<&dyn Ephemeris> {
+        let distance = |toe: Epoch| (t - toe).abs();
+        match sv.constellation {
+            Constellation::Glonass => self
+                .glonass
+                .iter()
+                .filter(|eph| eph.sv == sv)
+                .filter(|eph| distance(eph.toe) <= Duration::from_hours(GLONASS_VALIDITY_HOURS))
+                .min_by_key(|eph| distance(eph.toe))
+                .map(|eph| eph as &dyn Ephemeris),
+            _ => self
+                .kepler
+                .iter()
+                .filter(|eph| eph.sv == sv)
+                .filter(|eph| distance(eph.toe) <= Duration::from_hours(KEPLER_VALIDITY_HOURS))
+                .min_by_key(|eph| distance(eph.toe))
+                .map(|eph| eph as &dyn Ephemeris),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use super::{parse_epoch, parse_sv, Error};
+use crate::prelude::{Constellation, Epoch, TimeScale, SV};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+/// Pseudo-range observations, read from a RINEX 3 or 4 observation file.
+/// Other observables (phase, doppler, signal strength) are not retained.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Observations {
+    /// Time scale the receiver epochs are expressed in
+    pub timescale: TimeScale,
+    /// Pseudo-ranges [m] per Epoch, as (SV, observable, value), for example (G01, "C1C", 2.1E7)
+    pub pseudo_ranges: BTreeMap<Epoch, Vec<(SV, String, f64)>>,
+}
+
+impl Observations {
+    /// Parses pseudo-range observations from a local RINEX observation file
+    /// ```
+    /// use cggtts::rinex::Observations;
+    /// let obs = Observations::from_file("../data/rinex/LAB00FRA_R_20233120000_40M_30S_MO.rnx")
+    ///     .unwrap();
+    /// assert_eq!(obs.pseudo_ranges.len(), 81);
+    /// ```
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let fd = std::fs::File::open(path)?;
+        let reader = BufReader::new(fd);
+
+        let mut ret = Self {
+            timescale: TimeScale::GPST,
+            pseudo_ranges: BTreeMap::new(),
+        };
+
+        let mut header = true;
+        let mut obs_types = BTreeMap::<Constellation, Vec<String>>::new();
+        let mut current: Option<Constellation> = None;
+        let mut epoch: Option<Epoch> = None;
+        let mut skipped = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+            if header {
+                let label = line.get(60..).unwrap_or("").trim();
+                match label {
+                    "RINEX VERSION / TYPE" => {
+                        let version = line.get(0..9).unwrap_or("").trim();
+                        let major = version.split('.').next().unwrap_or("");
+                        if major != "3" && major != "4" {
+                            return Err(Error::NonSupportedVersion(version.to_string()));
+                        }
+                        if line.get(20..21) != Some("O") {
+                            return Err(Error::FileTypeMismatch);
+                        }
+                    },
+                    "SYS / # / OBS TYPES" => {
+                        let sys = line.get(0..1).unwrap_or(" ");
+                        if sys != " " {
+                            let constellation = Constellation::from_str(sys)
+                                .map_err(|_| Error::SVParsing(sys.to_string()))?;
+                            current = Some(constellation);
+                        }
+                        if let Some(constellation) = current {
+                            let codes = obs_types.entry(constellation).or_default();
+                            for code in line.get(7..58).unwrap_or("").split_whitespace() {
+                                codes.push(code.to_string());
+                            }
+                        }
+                    },
+                    "TIME OF FIRST OBS" => {
+                        ret.timescale = match line.get(48..51).unwrap_or("").trim() {
+                            "GAL" => TimeScale::GST,
+                            "BDT" => TimeScale::BDT,
+                            "GLO" => TimeScale::UTC,
+                            "QZS" => TimeScale::QZSST,
+                            _ => TimeScale::GPST,
+                        };
+                    },
+                    "END OF HEADER" => header = false,
+                    _ => {},
+                }
+                continue;
+            }
+
+            if skipped > 0 {
+                // event records
+                skipped -= 1;
+                continue;
+            }
+
+            if let Some(content) = line.strip_prefix('>') {
+                let flag = content.get(30..31).unwrap_or("0").trim();
+                let count = content.get(31..34).unwrap_or("").trim();
+                let count = count.parse::<usize>().unwrap_or(0);
+                match flag {
+                    "0" | "1" | "" => {
+                        let t = parse_epoch(content.get(1..28).unwrap_or(""), ret.timescale)?;
+                        ret.pseudo_ranges.entry(t).or_default();
+                        epoch = Some(t);
+                    },
+                    _ => {
+                        epoch = None;
+                        skipped = count;
+                    },
+                }
+                continue;
+            }
+
+            let t = match epoch {
+                Some(t) => t,
+                None => continue,
+            };
+            let sv = parse_sv(line.get(0..3).unwrap_or(""))?;
+            let codes = match obs_types.get(&sv.constellation) {
+                Some(codes) => codes,
+                None => continue,
+            };
+            let measurements = ret.pseudo_ranges.entry(t).or_default();
+            for (i, code) in codes.iter().enumerate() {
+                if !code.starts_with('C') {
+                    continue;
+                }
+                let start = 3 + i * 16;
+                let field = line.get(start..(start + 14).min(line.len())).unwrap_or("");
+                let field = field.trim();
+                if field.is_empty() {
+                    continue;
+                }
+                let value = field
+                    .parse::<f64>()
+                    .map_err(|_| Error::NumberParsing(field.to_string()))?;
+                measurements.push((sv, code.clone(), value));
+            }
+        }
+        Ok(ret)
+    }
+    /// Returns the sampling period, deduced from the shortest interval between two epochs
+    pub fn sampling_period(&self) -> Option<crate::prelude::Duration> {
+        self.pseudo_ranges
+            .keys()
+            .zip(self.pseudo_ranges.keys().skip(1))
+            .map(|(a, b)| *b - *a)
+            .min()
+    }
+}
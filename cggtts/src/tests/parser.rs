@@ -187,12 +187,12 @@ mod test {
 
         let total = cggtts.delay.total_delay(Code::C1);
         assert!(total.is_some());
-        assert_eq!(total.unwrap(), 53.9 + 237.0 + 149.6);
+        assert_eq!(total.unwrap(), 53.9 + 237.0 - 149.6);
 
         assert_eq!(cggtts.delay.delays[1], (Code::C2, Delay::Internal(49.8)));
         let total = cggtts.delay.total_delay(Code::C2);
         assert!(total.is_some());
-        assert_eq!(total.unwrap(), 49.8 + 237.0 + 149.6);
+        assert_eq!(total.unwrap(), 49.8 + 237.0 - 149.6);
 
         let cal_id = cggtts.delay.cal_id.clone();
         assert!(cal_id.is_some());
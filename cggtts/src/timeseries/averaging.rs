@@ -0,0 +1,192 @@
+//! Averaging and resampling
+use super::{Sample, TimeSeries};
+use crate::prelude::{Duration, Epoch};
+use itertools::Itertools;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Average of the samples of a [TimeSeries] over a given period
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Average {
+    /// Start of the averaging period
+    pub start: Epoch,
+    /// Averaging period
+    pub period: Duration,
+    /// Mean value [s]
+    pub mean: f64,
+    /// Standard deviation of the samples [s]
+    pub std_dev: f64,
+    /// Number of samples averaged
+    pub count: usize,
+}
+
+impl TimeSeries {
+    /// Averages Self over consecutive periods. Averaging periods
+    /// are aligned on `origin`: they start at `origin` + k × `period`.
+    /// Periods that contain no samples are not reported.
+    /// Circular T style 5-day averages ending on MJD xxxx4 and xxxx9 for example:
+    /// ```
+    /// use cggtts::prelude::{Duration, Epoch};
+    /// use cggtts::timeseries::{Sample, TimeSeries};
+    /// let t0 = Epoch::from_mjd_utc(60250.0);
+    /// let series = TimeSeries::new(
+    ///     (0..89 * 10)
+    ///         .map(|i| Sample {
+    ///             epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+    ///             value: 1.0E-9,
+    ///             slope: None,
+    ///         })
+    ///         .collect());
+    /// let averages = series.averages(
+    ///     Epoch::from_mjd_utc(60249.0),
+    ///     Duration::from_days(5.0));
+    /// assert_eq!(averages.len(), 3);
+    /// assert_eq!(averages[1].start, Epoch::from_mjd_utc(60254.0));
+    /// assert!((averages[1].mean - 1.0E-9).abs() < 1.0E-20);
+    /// assert_eq!(averages[1].count, 5 * 90);
+    /// ```
+    pub fn averages(&self, origin: Epoch, period: Duration) -> Vec<Average> {
+        let mut ret = Vec::<Average>::new();
+        for (k, group) in &self
+            .samples
+            .iter()
+            .chunk_by(|s| ((s.epoch - origin).to_seconds() / period.to_seconds()).floor() as i64)
+        {
+            let values: Vec<f64> = group.map(|s| s.value).collect();
+            let count = values.len();
+            let mean = values.iter().sum::<f64>() / count as f64;
+            let std_dev = if count > 1 {
+                (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+            } else {
+                0.0
+            };
+            ret.push(Average {
+                start: origin + k as f64 * period,
+                period,
+                mean,
+                std_dev,
+                count,
+            });
+        }
+        ret
+    }
+    /// Averages Self over each UTC day
+    pub fn daily_averages(&self) -> Vec<Average> {
+        self.averages(Epoch::from_mjd_utc(0.0), Duration::from_days(1.0))
+    }
+    /// Returns the value at given Epoch, linearly interpolated
+    /// between the two closest samples. Returns None
+    /// when Epoch lies outside the span of Self.
+    pub fn interpolate(&self, t: Epoch) -> Option<f64> {
+        let after = self.samples.iter().position(|s| s.epoch >= t)?;
+        let s1 = self.samples[after];
+        if s1.epoch == t {
+            return Some(s1.value);
+        }
+        let s0 = self.samples[after.checked_sub(1)?];
+        let s = (t - s0.epoch).to_seconds() / (s1.epoch - s0.epoch).to_seconds();
+        Some(s0.value + s * (s1.value - s0.value))
+    }
+    /// Resamples Self at `origin` + k × `period`, within the span of Self.
+    /// Use this on smoothed results to report values at 0h UTC
+    /// every 5 days for example.
+    /// ```
+    /// use cggtts::prelude::{Duration, Epoch};
+    /// use cggtts::timeseries::{Sample, TimeSeries};
+    /// let t0 = Epoch::from_mjd_utc(60250.1);
+    /// let series = TimeSeries::new(
+    ///     (0..89 * 10)
+    ///         .map(|i| Sample {
+    ///             epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+    ///             value: 1.0E-9,
+    ///             slope: None,
+    ///         })
+    ///         .collect());
+    /// let resampled = series.resample(
+    ///     Epoch::from_mjd_utc(60249.0),
+    ///     Duration::from_days(5.0));
+    /// assert_eq!(resampled.len(), 2); // MJD 60254 and 60259
+    /// ```
+    pub fn resample(&self, origin: Epoch, period: Duration) -> Self {
+        let (first, last) = match (self.first_epoch(), self.last_epoch()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Self::default(),
+        };
+        let mut k = ((first - origin).to_seconds() / period.to_seconds()).ceil() as i64;
+        let mut samples = Vec::<Sample>::new();
+        loop {
+            let epoch = origin + k as f64 * period;
+            if epoch > last {
+                break;
+            }
+            if let Some(value) = self.interpolate(epoch) {
+                samples.push(Sample {
+                    epoch,
+                    value,
+                    slope: None,
+                });
+            }
+            k += 1;
+        }
+        Self { samples }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::{Duration, Epoch};
+    use crate::timeseries::{Sample, TimeSeries};
+    #[test]
+    fn daily_averages() {
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        let series = TimeSeries::new(
+            (0..89 * 3)
+                .map(|i| Sample {
+                    epoch: t0
+                        + i as f64 * Duration::from_seconds(960.0)
+                        + Duration::from_hours(12.0),
+                    value: if i % 2 == 0 { 1.0E-9 } else { 3.0E-9 },
+                    slope: None,
+                })
+                .collect(),
+        );
+        let averages = series.daily_averages();
+        assert_eq!(averages.len(), 4);
+        assert_eq!(averages[0].start, t0);
+        assert_eq!(averages[0].period, Duration::from_days(1.0));
+        assert_eq!(averages[0].count, 45);
+        assert_eq!(averages[1].count, 90);
+        assert_eq!(averages.iter().map(|avg| avg.count).sum::<usize>(), 89 * 3);
+        assert!((averages[1].mean - 2.0E-9).abs() < 1.0E-20);
+        let expected = (90.0_f64 / 89.0).sqrt() * 1.0E-9;
+        assert!((averages[1].std_dev - expected).abs() < 1.0E-20);
+    }
+    #[test]
+    fn interpolation() {
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        let series = TimeSeries::new(
+            (0..100)
+                .map(|i| Sample {
+                    epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+                    value: i as f64 * 960.0 * 1.0E-13,
+                    slope: None,
+                })
+                .collect(),
+        );
+        assert_eq!(series.interpolate(t0), Some(0.0));
+        assert!(series
+            .interpolate(t0 - Duration::from_seconds(1.0))
+            .is_none());
+        let value = series.interpolate(t0 + Duration::from_hours(1.0)).unwrap();
+        assert!((value - 3600.0E-13).abs() < 1.0E-20);
+
+        let resampled = series.resample(t0, Duration::from_hours(6.0));
+        assert_eq!(resampled.len(), 5);
+        for sample in resampled.samples() {
+            let expected = (sample.epoch - t0).to_seconds() * 1.0E-13;
+            assert!((sample.value - expected).abs() < 1.0E-20);
+        }
+    }
+}
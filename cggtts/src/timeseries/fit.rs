@@ -0,0 +1,414 @@
+//! Least squares clock model fitting
+use super::{solve, Error, Sample, TimeSeries};
+use crate::prelude::Epoch;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Clock model to fit on a [TimeSeries]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ClockModel {
+    /// Phase offset only: x(t) = x0
+    PhaseOffset,
+    /// Phase and fractional frequency offset: x(t) = x0 + y0 (t - t0)
+    #[default]
+    FrequencyOffset,
+    /// Phase, frequency offset and linear frequency drift:
+    /// x(t) = x0 + y0 (t - t0) + d/2 (t - t0)²
+    LinearDrift,
+}
+
+impl ClockModel {
+    /* number of parameters to solve for */
+    fn nb_parameters(&self) -> usize {
+        match self {
+            Self::PhaseOffset => 1,
+            Self::FrequencyOffset => 2,
+            Self::LinearDrift => 3,
+        }
+    }
+}
+
+/// Result of a least squares clock model fit
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClockFit {
+    /// Model that was fitted
+    pub model: ClockModel,
+    /// First Epoch of the fit, this is the model reference Epoch t0
+    pub start: Epoch,
+    /// Last Epoch of the fit
+    pub end: Epoch,
+    /// Phase offset x0 at t0 [s]
+    pub phase_offset: f64,
+    /// Phase offset 1-sigma uncertainty [s]
+    pub phase_offset_sigma: f64,
+    /// Fractional frequency offset y0 at t0 [s/s]
+    pub frequency_offset: f64,
+    /// Fractional frequency offset 1-sigma uncertainty [s/s]
+    pub frequency_offset_sigma: f64,
+    /// Linear frequency drift [1/s]
+    pub drift: f64,
+    /// Linear frequency drift 1-sigma uncertainty [1/s]
+    pub drift_sigma: f64,
+    /// Number of phase samples used
+    pub nb_samples: usize,
+    /// Number of slope (SRSYS) samples used
+    pub nb_slopes: usize,
+    /// Phase residuals to the fitted model
+    pub residuals: TimeSeries,
+    /// RMS of the phase residuals [s]
+    pub rms: f64,
+}
+
+impl ClockFit {
+    /// Returns the modeled phase [s] at given Epoch
+    pub fn phase(&self, t: Epoch) -> f64 {
+        let dt = (t - self.start).to_seconds();
+        self.phase_offset + self.frequency_offset * dt + 0.5 * self.drift * dt.powi(2)
+    }
+    /// Returns the modeled fractional frequency offset [s/s] at given Epoch
+    pub fn frequency(&self, t: Epoch) -> f64 {
+        let dt = (t - self.start).to_seconds();
+        self.frequency_offset + self.drift * dt
+    }
+    /// Returns the linear frequency drift, expressed per day [1/d]
+    pub fn drift_per_day(&self) -> f64 {
+        self.drift * 86_400.0
+    }
+}
+
+impl std::fmt::Display for ClockFit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:+.3E} s/s (±{:.1E}) over MJD {:.3}-{:.3}, phase offset {:+.3E} s (±{:.1E})",
+            self.frequency_offset,
+            self.frequency_offset_sigma,
+            self.start.to_mjd_utc_days(),
+            self.end.to_mjd_utc_days(),
+            self.phase_offset,
+            self.phase_offset_sigma,
+        )?;
+        if self.model == ClockModel::LinearDrift {
+            write!(
+                f,
+                ", drift {:+.3E} /d (±{:.1E})",
+                self.drift_per_day(),
+                self.drift_sigma * 86_400.0
+            )?;
+        }
+        write!(f, ", rms {:.3E} s", self.rms)
+    }
+}
+
+/*
+ * Variance floor (1 fs²), avoids infinite weights on noise free data
+ */
+const VARIANCE_FLOOR: f64 = 1.0E-30;
+
+/*
+ * Accumulates weighted observations into normal equations
+ */
+struct NormalEquations {
+    n: Vec<Vec<f64>>,
+    b: Vec<f64>,
+}
+
+impl NormalEquations {
+    fn new(p: usize) -> Self {
+        Self {
+            n: vec![vec![0.0; p]; p],
+            b: vec![0.0; p],
+        }
+    }
+    fn add(&mut self, row: &[f64], obs: f64, weight: f64) {
+        let p = self.b.len();
+        for i in 0..p {
+            for j in 0..p {
+                self.n[i][j] += weight * row[i] * row[j];
+            }
+            self.b[i] += weight * row[i] * obs;
+        }
+    }
+}
+
+/* design matrix row, for a phase observation */
+fn phase_row(tau: f64, p: usize) -> Vec<f64> {
+    [1.0, tau, tau.powi(2)][..p].to_vec()
+}
+
+/* design matrix row, for a (scaled) slope observation */
+fn slope_row(tau: f64, p: usize) -> Vec<f64> {
+    [0.0, 1.0, 2.0 * tau][..p].to_vec()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| a * b).sum()
+}
+
+impl TimeSeries {
+    /// Least squares fit of given clock model, over the entire series.
+    /// When samples come with a slope (SRSYS), slopes contribute to the
+    /// frequency and drift estimates: phase and slope observations are weighted
+    /// by the inverse of their respective variances, which are estimated
+    /// from a first phase only fit.
+    /// ```
+    /// use cggtts::prelude::{Duration, Epoch};
+    /// use cggtts::timeseries::{ClockModel, Sample, TimeSeries};
+    /// let t0 = Epoch::from_mjd_utc(60250.0);
+    /// let series = TimeSeries::new(
+    ///     (0..100)
+    ///         .map(|i| Sample {
+    ///             epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+    ///             value: 1.0E-9 + 3.2E-14 * i as f64 * 960.0,
+    ///             slope: None,
+    ///         })
+    ///         .collect(),
+    /// );
+    /// let fit = series.clock_fit(ClockModel::FrequencyOffset)
+    ///     .unwrap();
+    /// assert!((fit.frequency_offset - 3.2E-14).abs() < 1.0E-18);
+    /// assert!((fit.phase_offset - 1.0E-9).abs() < 1.0E-15);
+    /// ```
+    pub fn clock_fit(&self, model: ClockModel) -> Result<ClockFit, Error> {
+        let p = model.nb_parameters();
+        if self.samples.len() < p {
+            return Err(Error::NotEnoughSamples(p));
+        }
+
+        let t0 = self.samples[0].epoch;
+
+        // time is normalized to the fit span, to keep the normal equations
+        // well conditioned. Slopes are scaled accordingly.
+        let span = (self.samples[self.samples.len() - 1].epoch - t0)
+            .to_seconds()
+            .max(1.0);
+
+        let tau: Vec<f64> = self
+            .samples
+            .iter()
+            .map(|s| (s.epoch - t0).to_seconds() / span)
+            .collect();
+
+        // phase only solution
+        let mut normal = NormalEquations::new(p);
+        for (s, tau) in self.samples.iter().zip(tau.iter()) {
+            normal.add(&phase_row(*tau, p), s.value, 1.0);
+        }
+        let (x, inv) = solve(&normal.n, &normal.b)?;
+
+        let slopes: Vec<(f64, f64)> = match p {
+            1 => Vec::new(), // slopes do not contribute to a phase offset
+            _ => self
+                .samples
+                .iter()
+                .zip(tau.iter())
+                .filter_map(|(s, tau)| s.slope.map(|slope| (*tau, slope * span)))
+                .collect(),
+        };
+
+        let n = self.samples.len();
+        let m = slopes.len();
+
+        let phase_rss: f64 = self
+            .samples
+            .iter()
+            .zip(tau.iter())
+            .map(|(s, tau)| (s.value - dot(&phase_row(*tau, p), &x)).powi(2))
+            .sum();
+
+        let (x, cov) = if m == 0 {
+            let dof = n.saturating_sub(p).max(1) as f64;
+            (x, scale(inv, phase_rss / dof))
+        } else {
+            // variance estimates, used as weights
+            let var_phase = (phase_rss / n.saturating_sub(p).max(1) as f64).max(VARIANCE_FLOOR);
+            let var_slope = (slopes
+                .iter()
+                .map(|(tau, slope)| (slope - dot(&slope_row(*tau, p), &x)).powi(2))
+                .sum::<f64>()
+                / m as f64)
+                .max(VARIANCE_FLOOR);
+
+            let mut normal = NormalEquations::new(p);
+            for (s, tau) in self.samples.iter().zip(tau.iter()) {
+                normal.add(&phase_row(*tau, p), s.value, 1.0 / var_phase);
+            }
+            for (tau, slope) in slopes.iter() {
+                normal.add(&slope_row(*tau, p), *slope, 1.0 / var_slope);
+            }
+
+            let (x, inv) = solve(&normal.n, &normal.b)?;
+
+            let chi2: f64 = self
+                .samples
+                .iter()
+                .zip(tau.iter())
+                .map(|(s, tau)| (s.value - dot(&phase_row(*tau, p), &x)).powi(2) / var_phase)
+                .sum::<f64>()
+                + slopes
+                    .iter()
+                    .map(|(tau, slope)| (slope - dot(&slope_row(*tau, p), &x)).powi(2) / var_slope)
+                    .sum::<f64>();
+
+            let dof = (n + m).saturating_sub(p).max(1) as f64;
+            (x, scale(inv, chi2 / dof))
+        };
+
+        let residuals = TimeSeries {
+            samples: self
+                .samples
+                .iter()
+                .zip(tau.iter())
+                .map(|(s, tau)| Sample {
+                    epoch: s.epoch,
+                    value: s.value - dot(&phase_row(*tau, p), &x),
+                    slope: match p {
+                        1 => s.slope,
+                        _ => s
+                            .slope
+                            .map(|slope| slope - dot(&slope_row(*tau, p), &x) / span),
+                    },
+                })
+                .collect(),
+        };
+
+        let rms = (residuals.samples().map(|s| s.value.powi(2)).sum::<f64>() / n as f64).sqrt();
+
+        // back to physical units
+        let param = |i: usize| x.get(i).copied().unwrap_or(0.0) / span.powi(i as i32);
+        let sigma = |i: usize| {
+            cov.get(i)
+                .map(|row| row[i].sqrt() / span.powi(i as i32))
+                .unwrap_or(0.0)
+        };
+
+        Ok(ClockFit {
+            model,
+            start: t0,
+            end: self.samples[n - 1].epoch,
+            phase_offset: param(0),
+            phase_offset_sigma: sigma(0),
+            frequency_offset: param(1),
+            frequency_offset_sigma: sigma(1),
+            drift: 2.0 * param(2),
+            drift_sigma: 2.0 * sigma(2),
+            nb_samples: n,
+            nb_slopes: m,
+            residuals,
+            rms,
+        })
+    }
+    /// Least squares fit of given clock model, over [start, end] window.
+    pub fn clock_fit_window(
+        &self,
+        model: ClockModel,
+        start: Epoch,
+        end: Epoch,
+    ) -> Result<ClockFit, Error> {
+        self.window(start, end).clock_fit(model)
+    }
+}
+
+fn scale(m: Vec<Vec<f64>>, k: f64) -> Vec<Vec<f64>> {
+    m.into_iter()
+        .map(|row| row.into_iter().map(|v| v * k).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::ClockModel;
+    use crate::prelude::{Duration, Epoch};
+    use crate::timeseries::{Sample, TimeSeries};
+    use rand::{distributions::Distribution, SeedableRng};
+
+    fn noisy_series(x0: f64, y0: f64, d: f64, sigma: f64, with_slopes: bool) -> TimeSeries {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let noise = rand::distributions::Uniform::new_inclusive(-sigma, sigma);
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        TimeSeries::new(
+            (0..7 * 89)
+                .map(|i| {
+                    let dt = i as f64 * 960.0;
+                    Sample {
+                        epoch: t0 + Duration::from_seconds(dt),
+                        value: x0 + y0 * dt + 0.5 * d * dt.powi(2) + noise.sample(&mut rng),
+                        slope: match with_slopes {
+                            true => Some(y0 + d * dt + noise.sample(&mut rng) / 780.0),
+                            false => None,
+                        },
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn frequency_offset() {
+        for with_slopes in [false, true] {
+            let series = noisy_series(5.0E-9, 3.2E-14, 0.0, 1.0E-9, with_slopes);
+            let fit = series.clock_fit(ClockModel::FrequencyOffset).unwrap();
+            assert_eq!(fit.nb_samples, 623);
+            assert_eq!(fit.nb_slopes, if with_slopes { 623 } else { 0 });
+            assert!(
+                (fit.frequency_offset - 3.2E-14).abs() < 3.0 * fit.frequency_offset_sigma,
+                "{}",
+                fit
+            );
+            assert!((fit.phase_offset - 5.0E-9).abs() < 3.0 * fit.phase_offset_sigma);
+            assert!(fit.rms < 1.0E-9);
+            assert_eq!(fit.residuals.len(), 623);
+        }
+    }
+
+    #[test]
+    fn noise_free() {
+        let series = noisy_series(0.0, 0.0, 0.0, 0.0, true);
+        let fit = series.clock_fit(ClockModel::LinearDrift).unwrap();
+        assert_eq!(fit.frequency_offset, 0.0);
+        assert_eq!(fit.rms, 0.0);
+    }
+
+    #[test]
+    fn slopes_improve_frequency_estimate() {
+        let phase_only = noisy_series(0.0, 1.0E-13, 0.0, 2.0E-9, false)
+            .clock_fit(ClockModel::FrequencyOffset)
+            .unwrap();
+        let combined = noisy_series(0.0, 1.0E-13, 0.0, 2.0E-9, true)
+            .clock_fit(ClockModel::FrequencyOffset)
+            .unwrap();
+        assert!(combined.frequency_offset_sigma < phase_only.frequency_offset_sigma);
+    }
+
+    #[test]
+    fn linear_drift() {
+        let series = noisy_series(0.0, 1.0E-13, 1.0E-19, 1.0E-10, true);
+        let fit = series.clock_fit(ClockModel::LinearDrift).unwrap();
+        assert!(
+            (fit.drift - 1.0E-19).abs() < 3.0 * fit.drift_sigma,
+            "{}",
+            fit
+        );
+        assert!((fit.frequency_offset - 1.0E-13).abs() < 3.0 * fit.frequency_offset_sigma);
+        let t = fit.end;
+        let dt = (t - fit.start).to_seconds();
+        assert!((fit.frequency(t) - (1.0E-13 + 1.0E-19 * dt)).abs() < 1.0E-15);
+    }
+
+    #[test]
+    fn windowed_fit() {
+        let series = noisy_series(0.0, 1.0E-13, 0.0, 1.0E-10, false);
+        let t0 = Epoch::from_mjd_utc(60251.0);
+        let fit = series
+            .clock_fit_window(ClockModel::PhaseOffset, t0, t0 + Duration::from_days(1.0))
+            .unwrap();
+        assert_eq!(fit.start, t0);
+        assert!((fit.phase_offset - 1.0E-13 * 86_400.0 * 1.5).abs() < 1.0E-9);
+        assert!(series
+            .clock_fit_window(ClockModel::LinearDrift, t0, t0)
+            .is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Time series of clock comparison results, and their analysis
+use crate::common_view::CommonView;
+use crate::prelude::{Duration, Epoch, Track};
+use itertools::Itertools;
+use thiserror::Error;
+
+mod averaging;
+mod fit;
+mod smoothing;
+mod steps;
+
+pub use averaging::Average;
+pub use fit::{ClockFit, ClockModel};
+pub use smoothing::VondrakSmoother;
+pub use steps::{Step, StepDetector, StepKind};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Time series analysis errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+    /// Not enough samples to solve for the requested model
+    #[error("not enough samples: {0} needed")]
+    NotEnoughSamples(usize),
+    /// Singular normal equations. Usually all samples share the same Epoch.
+    #[error("singular normal equations")]
+    SingularMatrix,
+}
+
+/// A single point of a [TimeSeries]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sample {
+    /// Sampling Epoch
+    pub epoch: Epoch,
+    /// Phase value [s]
+    pub value: f64,
+    /// Phase slope [s/s], when known
+    pub slope: Option<f64>,
+}
+
+/// TimeSeries describes a phase (clock offset) evolution against time,
+/// for example a common view comparison between two remote clocks.
+/// Samples are always sorted in chronological order.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TimeSeries {
+    samples: Vec<Sample>,
+}
+
+impl TimeSeries {
+    /// Builds a TimeSeries from a list of samples, in any order
+    pub fn new(samples: Vec<Sample>) -> Self {
+        let mut samples = samples;
+        samples.sort_by_key(|s| s.epoch);
+        Self { samples }
+    }
+    /// Builds a TimeSeries from common view measurements.
+    /// All measurements sharing the same Epoch (several SV and/or signals)
+    /// are averaged into a single sample.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::timeseries::TimeSeries;
+    /// let a = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let series = TimeSeries::from_common_view(&a.common_view(&a));
+    /// assert!(!series.is_empty());
+    /// ```
+    pub fn from_common_view(cv: &[CommonView]) -> Self {
+        let mut samples = Vec::<Sample>::new();
+        for (epoch, group) in &cv
+            .iter()
+            .sorted_by_key(|cv| cv.epoch)
+            .chunk_by(|cv| cv.epoch)
+        {
+            let group: Vec<_> = group.collect();
+            let n = group.len() as f64;
+            samples.push(Sample {
+                epoch,
+                value: group.iter().map(|cv| cv.dt).sum::<f64>() / n,
+                slope: Some(group.iter().map(|cv| cv.slope).sum::<f64>() / n),
+            });
+        }
+        Self { samples }
+    }
+    /// Builds a TimeSeries from the REFSYS of given tracks.
+    /// All tracks sharing the same Epoch (several SV) are averaged
+    /// into a single sample. Filter the tracks beforehand,
+    /// to only account for a single signal for example.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::timeseries::TimeSeries;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let series = TimeSeries::from_refsys(
+    ///     cggtts.tracks()
+    ///         .filter(|trk| trk.frc == "L1C"));
+    /// assert!(!series.is_empty());
+    /// ```
+    pub fn from_refsys<'a>(tracks: impl Iterator<Item = &'a Track>) -> Self {
+        let mut samples = Vec::<Sample>::new();
+        for (epoch, group) in &tracks
+            .sorted_by_key(|trk| trk.epoch)
+            .chunk_by(|trk| trk.epoch)
+        {
+            let group: Vec<_> = group.collect();
+            let n = group.len() as f64;
+            samples.push(Sample {
+                epoch,
+                value: group.iter().map(|trk| trk.data.refsys).sum::<f64>() / n,
+                slope: Some(group.iter().map(|trk| trk.data.srsys).sum::<f64>() / n),
+            });
+        }
+        Self { samples }
+    }
+    /// Returns an iterator over all samples
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+    /// Returns number of samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    /// Returns true if Self contains no samples
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    /// Returns first sample Epoch
+    pub fn first_epoch(&self) -> Option<Epoch> {
+        self.samples.first().map(|s| s.epoch)
+    }
+    /// Returns last sample Epoch
+    pub fn last_epoch(&self) -> Option<Epoch> {
+        self.samples.last().map(|s| s.epoch)
+    }
+    /// Returns time span covered by Self
+    pub fn span(&self) -> Duration {
+        match (self.first_epoch(), self.last_epoch()) {
+            (Some(first), Some(last)) => last - first,
+            _ => Duration::default(),
+        }
+    }
+    /// Returns a TimeSeries that only contains samples
+    /// within [start, end] (both included)
+    pub fn window(&self, start: Epoch, end: Epoch) -> Self {
+        Self {
+            samples: self
+                .samples
+                .iter()
+                .filter(|s| s.epoch >= start && s.epoch <= end)
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+/*
+ * Solves the (small, dense) linear system A x = b by Gauss-Jordan elimination
+ * with partial pivoting. Returns the solution and the inverse of A.
+ */
+pub(crate) fn solve(a: &[Vec<f64>], b: &[f64]) -> Result<(Vec<f64>, Vec<Vec<f64>>), Error> {
+    let n = b.len();
+    let mut m: Vec<Vec<f64>> = a.to_vec();
+    let mut inv: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let mut x = b.to_vec();
+
+    let scale = a.iter().flatten().fold(0.0_f64, |max, v| max.max(v.abs()));
+
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|i, j| m[*i][col].abs().total_cmp(&m[*j][col].abs()))
+            .unwrap_or(col);
+
+        if m[pivot][col].abs() <= scale * 1.0E-12 {
+            return Err(Error::SingularMatrix);
+        }
+
+        m.swap(col, pivot);
+        inv.swap(col, pivot);
+        x.swap(col, pivot);
+
+        let p = m[col][col];
+        for j in 0..n {
+            m[col][j] /= p;
+            inv[col][j] /= p;
+        }
+        x[col] /= p;
+
+        for row in 0..n {
+            if row != col {
+                let k = m[row][col];
+                for j in 0..n {
+                    m[row][j] -= k * m[col][j];
+                    inv[row][j] -= k * inv[col][j];
+                }
+                x[row] -= k * x[col];
+            }
+        }
+    }
+    Ok((x, inv))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{solve, Sample, TimeSeries};
+    use crate::prelude::{Duration, Epoch};
+    #[test]
+    fn gauss_jordan() {
+        let a = vec![vec![2.0, 1.0], vec![1.0, 3.0]];
+        let (x, inv) = solve(&a, &[3.0, 5.0]).unwrap();
+        assert!((x[0] - 0.8).abs() < 1.0E-12);
+        assert!((x[1] - 1.4).abs() < 1.0E-12);
+        assert!((inv[0][0] - 0.6).abs() < 1.0E-12);
+        assert!((inv[0][1] + 0.2).abs() < 1.0E-12);
+        assert!(solve(&[vec![1.0, 1.0], vec![1.0, 1.0]], &[1.0, 1.0]).is_err());
+    }
+    #[test]
+    fn window() {
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        let series = TimeSeries::new(
+            (0..10)
+                .rev()
+                .map(|i| Sample {
+                    epoch: t0 + i as f64 * Duration::from_days(1.0),
+                    value: 0.0,
+                    slope: None,
+                })
+                .collect(),
+        );
+        assert_eq!(series.first_epoch(), Some(t0));
+        assert_eq!(series.span(), Duration::from_days(9.0));
+        let window = series.window(t0 + Duration::from_days(2.0), t0 + Duration::from_days(4.0));
+        assert_eq!(window.len(), 3);
+    }
+}
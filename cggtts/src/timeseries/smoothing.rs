@@ -0,0 +1,245 @@
+//! Vondrak-Cepek smoothing
+use super::{ClockModel, Error, Sample, TimeSeries};
+use crate::prelude::Duration;
+use hifitime::Unit;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Vondrak-Cepek smoother. The smoothed values ŷ minimize
+///
+///   ∫ (ŷ - y)² dt + ε ∫ (ŷ''')² dt
+///
+/// both integrals being approximated on the (possibly uneven) sampling
+/// of the input series. The frequency response is 1 / (1 + ε ω⁶):
+/// quadratics pass through unchanged and fluctuations of period T
+/// are attenuated by half when ε = (T / 2π)⁶.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VondrakSmoother {
+    /// Smoothing factor ε [d⁶]. The larger, the smoother.
+    pub smoothing: f64,
+}
+
+impl Default for VondrakSmoother {
+    /// Builds a smoother with a 1 day cutoff period
+    fn default() -> Self {
+        Self { smoothing: 0.0 }.cutoff_period(Duration::from_days(1.0))
+    }
+}
+
+impl VondrakSmoother {
+    /// Returns a smoother with given smoothing factor ε, expressed in [d⁶]
+    pub fn smoothing(&self, smoothing: f64) -> Self {
+        Self { smoothing }
+    }
+    /// Returns a smoother that attenuates by half the fluctuations
+    /// of given period. Faster fluctuations are attenuated further.
+    pub fn cutoff_period(&self, period: Duration) -> Self {
+        let t = period.to_unit(Unit::Day);
+        self.smoothing((t / 2.0 / std::f64::consts::PI).powi(6))
+    }
+}
+
+/*
+ * Solves the symmetric positive definite banded system M x = b,
+ * M being stored as its lower diagonals: m[i][k] = M(i, i - k).
+ */
+fn banded_cholesky(m: &mut [Vec<f64>], b: &mut [f64]) -> Result<(), Error> {
+    let n = b.len();
+    let w = m[0].len() - 1;
+    for i in 0..n {
+        for k in (0..=w.min(i)).rev() {
+            let j = i - k;
+            let mut sum = m[i][k];
+            for l in 1..=(w - k).min(j) {
+                sum -= m[i][k + l] * m[j][l];
+            }
+            if k == 0 {
+                if sum <= 0.0 {
+                    return Err(Error::SingularMatrix);
+                }
+                m[i][0] = sum.sqrt();
+            } else {
+                m[i][k] = sum / m[j][0];
+            }
+        }
+    }
+    // forward substitution: L z = b
+    for i in 0..n {
+        for k in 1..=w.min(i) {
+            b[i] -= m[i][k] * b[i - k];
+        }
+        b[i] /= m[i][0];
+    }
+    // backward substitution: Lᵀ x = z
+    for i in (0..n).rev() {
+        for k in 1..=w.min(n - 1 - i) {
+            b[i] -= m[i + k][k] * b[i + k];
+        }
+        b[i] /= m[i][0];
+    }
+    Ok(())
+}
+
+impl TimeSeries {
+    /// Smoothes Self with given [VondrakSmoother].
+    /// Slopes are not smoothed and are dropped from the result.
+    /// ```
+    /// use cggtts::prelude::{Duration, Epoch};
+    /// use cggtts::timeseries::{Sample, TimeSeries, VondrakSmoother};
+    /// let t0 = Epoch::from_mjd_utc(60250.0);
+    /// let series = TimeSeries::new(
+    ///     (0..100)
+    ///         .map(|i| Sample {
+    ///             epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+    ///             value: if i % 2 == 0 { 1.0E-9 } else { -1.0E-9 },
+    ///             slope: None,
+    ///         })
+    ///         .collect());
+    /// let smoother = VondrakSmoother::default()
+    ///     .cutoff_period(Duration::from_days(5.0));
+    /// let smoothed = series.vondrak_smoothing(&smoother)
+    ///     .unwrap();
+    /// for sample in smoothed.samples() {
+    ///     assert!(sample.value.abs() < 1.0E-10);
+    /// }
+    /// ```
+    pub fn vondrak_smoothing(&self, smoother: &VondrakSmoother) -> Result<Self, Error> {
+        let n = self.samples.len();
+        if n < 4 {
+            return Err(Error::NotEnoughSamples(4));
+        }
+        let t0 = self.samples[0].epoch;
+        let x: Vec<f64> = self
+            .samples
+            .iter()
+            .map(|s| (s.epoch - t0).to_unit(Unit::Day))
+            .collect();
+
+        if x.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(Error::SingularMatrix);
+        }
+
+        /*
+         * Normal equations are very poorly conditioned for long cutoff periods.
+         * Quadratics are left untouched by the smoother: we only smooth
+         * the residuals to a quadratic fit, which limits the numerical noise.
+         */
+        let fit = self.clock_fit(ClockModel::LinearDrift)?;
+
+        let h = (x[n - 1] - x[0]) / (n - 1) as f64;
+        let mut m: Vec<Vec<f64>> = (0..n).map(|_| vec![h, 0.0, 0.0, 0.0]).collect();
+        let mut b: Vec<f64> = fit.residuals.samples().map(|s| h * s.value).collect();
+
+        for k in 0..n - 3 {
+            // y''' ≈ 6 × third divided difference, over [x(k+1), x(k+2)]
+            let mut c = [0.0_f64; 4];
+            for (j, c) in c.iter_mut().enumerate() {
+                let mut den = 1.0;
+                for l in 0..4 {
+                    if l != j {
+                        den *= x[k + j] - x[k + l];
+                    }
+                }
+                *c = 6.0 / den;
+            }
+            let w = smoother.smoothing * (x[k + 2] - x[k + 1]);
+            for i in 0..4 {
+                for j in 0..=i {
+                    m[k + i][i - j] += w * c[i] * c[j];
+                }
+            }
+        }
+
+        banded_cholesky(&mut m, &mut b)?;
+
+        Ok(Self {
+            samples: self
+                .samples
+                .iter()
+                .zip(b)
+                .map(|(s, residual)| Sample {
+                    epoch: s.epoch,
+                    value: fit.phase(s.epoch) + residual,
+                    slope: None,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{banded_cholesky, VondrakSmoother};
+    use crate::prelude::{Duration, Epoch};
+    use crate::timeseries::{Sample, TimeSeries};
+    use rand::{distributions::Distribution, SeedableRng};
+    #[test]
+    fn cholesky() {
+        // tridiagonal [[4, 1, 0], [1, 4, 1], [0, 1, 4]]
+        let mut m = vec![vec![4.0, 0.0], vec![4.0, 1.0], vec![4.0, 1.0]];
+        let mut b = vec![5.0, 6.0, 5.0];
+        banded_cholesky(&mut m, &mut b).unwrap();
+        for x in b {
+            assert!((x - 1.0).abs() < 1.0E-12);
+        }
+    }
+    #[test]
+    fn cutoff_period() {
+        let smoother = VondrakSmoother::default().cutoff_period(Duration::from_days(1.0));
+        let expected = (1.0 / 2.0 / std::f64::consts::PI).powi(6);
+        assert!((smoother.smoothing - expected).abs() < 1.0E-15);
+        assert_eq!(VondrakSmoother::default(), smoother);
+    }
+    #[test]
+    fn quadratics_unchanged() {
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        let series = TimeSeries::new(
+            (0..200)
+                .map(|i| {
+                    // uneven sampling
+                    let dt = i as f64 * 960.0 + (i % 3) as f64 * 120.0;
+                    Sample {
+                        epoch: t0 + Duration::from_seconds(dt),
+                        value: 1.0E-9 + 1.0E-13 * dt + 1.0E-19 * dt.powi(2),
+                        slope: None,
+                    }
+                })
+                .collect(),
+        );
+        let smoother = VondrakSmoother::default().cutoff_period(Duration::from_days(5.0));
+        let smoothed = series.vondrak_smoothing(&smoother).unwrap();
+        assert_eq!(smoothed.len(), series.len());
+        for (a, b) in series.samples().zip(smoothed.samples()) {
+            assert_eq!(a.epoch, b.epoch);
+            assert!((a.value - b.value).abs() < 1.0E-18, "{:?} {:?}", a, b);
+        }
+    }
+    #[test]
+    fn noise_reduction() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let noise = rand::distributions::Uniform::new_inclusive(-1.0E-9, 1.0E-9);
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        let signal = |dt: f64| 5.0E-9 * (2.0 * std::f64::consts::PI * dt / 86_400.0 / 30.0).sin();
+        let series = TimeSeries::new(
+            (0..30 * 89)
+                .map(|i| {
+                    let dt = i as f64 * 960.0;
+                    Sample {
+                        epoch: t0 + Duration::from_seconds(dt),
+                        value: signal(dt) + noise.sample(&mut rng),
+                        slope: None,
+                    }
+                })
+                .collect(),
+        );
+        let smoother = VondrakSmoother::default().cutoff_period(Duration::from_days(2.0));
+        let smoothed = series.vondrak_smoothing(&smoother).unwrap();
+        for sample in smoothed.samples() {
+            let dt = (sample.epoch - t0).to_seconds();
+            assert!((sample.value - signal(dt)).abs() < 2.0E-10, "{:?}", sample);
+        }
+        assert!(TimeSeries::default().vondrak_smoothing(&smoother).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+//! Phase step and frequency jump (change point) detection
+use super::{solve, Sample, TimeSeries};
+use crate::prelude::Epoch;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/*
+ * Noise variance floor (1 fs²), avoids infinite statistics on noise free data
+ */
+const VARIANCE_FLOOR: f64 = 1.0E-30;
+
+/// Type of discontinuity
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum StepKind {
+    /// Phase (time) step, magnitude is expressed in [s]
+    Phase,
+    /// Frequency jump, magnitude is expressed in [s/s]
+    Frequency,
+}
+
+/// A discontinuity detected in a [TimeSeries]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Step {
+    /// Epoch of the first sample affected by this step
+    pub epoch: Epoch,
+    /// Type of discontinuity
+    pub kind: StepKind,
+    /// Step magnitude, either in [s] or [s/s]
+    pub magnitude: f64,
+    /// Step magnitude, expressed in standard deviations of its estimate
+    pub z_score: f64,
+    /// Confidence level (0 to 1) that this step is not a noise realization
+    pub confidence: f64,
+}
+
+/// Change point detector, applied to a [TimeSeries] with [TimeSeries::detect_steps].
+/// At each candidate Epoch, the `window` samples preceding and following it are fitted
+/// with a line plus a phase step, and with a line plus a continuous slope change
+/// (frequency jump), both located at the candidate Epoch.
+/// The most significant step is retained, removed from the series, and the process
+/// is repeated until no step exceeds the `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StepDetector {
+    /// Number of samples on each side of a candidate step
+    pub window: usize,
+    /// Detection threshold, in standard deviations
+    pub threshold: f64,
+}
+
+impl Default for StepDetector {
+    /// Builds a detector using 10 samples on each side (roughly 3 hours of
+    /// standard tracks) and a 5 sigma threshold
+    fn default() -> Self {
+        Self {
+            window: 10,
+            threshold: 5.0,
+        }
+    }
+}
+
+impl StepDetector {
+    /// Returns Self with desired number of samples on each side of a candidate step
+    pub fn window(&self, window: usize) -> Self {
+        let mut s = *self;
+        s.window = window;
+        s
+    }
+    /// Returns Self with desired detection threshold, in standard deviations
+    pub fn threshold(&self, threshold: f64) -> Self {
+        let mut s = *self;
+        s.threshold = threshold;
+        s
+    }
+}
+
+/*
+ * Least squares fit of a discontinuity model, centered on candidate Epoch t:
+ *  - phase step: y = a + b x + d H(x)
+ *  - frequency jump: y = a + b x + d x H(x)
+ * where H is the Heaviside step. Returns the estimated d and its z score.
+ */
+fn discontinuity(t: Epoch, samples: &[Sample], kind: StepKind) -> Option<(f64, f64)> {
+    let pts: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|s| ((s.epoch - t).to_seconds(), s.value))
+        .collect();
+
+    // normalized time, keeps the normal equations well conditioned
+    let scale = pts.iter().fold(1.0_f64, |max, (x, _)| max.max(x.abs()));
+
+    let row = |x: f64| -> [f64; 3] {
+        let x = x / scale;
+        let h = if x >= 0.0 { 1.0 } else { 0.0 };
+        match kind {
+            StepKind::Phase => [1.0, x, h],
+            StepKind::Frequency => [1.0, x, x * h],
+        }
+    };
+
+    let mut n = vec![vec![0.0; 3]; 3];
+    let mut b = vec![0.0; 3];
+    for (x, y) in pts.iter() {
+        let r = row(*x);
+        for i in 0..3 {
+            for j in 0..3 {
+                n[i][j] += r[i] * r[j];
+            }
+            b[i] += r[i] * y;
+        }
+    }
+
+    let (p, inv) = solve(&n, &b).ok()?;
+
+    let rss: f64 = pts
+        .iter()
+        .map(|(x, y)| {
+            let r = row(*x);
+            (y - r[0] * p[0] - r[1] * p[1] - r[2] * p[2]).powi(2)
+        })
+        .sum();
+
+    let var = (rss / (pts.len() - 3) as f64).max(VARIANCE_FLOOR);
+    let z = p[2].abs() / (var * inv[2][2]).sqrt();
+
+    match kind {
+        StepKind::Phase => Some((p[2], z)),
+        StepKind::Frequency => Some((p[2] / scale, z)),
+    }
+}
+
+/*
+ * Error function, Abramowitz & Stegun 7.1.26 (|error| < 1.5E-7)
+ */
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+impl TimeSeries {
+    /* most significant step candidate, if any */
+    fn most_significant_step(&self, detector: &StepDetector) -> Option<Step> {
+        let w = detector.window.max(3);
+        let n = self.samples.len();
+        if n < 2 * w {
+            return None;
+        }
+
+        let mut best = Option::<Step>::None;
+        for k in w..=n - w {
+            let t = self.samples[k].epoch;
+            let window = &self.samples[k - w..k + w];
+
+            let phase = discontinuity(t, window, StepKind::Phase);
+            let freq = discontinuity(t, window, StepKind::Frequency);
+
+            let (kind, magnitude, z_score) = match (phase, freq) {
+                (Some((phase, z_phase)), Some((freq, z_freq))) => {
+                    if z_phase >= z_freq {
+                        (StepKind::Phase, phase, z_phase)
+                    } else {
+                        (StepKind::Frequency, freq, z_freq)
+                    }
+                },
+                (Some((phase, z_phase)), None) => (StepKind::Phase, phase, z_phase),
+                (None, Some((freq, z_freq))) => (StepKind::Frequency, freq, z_freq),
+                (None, None) => continue,
+            };
+
+            if z_score < detector.threshold {
+                continue;
+            }
+
+            let is_best = match best {
+                Some(best) => z_score > best.z_score,
+                None => true,
+            };
+
+            if is_best {
+                best = Some(Step {
+                    epoch: t,
+                    kind,
+                    magnitude,
+                    z_score,
+                    confidence: erf(z_score / std::f64::consts::SQRT_2),
+                });
+            }
+        }
+        best
+    }
+
+    /// Detects phase steps and frequency jumps in Self,
+    /// for example on a station REFSYS series (see [TimeSeries::from_refsys]).
+    /// Returns detected steps in chronological order.
+    /// ```
+    /// use cggtts::prelude::{Duration, Epoch};
+    /// use cggtts::timeseries::{Sample, StepDetector, StepKind, TimeSeries};
+    /// let t0 = Epoch::from_mjd_utc(60250.0);
+    /// let series = TimeSeries::new(
+    ///     (0..89)
+    ///         .map(|i| Sample {
+    ///             epoch: t0 + i as f64 * Duration::from_seconds(960.0),
+    ///             value: if i < 40 { 0.0 } else { 10.0E-9 }
+    ///                 + if i % 2 == 0 { 1.0E-9 } else { -1.0E-9 },
+    ///             slope: None,
+    ///         })
+    ///         .collect(),
+    /// );
+    /// let steps = series.detect_steps(&StepDetector::default());
+    /// assert_eq!(steps.len(), 1);
+    /// assert_eq!(steps[0].kind, StepKind::Phase);
+    /// assert_eq!(steps[0].epoch, t0 + 40.0 * Duration::from_seconds(960.0));
+    /// assert!((steps[0].magnitude - 10.0E-9).abs() < 1.0E-9);
+    /// ```
+    pub fn detect_steps(&self, detector: &StepDetector) -> Vec<Step> {
+        let mut steps = Vec::<Step>::new();
+        let mut corrected = self.clone();
+        while let Some(step) = corrected.most_significant_step(detector) {
+            corrected = corrected.remove_steps(&[step]);
+            steps.push(step);
+            if steps.len() > self.samples.len() / detector.window.max(3) {
+                break; // cannot resolve more steps than this
+            }
+        }
+        steps.sort_by_key(|s| s.epoch);
+        steps
+    }
+
+    /// Returns a corrected TimeSeries, from which given steps were removed.
+    /// Samples prior the step Epoch are preserved, following samples are
+    /// corrected by the phase step, or by the phase accumulated since a frequency jump.
+    pub fn remove_steps(&self, steps: &[Step]) -> Self {
+        let mut samples = self.samples.clone();
+        for step in steps {
+            for s in samples.iter_mut().filter(|s| s.epoch >= step.epoch) {
+                match step.kind {
+                    StepKind::Phase => {
+                        s.value -= step.magnitude;
+                    },
+                    StepKind::Frequency => {
+                        s.value -= step.magnitude * (s.epoch - step.epoch).to_seconds();
+                        s.slope = s.slope.map(|slope| slope - step.magnitude);
+                    },
+                }
+            }
+        }
+        Self { samples }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{erf, StepDetector, StepKind};
+    use crate::prelude::{Duration, Epoch};
+    use crate::timeseries::{Sample, TimeSeries};
+    use rand::{distributions::Distribution, SeedableRng};
+
+    fn series(f: impl Fn(f64) -> f64, sigma: f64) -> TimeSeries {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let noise = rand::distributions::Uniform::new_inclusive(-sigma, sigma);
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        TimeSeries::new(
+            (0..3 * 89)
+                .map(|i| {
+                    let dt = i as f64 * 960.0;
+                    Sample {
+                        epoch: t0 + Duration::from_seconds(dt),
+                        value: f(dt) + noise.sample(&mut rng),
+                        slope: None,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn error_function() {
+        assert!(erf(0.0).abs() < 1.0E-7);
+        assert!((erf(1.0) - 0.8427007929).abs() < 1.0E-6);
+        assert!((erf(-1.0) + 0.8427007929).abs() < 1.0E-6);
+        assert!((erf(3.0) - 0.9999779095).abs() < 1.0E-6);
+    }
+
+    #[test]
+    fn no_steps() {
+        let s = series(|dt| 1.0E-13 * dt, 1.0E-9);
+        assert!(s.detect_steps(&StepDetector::default()).is_empty());
+    }
+
+    #[test]
+    fn phase_steps() {
+        let t_step = 100.0 * 960.0;
+        let s = series(
+            |dt| {
+                1.0E-13 * dt
+                    + if dt >= t_step { 15.0E-9 } else { 0.0 }
+                    + if dt >= 2.0 * t_step { -8.0E-9 } else { 0.0 }
+            },
+            1.0E-9,
+        );
+        let steps = s.detect_steps(&StepDetector::default());
+        assert_eq!(steps.len(), 2, "{:?}", steps);
+        let t0 = Epoch::from_mjd_utc(60250.0);
+        assert_eq!(steps[0].kind, StepKind::Phase);
+        assert_eq!(steps[0].epoch, t0 + Duration::from_seconds(t_step));
+        assert!((steps[0].magnitude - 15.0E-9).abs() < 1.5E-9);
+        assert!(steps[0].confidence > 0.999);
+        assert_eq!(steps[1].kind, StepKind::Phase);
+        assert_eq!(steps[1].epoch, t0 + Duration::from_seconds(2.0 * t_step));
+        assert!((steps[1].magnitude + 8.0E-9).abs() < 1.5E-9);
+
+        let corrected = s.remove_steps(&steps);
+        assert!(corrected.detect_steps(&StepDetector::default()).is_empty());
+    }
+
+    #[test]
+    fn frequency_jump() {
+        let t_step = 150.0 * 960.0;
+        let s = series(
+            |dt| {
+                if dt >= t_step {
+                    5.0E-12 * (dt - t_step)
+                } else {
+                    0.0
+                }
+            },
+            1.0E-10,
+        );
+        let steps = s.detect_steps(&StepDetector::default().window(20));
+        assert_eq!(steps.len(), 1, "{:?}", steps);
+        assert_eq!(steps[0].kind, StepKind::Frequency);
+        assert!((steps[0].magnitude - 5.0E-12).abs() < 1.0E-13);
+        let t = steps[0].epoch - Epoch::from_mjd_utc(60250.0);
+        assert!((t.to_seconds() - t_step).abs() <= 960.0);
+    }
+}
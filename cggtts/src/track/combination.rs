@@ -0,0 +1,300 @@
+//! Dual frequency combination of single frequency tracks
+use super::{GlonassChannel, IonosphericData, Track, TrackData};
+use gnss::prelude::Constellation;
+use thiserror::Error;
+
+/// Dual frequency combination errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum CombinationError {
+    /// Both tracks should describe the same SV
+    #[error("tracks do not describe the same SV")]
+    SVMismatch,
+    /// Both tracks should share the same Epoch and duration
+    #[error("tracks do not share the same Epoch and duration")]
+    EpochMismatch,
+    /// Carrier frequency could not be determined from
+    /// frequency code (and Glonass channel)
+    #[error("unknown carrier frequency for \"{0}\"")]
+    UnknownCarrier(String),
+    /// Both tracks were measured on the same carrier frequency
+    #[error("tracks share the same carrier frequency")]
+    SameCarrier,
+}
+
+/* Glonass FDMA carrier frequency [Hz] for given base, step and channel */
+fn glonass_fdma(base: f64, step: f64, fr: GlonassChannel) -> Option<f64> {
+    match fr {
+        GlonassChannel::ChanNum(k) => Some(base + k as f64 * step),
+        GlonassChannel::Unknown => None,
+    }
+}
+
+/*
+ * Carrier frequency [Hz] for given constellation and frequency code,
+ * see [Track::carrier_frequency]
+ */
+pub(crate) fn carrier_frequency(
+    constellation: Constellation,
+    frc: &str,
+    fr: GlonassChannel,
+) -> Option<f64> {
+    let band = frc.get(0..2)?;
+    let mhz = match constellation {
+        Constellation::Glonass => match band {
+            "L1" | "G1" => glonass_fdma(1602.0, 0.5625, fr)?,
+            "L2" | "G2" => glonass_fdma(1246.0, 0.4375, fr)?,
+            "L4" => 1600.995,
+            "L6" => 1248.06,
+            "L3" => 1202.025,
+            _ => return None,
+        },
+        Constellation::Galileo => match frc {
+            "E1" => 1575.42,
+            "E5a" => 1176.45,
+            "E5b" => 1207.14,
+            "E5" => 1191.795,
+            "E6" => 1278.75,
+            _ => match band {
+                "L1" => 1575.42,
+                "L5" => 1176.45,
+                "L7" => 1207.14,
+                "L8" => 1191.795,
+                "L6" => 1278.75,
+                _ => return None,
+            },
+        },
+        Constellation::BeiDou => match band {
+            "B1" | "L2" => 1561.098,
+            "B2" | "L7" => 1207.14,
+            "B3" | "L6" => 1268.52,
+            "L1" => 1575.42,
+            "L5" => 1176.45,
+            "L8" => 1191.795,
+            _ => return None,
+        },
+        Constellation::IRNSS => match band {
+            "L5" => 1176.45,
+            "L9" => 2492.028,
+            "L1" => 1575.42,
+            _ => return None,
+        },
+        Constellation::QZSS => match band {
+            "L1" => 1575.42,
+            "L2" => 1227.60,
+            "L5" => 1176.45,
+            "L6" => 1278.75,
+            _ => return None,
+        },
+        c if c == Constellation::GPS || c.is_sbas() => match band {
+            "L1" => 1575.42,
+            "L2" => 1227.60,
+            "L5" => 1176.45,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(mhz * 1.0E6)
+}
+
+impl Track {
+    /// Returns the carrier frequency [Hz] this track was measured on,
+    /// deduced from the frequency code (both RINEX like "L1C" codes
+    /// and signal names like "E5a" or "B1" are supported).
+    /// For Glonass FDMA signals, the channel (`FR`) is interpreted
+    /// as the frequency number k and must be known.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let trk = cggtts.tracks()
+    ///     .find(|trk| trk.frc == "L2P")
+    ///     .unwrap();
+    /// assert_eq!(trk.carrier_frequency(), Some(1227.60E6));
+    /// ```
+    pub fn carrier_frequency(&self) -> Option<f64> {
+        carrier_frequency(self.sv.constellation, &self.frc, self.fr)
+    }
+    /// Forms the ionosphere-free (L3) combination of Self and `rhs`,
+    /// two tracks of the same SV, realized at the same Epoch
+    /// on two different carrier frequencies.
+    /// The modeled ionospheric delay is first removed from both REFSV and REFSYS,
+    /// then the combination (f1² X1 - f2² X2) / (f1² - f2²) is formed.
+    /// The resulting track is marked with the L3 code, followed by the attribute
+    /// of `rhs` (for example "L3P" when combining "L1P" and "L2P"), like
+    /// [Track::combined_frc] expects it. Its measured ionospheric delay (MSIO)
+    /// is expressed on the carrier frequency of Self.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let l1p = cggtts.tracks()
+    ///     .find(|trk| trk.frc == "L1P")
+    ///     .unwrap();
+    /// let l2p = cggtts.tracks()
+    ///     .find(|trk| trk.sv == l1p.sv && trk.epoch == l1p.epoch && trk.frc == "L2P")
+    ///     .unwrap();
+    /// let l3p = l1p.ionosphere_free(l2p)
+    ///     .unwrap();
+    /// assert_eq!(l3p.frc, "L3P");
+    /// assert_eq!(l3p.combined_frc(), Some(("L1P".to_string(), "L2P".to_string())));
+    /// assert!(l3p.has_ionospheric_data());
+    /// ```
+    pub fn ionosphere_free(&self, rhs: &Self) -> Result<Self, CombinationError> {
+        if self.sv != rhs.sv {
+            return Err(CombinationError::SVMismatch);
+        }
+        if self.epoch != rhs.epoch || self.duration != rhs.duration {
+            return Err(CombinationError::EpochMismatch);
+        }
+        let f1 = self
+            .carrier_frequency()
+            .ok_or(CombinationError::UnknownCarrier(self.frc.clone()))?;
+        let f2 = rhs
+            .carrier_frequency()
+            .ok_or(CombinationError::UnknownCarrier(rhs.frc.clone()))?;
+        if f1 == f2 {
+            return Err(CombinationError::SameCarrier);
+        }
+
+        let (g1, g2) = (f1.powi(2), f2.powi(2));
+        let iono_free = |x1: f64, x2: f64| (g1 * x1 - g2 * x2) / (g1 - g2);
+        let measured_iono = |x1: f64, x2: f64| (x2 - x1) * g2 / (g1 - g2);
+
+        let (d1, d2) = (&self.data, &rhs.data);
+
+        let data = TrackData {
+            refsv: iono_free(d1.refsv + d1.mdio, d2.refsv + d2.mdio),
+            srsv: iono_free(d1.srsv + d1.smdi, d2.srsv + d2.smdi),
+            refsys: iono_free(d1.refsys + d1.mdio, d2.refsys + d2.mdio),
+            srsys: iono_free(d1.srsys + d1.smdi, d2.srsys + d2.smdi),
+            dsg: ((g1 * d1.dsg).powi(2) + (g2 * d2.dsg).powi(2)).sqrt() / (g1 - g2).abs(),
+            ..*d1
+        };
+
+        let iono = IonosphericData {
+            msio: measured_iono(d1.refsys + d1.mdio, d2.refsys + d2.mdio),
+            smsi: measured_iono(d1.srsys + d1.smdi, d2.srsys + d2.smdi),
+            isg: (d1.dsg.powi(2) + d2.dsg.powi(2)).sqrt() * g2 / (g1 - g2).abs(),
+        };
+
+        let frc = match rhs.frc.len() {
+            3 if rhs.frc.starts_with('L') => format!("L3{}", &rhs.frc[2..]),
+            _ => "L3".to_string(),
+        };
+
+        Ok(Self {
+            data,
+            iono: Some(iono),
+            frc,
+            ..self.clone()
+        })
+    }
+    /// Returns the frequency codes this ionosphere-free (L3) track was formed with,
+    /// or None if this is not an L3 track. Since the CGGTTS format only
+    /// describes the L3 code, the usual combination for this constellation is assumed:
+    /// L1C/L5Q for Galileo, L2I/L7I for BeiDou, L1C/L2C or L1P/L2P otherwise.
+    /// ```
+    /// use cggtts::prelude::Track;
+    /// let trk = Track::default()
+    ///     .with_carrier_code("L3P");
+    /// assert_eq!(trk.combined_frc(), Some(("L1P".to_string(), "L2P".to_string())));
+    /// ```
+    pub fn combined_frc(&self) -> Option<(String, String)> {
+        let attribute = self.frc.strip_prefix("L3")?;
+        let (lhs, rhs) = match self.sv.constellation {
+            Constellation::Galileo => ("L1C", "L5Q"),
+            Constellation::BeiDou => ("L2I", "L7I"),
+            _ => match attribute {
+                "C" => ("L1C", "L2C"),
+                "P" | "W" | "Y" => ("L1P", "L2P"),
+                _ => return None,
+            },
+        };
+        Some((lhs.to_string(), rhs.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CombinationError;
+    use crate::prelude::{Duration, CGGTTS, SV};
+    use crate::track::GlonassChannel;
+    use std::str::FromStr;
+    #[test]
+    fn carrier_frequency() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        for trk in cggtts.tracks() {
+            let expected = match trk.frc.as_str() {
+                "L1C" | "L1P" => 1575.42E6,
+                "L2C" | "L2P" => 1227.60E6,
+                "L5C" => 1176.45E6,
+                _ => continue,
+            };
+            assert_eq!(trk.carrier_frequency(), Some(expected));
+        }
+        let mut trk = cggtts.tracks[0]
+            .with_sv(SV::from_str("R05").unwrap())
+            .with_carrier_code("L1C");
+        assert!(trk.carrier_frequency().is_none(), "unknown FDMA channel");
+        trk.fr = GlonassChannel::ChanNum(2);
+        assert_eq!(trk.carrier_frequency(), Some(1603.125E6));
+        let trk = trk
+            .with_sv(SV::from_str("E01").unwrap())
+            .with_carrier_code("E5a");
+        assert_eq!(trk.carrier_frequency(), Some(1176.45E6));
+    }
+    #[test]
+    fn ionosphere_free() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        let mut nb = 0;
+        for l1p in cggtts.tracks().filter(|trk| trk.frc == "L1P") {
+            let l2p = cggtts
+                .tracks()
+                .find(|trk| trk.sv == l1p.sv && trk.epoch == l1p.epoch && trk.frc == "L2P")
+                .unwrap();
+            let l3p = l1p.ionosphere_free(l2p).unwrap();
+            assert_eq!(l3p.frc, "L3P");
+            assert_eq!(
+                l3p.combined_frc(),
+                Some(("L1P".to_string(), "L2P".to_string()))
+            );
+            assert_eq!(l3p.sv, l1p.sv);
+            assert_eq!(l3p.epoch, l1p.epoch);
+            // MSIO is provided by the receiver: results should be close,
+            // within rounding of the CGGTTS format
+            let msio = l3p.iono.unwrap().msio;
+            let expected = l1p.iono.unwrap().msio;
+            assert!((msio - expected).abs() < 0.5E-9, "{} {}", msio, expected);
+            // L3 combination is consistent with a measured ionospheric delay
+            let l1 = l1p.data.refsys + l1p.data.mdio;
+            let iono_free = l3p.data.refsys;
+            assert!((l1 - msio - iono_free).abs() < 1.0E-15);
+            nb += 1;
+        }
+        assert!(nb > 0);
+
+        let l1p = cggtts.tracks().find(|trk| trk.frc == "L1P").unwrap();
+        let l1c = cggtts
+            .tracks()
+            .find(|trk| trk.sv == l1p.sv && trk.epoch == l1p.epoch && trk.frc == "L1C")
+            .unwrap();
+        assert_eq!(l1p.ionosphere_free(l1c), Err(CombinationError::SameCarrier));
+        let l2p = cggtts
+            .tracks()
+            .find(|trk| trk.sv == l1p.sv && trk.epoch == l1p.epoch && trk.frc == "L2P")
+            .unwrap();
+        let l3 = l1c.ionosphere_free(l2p).unwrap();
+        assert_eq!(l3.frc, "L3P");
+        let other = cggtts.tracks().find(|trk| trk.sv != l1p.sv).unwrap();
+        assert_eq!(
+            l1p.ionosphere_free(other),
+            Err(CombinationError::SVMismatch)
+        );
+        let mut later = l1c.with_carrier_code("L2P");
+        later.epoch += Duration::from_seconds(960.0);
+        assert_eq!(
+            l1p.ionosphere_free(&later),
+            Err(CombinationError::EpochMismatch)
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Multi SV, multi signal track formation from raw measurements
+use crate::prelude::{Constellation, Duration, Epoch, SV};
+use crate::track::{
+    CommonViewClass, FitData, FitMode, GlonassChannel, SVTracker, Scheduler, Track,
+};
+use std::collections::BTreeMap;
+
+/// Observation of a single signal from a single SV, at a given Epoch
+#[derive(Debug, Clone)]
+pub struct Observation {
+    /// SV being observed
+    pub sv: SV,
+    /// Carrier frequency standard 3 letter code
+    pub frc: String,
+    /// Issue of Ephemeris in use, see [crate::track::TrackData::ioe]
+    pub ioe: u16,
+    /// Hardware / receiver channel [0:99], 0 if Unknown
+    pub hc: u8,
+    /// Glonass Channel Frequency, for Glonass SV
+    pub fr: GlonassChannel,
+    /// Measurements
+    pub data: FitData,
+}
+
+/* tracking state of a single (SV, frc) */
+#[derive(Debug, Clone)]
+struct Channel {
+    tracker: SVTracker,
+    ioe: u16,
+    hc: u8,
+    fr: GlonassChannel,
+    last: Epoch,
+}
+
+/// TrackGenerator forms synchronous CGGTTS tracks from a stream of observations,
+/// for as many SV and signals as needed. It holds one [SVTracker] per (SV, frc),
+/// follows the [Scheduler] and emits [Track]s at the end of each scheduled track.
+/// Trackers are reset on data gaps and on IOE changes, only complete
+/// tracks are emitted.
+/// Tracks are declared [CommonViewClass::MultiChannel] when several SV
+/// were tracked during the scheduled track, [CommonViewClass::SingleChannel]
+/// otherwise, unless the class is forced with [TrackGenerator::with_class].
+#[derive(Debug, Clone)]
+pub struct TrackGenerator {
+    scheduler: Scheduler,
+    sampling_period: Duration,
+    fit_mode: FitMode,
+    gap_tolerance: Duration,
+    min_completeness: f64,
+    class: Option<CommonViewClass>,
+    /* (start, midpoint, end) of the ongoing track */
+    slot: Option<(Epoch, Epoch, Epoch)>,
+    channels: BTreeMap<(SV, String), Channel>,
+}
+
+impl TrackGenerator {
+    /// Builds a new TrackGenerator, following given schedule,
+    /// for observations that are sampled every `sampling_period`.
+    pub fn new(scheduler: Scheduler, sampling_period: Duration) -> Self {
+        Self {
+            scheduler,
+            sampling_period,
+            fit_mode: FitMode::default(),
+            gap_tolerance: Duration::ZERO,
+            min_completeness: 1.0,
+            class: None,
+            slot: None,
+            channels: BTreeMap::new(),
+        }
+    }
+    /// Returns a TrackGenerator that uses given fitting method
+    pub fn with_fit_mode(&self, mode: FitMode) -> Self {
+        let mut s = self.clone();
+        s.fit_mode = mode;
+        s
+    }
+    /// Returns a TrackGenerator that tolerates data gaps, see [SVTracker::with_gap_tolerance]
+    pub fn with_gap_tolerance(&self, tolerance: Duration) -> Self {
+        let mut s = self.clone();
+        s.gap_tolerance = tolerance;
+        s
+    }
+    /// Returns a TrackGenerator that forms incomplete tracks,
+    /// see [SVTracker::with_min_completeness]
+    pub fn with_min_completeness(&self, ratio: f64) -> Self {
+        let mut s = self.clone();
+        s.min_completeness = ratio;
+        s
+    }
+    /// Returns a TrackGenerator that declares all tracks with given [CommonViewClass],
+    /// whatever the number of SV tracked simultaneously
+    pub fn with_class(&self, class: CommonViewClass) -> Self {
+        let mut s = self.clone();
+        s.class = Some(class);
+        s
+    }
+    /* scheduled track `t` falls in */
+    fn slot(&self, t: Epoch) -> Option<(Epoch, Epoch, Epoch)> {
+        let mjd = t.to_mjd_utc_days().floor() as u32;
+        self.scheduler
+            .tracks_for_mjd(mjd)
+            .find(|(start, _, end)| *start <= t && t <= *end)
+    }
+    /* fits and releases all ongoing tracks */
+    fn release(&mut self) -> Vec<Track> {
+        let mut tracks = Vec::<Track>::new();
+        let (start, midpoint, _) = match self.slot.take() {
+            Some(slot) => slot,
+            None => return tracks,
+        };
+        let class = self.class.unwrap_or_else(|| {
+            let mut svs = self.channels.keys().map(|(sv, _)| sv);
+            let first = svs.next();
+            if svs.any(|sv| Some(sv) != first) {
+                CommonViewClass::MultiChannel
+            } else {
+                CommonViewClass::SingleChannel
+            }
+        });
+        for ((sv, frc), channel) in std::mem::take(&mut self.channels) {
+            let fit = channel.tracker.fit(
+                channel.ioe,
+                self.scheduler.trk_duration,
+                self.sampling_period,
+                midpoint,
+            );
+            let ((elevation, azimuth), data, iono) = match fit {
+                Ok(fit) => fit,
+                Err(_) => continue, // incomplete track
+            };
+            let track = match sv.constellation {
+                Constellation::Glonass => Track::new_glonass(
+                    sv,
+                    start,
+                    self.scheduler.trk_duration,
+                    class,
+                    elevation,
+                    azimuth,
+                    data,
+                    iono,
+                    channel.hc,
+                    channel.fr,
+                    &frc,
+                ),
+                _ => Track::new(
+                    sv,
+                    start,
+                    self.scheduler.trk_duration,
+                    class,
+                    elevation,
+                    azimuth,
+                    data,
+                    iono,
+                    channel.hc,
+                    &frc,
+                ),
+            };
+            tracks.push(track);
+        }
+        tracks
+    }
+    /// Latches all observations made at Epoch `t`, which should be expressed in UTC
+    /// and streamed in chronological order.
+    /// Returns the tracks that were completed, if any.
+    /// Observations made outside scheduled tracks (acquisition time) are dropped.
+    pub fn latch(&mut self, t: Epoch, observations: &[Observation]) -> Vec<Track> {
+        let mut tracks = Vec::<Track>::new();
+        if let Some((_, _, end)) = self.slot {
+            if t > end {
+                tracks = self.release();
+            }
+        }
+
+        let slot = match self.slot(t) {
+            Some(slot) => slot,
+            None => return tracks,
+        };
+        self.slot = Some(slot);
+
+        let tracker = SVTracker::default()
+            .with_fit_mode(self.fit_mode)
+            .with_gap_tolerance(self.gap_tolerance)
+            .with_min_completeness(self.min_completeness);
+
+        for obs in observations {
+            let key = (obs.sv, obs.frc.clone());
+            let channel = self.channels.entry(key).or_insert_with(|| Channel {
+                tracker: tracker.clone(),
+                ioe: obs.ioe,
+                hc: obs.hc,
+                fr: obs.fr,
+                last: t,
+            });
+            if channel.tracker.not_empty() {
+                if t <= channel.last {
+                    continue; // replayed or duplicate sample
+                }
+                let gap = t - channel.last > self.sampling_period + self.gap_tolerance;
+                if gap || obs.ioe != channel.ioe {
+                    // data gap or new ephemeris: restart
+                    channel.tracker.reset();
+                }
+            }
+            if channel
+                .tracker
+                .latch_measurement(t, obs.data.clone())
+                .is_ok()
+            {
+                channel.ioe = obs.ioe;
+                channel.hc = obs.hc;
+                channel.fr = obs.fr;
+                channel.last = t;
+            }
+        }
+
+        let (_, _, end) = slot;
+        if t >= end {
+            tracks.append(&mut self.release());
+        }
+        tracks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Observation, TrackGenerator};
+    use crate::prelude::{CommonViewClass, Duration, Epoch, SV};
+    use crate::track::{FitData, GlonassChannel, Scheduler};
+    use std::str::FromStr;
+
+    fn observation(sv: &str, frc: &str, ioe: u16, dt: f64) -> Observation {
+        let sv = SV::from_str(sv).unwrap();
+        Observation {
+            sv,
+            frc: frc.to_string(),
+            ioe,
+            hc: sv.prn,
+            fr: match sv.prn {
+                7 => GlonassChannel::ChanNum(5),
+                _ => GlonassChannel::Unknown,
+            },
+            data: FitData {
+                refsv: 1.0E-6,
+                refsys: 1.0E-7 + 1.0E-12 * dt,
+                mdtr: 1.0E-8,
+                elevation: 30.0,
+                azimuth: 90.0,
+                mdio: Some(5.0E-9),
+                msio: None,
+            },
+        }
+    }
+    #[test]
+    fn track_generation() {
+        let scheduler = Scheduler::default();
+        let mut generator = TrackGenerator::new(scheduler.clone(), Duration::from_seconds(1.0));
+
+        let mjd = 59506;
+        let slots: Vec<_> = scheduler.tracks_for_mjd(mjd).collect();
+
+        let mut tracks = Vec::new();
+        // stream 3 slots worth of data
+        let t0 = Epoch::from_mjd_utc(mjd as f64);
+        for i in 0..3 * 960 + 120 {
+            let t = t0 + Duration::from_seconds(i as f64);
+            let dt = i as f64;
+            let mut obs = vec![
+                observation("G01", "L1C", 10, dt),
+                observation("G01", "L2P", 10, dt),
+                observation("R07", "L1C", 20, dt),
+            ];
+            // E05 disappears in the middle of the 2nd track
+            if !(1_400..1_410).contains(&i) {
+                obs.push(observation("E05", "L1C", 30, dt));
+            }
+            // G01 changes ephemeris during 3rd track
+            if i > 2_500 {
+                obs[0].ioe = 11;
+                obs[1].ioe = 11;
+            }
+            tracks.append(&mut generator.latch(t, &obs));
+        }
+
+        let (start, _, _) = slots[0];
+        let first: Vec<_> = tracks.iter().filter(|trk| trk.epoch == start).collect();
+        assert_eq!(first.len(), 4);
+
+        let (start, _, _) = slots[1];
+        let second: Vec<_> = tracks.iter().filter(|trk| trk.epoch == start).collect();
+        assert_eq!(second.len(), 3);
+        assert!(second
+            .iter()
+            .all(|trk| trk.sv != SV::from_str("E05").unwrap()));
+
+        let (start, _, _) = slots[2];
+        let third: Vec<_> = tracks.iter().filter(|trk| trk.epoch == start).collect();
+        assert_eq!(third.len(), 2);
+
+        assert_eq!(tracks.len(), 9);
+        for trk in &tracks {
+            assert!(trk.follows_bipm_specs());
+            assert_eq!(trk.class, CommonViewClass::MultiChannel);
+            assert_eq!(trk.hc, trk.sv.prn);
+            assert!((trk.data.refsys - 1.0E-7).abs() < 1.0E-8);
+            match trk.sv.prn {
+                1 => assert_eq!(trk.data.ioe, 10),
+                5 => assert_eq!(trk.data.ioe, 30),
+                7 => {
+                    assert_eq!(trk.data.ioe, 20);
+                    assert_eq!(trk.fr, GlonassChannel::ChanNum(5));
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+    #[test]
+    fn track_class() {
+        let scheduler = Scheduler::default();
+        let t0 = Epoch::from_mjd_utc(59506.0);
+        let generator = TrackGenerator::new(scheduler, Duration::from_seconds(1.0));
+
+        // single SV, even on several signals
+        let mut single = generator.clone();
+        let mut tracks = Vec::new();
+        for i in 0..960 + 120 {
+            let t = t0 + Duration::from_seconds(i as f64);
+            let obs = [
+                observation("G01", "L1C", 10, i as f64),
+                observation("G01", "L2P", 10, i as f64),
+            ];
+            tracks.append(&mut single.latch(t, &obs));
+        }
+        assert_eq!(tracks.len(), 2);
+        assert!(tracks
+            .iter()
+            .all(|trk| trk.class == CommonViewClass::SingleChannel));
+
+        // forced class
+        let mut forced = generator.with_class(CommonViewClass::MultiChannel);
+        let mut tracks = Vec::new();
+        for i in 0..960 + 120 {
+            let t = t0 + Duration::from_seconds(i as f64);
+            let obs = [observation("G01", "L1C", 10, i as f64)];
+            tracks.append(&mut forced.latch(t, &obs));
+        }
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].class, CommonViewClass::MultiChannel);
+    }
+}
@@ -7,13 +7,26 @@ pub use glonass::GlonassChannel;
 mod class;
 pub use class::CommonViewClass;
 
+mod combination;
+pub use combination::CombinationError;
+
+pub(crate) use combination::carrier_frequency;
+
 #[cfg(feature = "scheduler")]
 #[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
 mod scheduler;
 
 #[cfg(feature = "scheduler")]
 #[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
-pub use scheduler::{FitData, SVTracker, Scheduler};
+pub use scheduler::{FitData, FitError, FitMode, LatchError, LatchPolicy, SVTracker, Scheduler};
+
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+mod generator;
+
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+pub use generator::{Observation, TrackGenerator};
 
 use gnss::prelude::{Constellation, SV};
 use hifitime::{Duration, Epoch, Unit};
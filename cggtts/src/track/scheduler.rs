@@ -3,10 +3,29 @@ use polyfit_rs::polyfit_rs::polyfit;
 use std::collections::BTreeMap;
 use thiserror::Error;
 
+/* least squares line fit, returns (slope, value at x=0) */
+fn linear_fit(xs: &[f64], ys: &[f64]) -> Result<(f64, f64), FitError> {
+    if xs.len() < 2 {
+        return Err(FitError::LinearRegressionFailure);
+    }
+    let fit = polyfit(xs, ys, 1).map_err(|_| FitError::LinearRegressionFailure)?;
+    Ok((fit[1], fit[0]))
+}
+
+/* RMS of the residuals about the (slope, value at x=0) line */
+fn rms_residuals(xs: &[f64], ys: &[f64], slope: f64, intercept: f64) -> f64 {
+    let sum = xs
+        .iter()
+        .zip(ys.iter())
+        .map(|(x, y)| (y - slope * x - intercept).powi(2))
+        .sum::<f64>();
+    (sum / xs.len() as f64).sqrt()
+}
+
 fn linear_reg_2d(i: (f64, f64), j: (f64, f64)) -> (f64, f64) {
-    let (_, y_i) = i;
+    let (x_i, y_i) = i;
     let (x_j, y_j) = j;
-    let a = y_j - y_i;
+    let a = (y_j - y_i) / (x_j - x_i);
     let b = y_j - a * x_j;
     (a, b)
 }
@@ -29,13 +48,77 @@ pub enum FitError {
     /// Buffer should be centered on tracking midpoint
     #[error("not centered on midpoint")]
     NotCenteredOnTrackMidpoint,
+    /// In [FitMode::TwoStage], less than two 30 s blocks contain the
+    /// 3 measurements the first stage quadratic fit requires.
+    /// Sampling is too slow for this mode, use [FitMode::Linear].
+    #[error("not enough measurements per block for two stage fitting")]
+    UnderSampledBlocks,
+}
+
+/// Measurement latching errors
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum LatchError {
+    /// Measurement is older than the latest latched measurement
+    #[error("measurement is older than latest measurement")]
+    OutOfOrder,
+    /// A measurement was already latched at that Epoch
+    #[error("measurement already latched at that epoch")]
+    Duplicate,
+}
+
+/// Describes how [SVTracker] handles measurements that are not
+/// streamed in chronological order, or that are replayed.
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum LatchPolicy {
+    /// Such measurements are rejected with a [LatchError]
+    #[default]
+    Reject,
+    /// Such measurements are silently dropped
+    Drop,
+    /// Such measurements are latched, replacing any measurement
+    /// previously latched at that Epoch
+    Replace,
+}
+
+/// Track fitting method
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum FitMode {
+    /// A single straight line is fitted to all latched measurements
+    #[default]
+    Linear,
+    /// Standard CGGTTS 2E process: a quadratic is fitted to each 30 s
+    /// block of measurements and evaluated at the block midpoint,
+    /// then a straight line is fitted through these values
+    /// (26 points over a 13' track). Blocks are anchored on the scheduled
+    /// track start and require 3 measurements at least.
+    TwoStage,
 }
 
 /// SV Tracker is used to track a single SV and form a CGGTTS track.
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct SVTracker {
     /* internal buffer */
     buffer: BTreeMap<Epoch, FitData>,
+    /* fitting method */
+    fit_mode: FitMode,
+    /* out of order measurements handling */
+    policy: LatchPolicy,
+    /* tolerated interruption, on top of sampling period */
+    gap_tolerance: Duration,
+    /* minimal ratio of expected measurements to form a track */
+    min_completeness: f64,
+}
+
+impl Default for SVTracker {
+    fn default() -> Self {
+        Self {
+            buffer: BTreeMap::new(),
+            fit_mode: FitMode::default(),
+            policy: LatchPolicy::default(),
+            gap_tolerance: Duration::ZERO,
+            min_completeness: 1.0,
+        }
+    }
 }
 
 /// FitData is a measurement to pass several times
@@ -59,6 +142,38 @@ pub struct FitData {
 }
 
 impl SVTracker {
+    /// Duration of the first stage blocks, in [FitMode::TwoStage]
+    pub const TWO_STAGE_BLOCK_SECONDS: f64 = 30.0;
+
+    /// Returns a tracker that uses given fitting method
+    pub fn with_fit_mode(&self, mode: FitMode) -> Self {
+        let mut s = self.clone();
+        s.fit_mode = mode;
+        s
+    }
+    /// Returns a tracker that handles out of order
+    /// or replayed measurements with given policy
+    pub fn with_latch_policy(&self, policy: LatchPolicy) -> Self {
+        let mut s = self.clone();
+        s.policy = policy;
+        s
+    }
+    /// Returns a tracker that tolerates data gaps,
+    /// as long as two consecutive measurements are not separated
+    /// by more than the sampling period plus given tolerance
+    pub fn with_gap_tolerance(&self, tolerance: Duration) -> Self {
+        let mut s = self.clone();
+        s.gap_tolerance = tolerance;
+        s
+    }
+    /// Returns a tracker that forms tracks when at least given
+    /// ratio [0; 1] of the expected measurements were latched.
+    /// Default is 1.0: all measurements are required.
+    pub fn with_min_completeness(&self, ratio: f64) -> Self {
+        let mut s = self.clone();
+        s.min_completeness = ratio.clamp(0.0, 1.0);
+        s
+    }
     /* has msio data */
     fn has_msio(&self) -> bool {
         self.buffer
@@ -67,7 +182,60 @@ impl SVTracker {
             .count()
             > 0
     }
+    /* (t - trk_midpoint [s], value) points the track line is fitted to */
+    fn fit_points<F: Fn(&FitData) -> f64>(
+        &self,
+        trk_midpoint: Epoch,
+        trk_duration: Duration,
+        value: F,
+    ) -> Result<(Vec<f64>, Vec<f64>), FitError> {
+        match self.fit_mode {
+            FitMode::Linear => Ok(self
+                .buffer
+                .iter()
+                .map(|(t, data)| ((*t - trk_midpoint).to_seconds(), value(data)))
+                .unzip()),
+            FitMode::TwoStage => {
+                // blocks are anchored on the scheduled track start
+                let block = Duration::from_seconds(Self::TWO_STAGE_BLOCK_SECONDS);
+                let trk_start = trk_midpoint - trk_duration / 2;
+                let nb_blocks = (trk_duration.to_seconds() / block.to_seconds()).ceil() as i64;
+
+                let mut blocks = BTreeMap::<i64, (Vec<f64>, Vec<f64>)>::new();
+                for (t, data) in &self.buffer {
+                    let index = ((*t - trk_start).to_seconds() / block.to_seconds()).floor() as i64;
+                    if !(0..nb_blocks).contains(&index) {
+                        continue;
+                    }
+                    let block_mid = trk_start + (index as f64 + 0.5) * block;
+                    let (xs, ys) = blocks.entry(index).or_default();
+                    xs.push((*t - block_mid).to_seconds());
+                    ys.push(value(data));
+                }
+
+                let mut points = (Vec::new(), Vec::new());
+                for (index, (xs, ys)) in blocks {
+                    // a quadratic needs 3 samples at least
+                    if xs.len() < 3 {
+                        continue;
+                    }
+                    let fit =
+                        polyfit(&xs, &ys, 2).map_err(|_| FitError::LinearRegressionFailure)?;
+                    let block_mid = trk_start + (index as f64 + 0.5) * block;
+                    points.0.push((block_mid - trk_midpoint).to_seconds());
+                    points.1.push(fit[0]);
+                }
+                // the second stage line needs 2 points at least
+                if points.0.len() < 2 {
+                    return Err(FitError::UnderSampledBlocks);
+                }
+                Ok(points)
+            },
+        }
+    }
     /// Try to fit a track. You need to provide the ongoing IOE.
+    /// `trk_duration` is the tracking duration (not the slot period),
+    /// usually [Scheduler::trk_duration].
     pub fn fit(
         &self,
         ioe: u16,
@@ -77,11 +245,14 @@ impl SVTracker {
     ) -> Result<((f64, f64), TrackData, Option<IonosphericData>), FitError> {
         // verify tracking completion
         //  complete if we have enough measurements
-        let expected_nb =
-            (trk_duration.to_seconds() / sampling_period.to_seconds()).ceil() as usize;
-        if self.buffer.len() < expected_nb {
+        let expected_nb = (trk_duration.to_seconds() / sampling_period.to_seconds()).ceil()
+            * self.min_completeness;
+        if (self.buffer.len() as f64) < expected_nb.ceil() {
             return Err(FitError::IncompleteTrackMissingMeasurements);
         }
+        if !self.no_gaps(sampling_period) {
+            return Err(FitError::NonContiguousBuffer);
+        }
 
         // verify tracking completion
         // complete if we're centered on midpoint
@@ -93,21 +264,13 @@ impl SVTracker {
             return Err(FitError::NotCenteredOnTrackMidpoint);
         }
 
+        // sampling instants relative to the track midpoint [s]
         let t_xs: Vec<_> = self
             .buffer
             .keys()
-            .map(|t| {
-                t.to_duration_in_time_scale(t.time_scale)
-                    .total_nanoseconds() as f64
-                    * 1.0E-9
-            })
+            .map(|t| (*t - trk_midpoint).to_seconds())
             .collect();
 
-        let t_mid_s = trk_midpoint
-            .to_duration_in_time_scale(first.time_scale)
-            .total_nanoseconds() as f64
-            * 1.0E-9;
-
         let mut t_mid_index = 0;
         for (index, t) in self.buffer.keys().enumerate() {
             if *t < trk_midpoint {
@@ -135,93 +298,44 @@ impl SVTracker {
                 (elev.1.elevation, azi)
             },
             None => {
-                /* linear interpolation */
+                /* linear interpolation, evaluated at the midpoint (x = 0) */
                 let elev: Vec<_> = self.buffer.iter().map(|(_, fit)| fit.elevation).collect();
-                let (a, b) = linear_reg_2d(
+                let (_, elev) = linear_reg_2d(
                     (t_xs[t_mid_index], elev[t_mid_index]),
                     (t_xs[t_mid_index + 1], elev[t_mid_index + 1]),
                 );
 
-                let elev = a * t_mid_s + b;
-
                 let azi: Vec<_> = self.buffer.iter().map(|(_, fit)| fit.azimuth).collect();
-                let (a, b) = linear_reg_2d(
+                // unwrap the 0/360° discontinuity between both samples
+                let mut azi_j = azi[t_mid_index + 1];
+                if azi_j - azi[t_mid_index] > 180.0 {
+                    azi_j -= 360.0;
+                } else if azi[t_mid_index] - azi_j > 180.0 {
+                    azi_j += 360.0;
+                }
+                let (_, azi) = linear_reg_2d(
                     (t_xs[t_mid_index], azi[t_mid_index]),
-                    (t_xs[t_mid_index + 1], azi[t_mid_index + 1]),
+                    (t_xs[t_mid_index + 1], azi_j),
                 );
-
-                let azi = a * t_mid_s + b;
+                let azi = azi.rem_euclid(360.0);
                 (elev, azi)
             },
         };
 
-        let fit = polyfit(
-            &t_xs,
-            &self
-                .buffer
-                .values()
-                .map(|f| f.refsv)
-                .collect::<Vec<_>>()
-                .as_slice(),
-            1,
-        )
-        .map_err(|_| FitError::LinearRegressionFailure)?;
-
-        let (srsv, srsv_b) = (fit[1], fit[0]);
-        let refsv = srsv * t_mid_s + srsv_b;
-
-        let fit = polyfit(
-            &t_xs,
-            &self
-                .buffer
-                .values()
-                .map(|f| f.refsys)
-                .collect::<Vec<_>>()
-                .as_slice(),
-            1,
-        )
-        .map_err(|_| FitError::LinearRegressionFailure)?;
-
-        let (srsys, srsys_b) = (fit[1], fit[0]);
-        let refsys = srsys * t_mid_s + srsys_b;
-
-        let refsys_fit: Vec<_> = t_xs.iter().map(|t_s| srsys * t_s + srsys_b).collect();
-
-        let mut dsg = 0.0_f64;
-        for refsys_fit in refsys_fit {
-            dsg += (refsys_fit - refsys).powi(2);
-        }
-        dsg = dsg.sqrt();
+        let (xs, ys) = self.fit_points(trk_midpoint, trk_duration, |f| f.refsv)?;
+        let (srsv, refsv) = linear_fit(&xs, &ys)?;
 
-        let fit = polyfit(
-            &t_xs,
-            &self
-                .buffer
-                .values()
-                .map(|f| f.mdtr)
-                .collect::<Vec<_>>()
-                .as_slice(),
-            1,
-        )
-        .map_err(|_| FitError::LinearRegressionFailure)?;
-
-        let (smdt, smdt_b) = (fit[1], fit[0]);
-        let mdtr = smdt * t_mid_s + smdt_b;
-
-        let fit = polyfit(
-            &t_xs,
-            &self
-                .buffer
-                .values()
-                .map(|f| f.mdio.unwrap_or(0.0_f64))
-                .collect::<Vec<_>>()
-                .as_slice(),
-            1,
-        )
-        .map_err(|_| FitError::LinearRegressionFailure)?;
+        let (xs, ys) = self.fit_points(trk_midpoint, trk_duration, |f| f.refsys)?;
+        let (srsys, refsys) = linear_fit(&xs, &ys)?;
+
+        let dsg = rms_residuals(&xs, &ys, srsys, refsys);
+
+        let (xs, ys) = self.fit_points(trk_midpoint, trk_duration, |f| f.mdtr)?;
+        let (smdt, mdtr) = linear_fit(&xs, &ys)?;
 
-        let (smdi, smdi_b) = (fit[1], fit[0]);
-        let mdio = smdi * t_mid_s + smdi_b;
+        let (xs, ys) =
+            self.fit_points(trk_midpoint, trk_duration, |f| f.mdio.unwrap_or(0.0_f64))?;
+        let (smdi, mdio) = linear_fit(&xs, &ys)?;
 
         let trk_data = TrackData {
             refsv,
@@ -239,27 +353,11 @@ impl SVTracker {
         let iono_data = match self.has_msio() {
             false => None,
             true => {
-                let fit = polyfit(
-                    &t_xs,
-                    &self
-                        .buffer
-                        .values()
-                        .map(|f| f.msio.unwrap())
-                        .collect::<Vec<_>>()
-                        .as_slice(),
-                    1,
-                )
-                .map_err(|_| FitError::LinearRegressionFailure)?;
+                let (xs, ys) =
+                    self.fit_points(trk_midpoint, trk_duration, |f| f.msio.unwrap_or(0.0_f64))?;
+                let (smsi, msio) = linear_fit(&xs, &ys)?;
 
-                let (smsi, smsi_b) = (fit[1], fit[0]);
-                let msio = smsi * t_mid_s + smsi_b;
-
-                let mut isg = 0.0_f64;
-                let msio_fit: Vec<_> = t_xs.iter().map(|t_s| smsi * t_s + smsi_b).collect();
-                for msio_fit in msio_fit {
-                    isg += (msio_fit - msio).powi(2);
-                }
-                isg = isg.sqrt();
+                let isg = rms_residuals(&xs, &ys, smsi, msio);
 
                 Some(IonosphericData { msio, smsi, isg })
             },
@@ -270,23 +368,35 @@ impl SVTracker {
 
     /// Latch a new measurement at given UTC Epoch.
     /// You can then use .fit() to try to fit a track.
-    pub fn latch_measurement(&mut self, utc_t: Epoch, data: FitData) {
+    /// Measurements should be streamed in chronological order,
+    /// other measurements are handled according to the [LatchPolicy].
+    pub fn latch_measurement(&mut self, utc_t: Epoch, data: FitData) -> Result<(), LatchError> {
         if let Some((last_t, _)) = self.buffer.last_key_value() {
-            assert!(
-                utc_t > *last_t,
-                "samples should be streamed in chronological order"
-            );
+            if utc_t <= *last_t {
+                match self.policy {
+                    LatchPolicy::Reject => {
+                        if self.buffer.contains_key(&utc_t) {
+                            return Err(LatchError::Duplicate);
+                        }
+                        return Err(LatchError::OutOfOrder);
+                    },
+                    LatchPolicy::Drop => return Ok(()),
+                    LatchPolicy::Replace => {},
+                }
+            }
         }
         self.buffer.insert(utc_t, data);
+        Ok(())
     }
 
     /// You should only form a track (.fit()) if no_gaps are present in the buffer.
+    /// Interruptions within the gap tolerance are not considered as gaps.
     pub fn no_gaps(&self, sampling_period: Duration) -> bool {
         let mut prev = Option::<Epoch>::None;
         for t in self.buffer.keys() {
             if let Some(prev) = prev {
                 let dt = *t - prev;
-                if dt > sampling_period {
+                if dt > sampling_period + self.gap_tolerance {
                     return false;
                 }
             }
@@ -307,46 +417,71 @@ impl SVTracker {
 }
 
 /// Scheduler used to form synchronous CGGTTS tracks.
+/// Tracks are scheduled every slot `period`, and each track
+/// lasts `trk_duration` within its slot, the remaining time being
+/// dedicated to acquisition.
 #[derive(Debug, Clone)]
 pub struct Scheduler {
-    /// Tracking duration in use.
+    /// Slot period: one track is scheduled every period.
+    pub period: Duration,
+    /// Tracking duration in use, within each slot.
     pub trk_duration: Duration,
 }
 
 impl Default for Scheduler {
     fn default() -> Self {
         Self {
+            period: Self::bipm_slot_period(),
             trk_duration: Self::bipm_tracking_duration(),
         }
     }
 }
 
 impl Scheduler {
+    /// Standard slot period [s]
+    pub const BIPM_SLOT_PERIOD_SECONDS: u32 = 960;
+
     /// Standard tracking duration [s]
-    pub const BIPM_TRACKING_DURATION_SECONDS: u32 = 960;
+    pub const BIPM_TRACKING_DURATION_SECONDS: u32 = 780;
+
+    /// Returns standard slot period
+    pub fn bipm_slot_period() -> Duration {
+        Duration::from_seconds(Self::BIPM_SLOT_PERIOD_SECONDS as f64)
+    }
 
     /// Returns standard tracking duration
     pub fn bipm_tracking_duration() -> Duration {
         Duration::from_seconds(Self::BIPM_TRACKING_DURATION_SECONDS as f64)
     }
 
-    /// Generates a new Track Scheduler from a given (usually simply "now")
-    /// datetime expressed as an Epoch.
+    /// Builds a new Track Scheduler with custom tracking duration.
+    /// Tracks follow each other, without acquisition time:
+    /// the slot period is the tracking duration, see [Self::with_period].
     pub fn new(trk_duration: Duration) -> Self {
-        Self { trk_duration }
+        Self {
+            period: trk_duration,
+            trk_duration,
+        }
+    }
+    /// Returns a Scheduler with custom slot period.
+    /// Tracking duration should not exceed the slot period.
+    pub fn with_period(&self, period: Duration) -> Self {
+        let mut s = self.clone();
+        s.period = period;
+        s
     }
 
     /* track 0 offset within any MJD, expressed in nanos */
-    pub(crate) fn t0_offset_nanos(mjd: u32, trk_duration: Duration) -> i128 {
-        let tracking_nanos = trk_duration.total_nanoseconds();
+    pub(crate) fn t0_offset_nanos(mjd: u32, period: Duration) -> i128 {
+        let period_nanos = period.total_nanoseconds();
         let offset_nanos = (
             (50_722 - mjd as i128)
             * 4 * 1_000_000_000 * 60  // shift per day
             + 2 * 1_000_000_000 * 60
             // offset on MJD=50722 reference
-        ) % trk_duration.total_nanoseconds();
+        ) % period.total_nanoseconds();
         if offset_nanos < 0 {
-            offset_nanos + tracking_nanos
+            offset_nanos + period_nanos
         } else {
             offset_nanos
         }
@@ -359,30 +494,30 @@ impl Scheduler {
             _ => Epoch::from_utc_duration(t.to_utc_duration()),
         };
 
-        let trk_duration = self.trk_duration;
+        let period = self.period;
         let mjd = utc_t.to_mjd_utc_days();
         let mjd_u = mjd.floor() as u32;
 
         let mjd_next = Epoch::from_mjd_utc((mjd_u + 1) as f64);
         let time_to_midnight = mjd_next - utc_t;
 
-        match time_to_midnight < trk_duration {
+        match time_to_midnight < period {
             true => {
                 /*
                  * if we're in the last track of the day,
                  * we need to consider next day (MJD+1)
                  */
-                let offset_nanos = Self::t0_offset_nanos(mjd_u + 1, trk_duration);
+                let offset_nanos = Self::t0_offset_nanos(mjd_u + 1, period);
                 Epoch::from_mjd_utc((mjd_u + 1) as f64)
                     + Duration::from_nanoseconds(offset_nanos as f64)
             },
             false => {
-                let offset_nanos = Self::t0_offset_nanos(mjd_u, trk_duration);
+                let offset_nanos = Self::t0_offset_nanos(mjd_u, period);
 
                 // determine track number this "t" contributes to
                 let day_offset_nanos =
                     (utc_t - Epoch::from_mjd_utc(mjd_u as f64)).total_nanoseconds() - offset_nanos;
-                let i = (day_offset_nanos as f64 / trk_duration.total_nanoseconds() as f64).ceil();
+                let i = (day_offset_nanos as f64 / period.total_nanoseconds() as f64).ceil();
 
                 let mut e = Epoch::from_mjd_utc(mjd_u as f64)
                     + Duration::from_nanoseconds(offset_nanos as f64);
@@ -390,12 +525,46 @@ impl Scheduler {
                 // on first track of day: we only have the day nanos offset
                 if i > 0.0 {
                     // add ith track offset
-                    e += Duration::from_nanoseconds(i * trk_duration.total_nanoseconds() as f64);
+                    e += Duration::from_nanoseconds(i * period.total_nanoseconds() as f64);
                 }
                 e
             },
         }
     }
+    /// Returns all tracks scheduled on given MJD, as (start, midpoint, end) Epochs.
+    /// Midpoint and end are defined by the tracking duration.
+    /// The track grid of each MJD is shifted by 4' per day with respect to
+    /// the reference MJD 50722, and the slot that would overlap midnight is dropped.
+    /// ```
+    /// use cggtts::prelude::Epoch;
+    /// use cggtts::track::Scheduler;
+    /// let scheduler = Scheduler::default();
+    /// let tracks: Vec<_> = scheduler.tracks_for_mjd(50722).collect();
+    /// assert_eq!(tracks.len(), 89);
+    /// let (start, _, _) = tracks[0];
+    /// assert_eq!(start, Epoch::from_gregorian_utc(1997, 10, 1, 0, 2, 0, 0));
+    /// ```
+    pub fn tracks_for_mjd(&self, mjd: u32) -> impl Iterator<Item = (Epoch, Epoch, Epoch)> {
+        let (period, trk_duration) = (self.period, self.trk_duration);
+        let next_midnight = Epoch::from_mjd_utc((mjd + 1) as f64);
+        let offset_nanos = Self::t0_offset_nanos(mjd, period);
+        let t0 = Epoch::from_mjd_utc(mjd as f64) + Duration::from_nanoseconds(offset_nanos as f64);
+        (0..)
+            .map(move |i| t0 + i as f64 * period)
+            .take_while(move |start| *start + period <= next_midnight)
+            .map(move |start| (start, start + trk_duration / 2, start + trk_duration))
+    }
+    /// Returns true if `t` is the start time of a scheduled track,
+    /// see [Self::tracks_for_mjd].
+    pub fn is_scheduled(&self, t: Epoch) -> bool {
+        let utc_t = match t.time_scale {
+            TimeScale::UTC => t,
+            _ => Epoch::from_utc_duration(t.to_utc_duration()),
+        };
+        let mjd_u = utc_t.to_mjd_utc_days().floor() as u32;
+        self.tracks_for_mjd(mjd_u)
+            .any(|(start, _, _)| start == utc_t)
+    }
     /// Helper to determine how long until a next "synchronous" track.
     pub fn time_to_next_track(&self, now: Epoch) -> Duration {
         self.next_track_start(now) - now
@@ -404,11 +573,297 @@ impl Scheduler {
 
 #[cfg(test)]
 mod test {
-    use crate::track::Scheduler;
+    use super::{FitError, LatchError, LatchPolicy};
+    use crate::track::{FitData, FitMode, SVTracker, Scheduler};
     use crate::{Duration, Epoch};
+    use rand::{distributions::Distribution, SeedableRng};
+
+    /* latches one BIPM track worth of 1 s measurements */
+    fn latch_track<F: Fn(f64) -> f64>(tracker: &mut SVTracker, t0: Epoch, refsys: F) {
+        for i in 0..=780 {
+            let t = t0 + Duration::from_seconds(i as f64);
+            let dt = i as f64 - 390.0;
+            tracker
+                .latch_measurement(
+                    t,
+                    FitData {
+                        refsv: refsys(dt) + 1.0E-6,
+                        refsys: refsys(dt),
+                        mdtr: 1.0E-8,
+                        elevation: 45.0,
+                        azimuth: 180.0,
+                        mdio: Some(5.0E-9 + 1.0E-13 * dt),
+                        msio: None,
+                    },
+                )
+                .unwrap();
+        }
+    }
+    #[test]
+    fn linear_fit() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        for mode in [FitMode::Linear, FitMode::TwoStage] {
+            let mut tracker = SVTracker::default().with_fit_mode(mode);
+            latch_track(&mut tracker, t0, |dt| 1.0E-7 + 2.0E-12 * dt);
+            let ((elev, azi), data, iono) = tracker
+                .fit(
+                    1,
+                    Scheduler::bipm_tracking_duration(),
+                    Duration::from_seconds(1.0),
+                    mid,
+                )
+                .unwrap();
+            assert_eq!((elev, azi), (45.0, 180.0));
+            assert!((data.refsys - 1.0E-7).abs() < 1.0E-15, "{:?}", mode);
+            assert!((data.srsys - 2.0E-12).abs() < 1.0E-18, "{:?}", mode);
+            assert!((data.refsv - 1.0E-7 - 1.0E-6).abs() < 1.0E-15, "{:?}", mode);
+            assert!((data.mdtr - 1.0E-8).abs() < 1.0E-15, "{:?}", mode);
+            assert!((data.mdio - 5.0E-9).abs() < 1.0E-15, "{:?}", mode);
+            assert!((data.smdi - 1.0E-13).abs() < 1.0E-18, "{:?}", mode);
+            assert!(data.dsg < 1.0E-15, "{:?}", mode);
+            assert!(iono.is_none());
+        }
+    }
+    /* latches one BIPM track worth of 30 s measurements, none of them on the track midpoint */
+    fn latch_attitude<F: Fn(f64) -> (f64, f64)>(tracker: &mut SVTracker, t0: Epoch, attitude: F) {
+        for i in 0..26 {
+            let dt = 15.0 + 30.0 * i as f64;
+            let (elevation, azimuth) = attitude(dt);
+            tracker
+                .latch_measurement(
+                    t0 + Duration::from_seconds(dt),
+                    FitData {
+                        elevation,
+                        azimuth,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+        }
+    }
+    #[test]
+    fn interpolated_attitude() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        let sampling = Duration::from_seconds(30.0);
+        // 0.01 °/s rising SV, slowly turning eastward
+        let mut tracker = SVTracker::default();
+        latch_attitude(&mut tracker, t0, |dt| (30.0 + 0.01 * dt, 100.0 + 0.02 * dt));
+        let ((elev, azi), _, _) = tracker
+            .fit(1, Scheduler::bipm_tracking_duration(), sampling, mid)
+            .unwrap();
+        assert!((elev - 33.9).abs() < 1.0E-9, "{}", elev);
+        assert!((azi - 107.8).abs() < 1.0E-9, "{}", azi);
+    }
+    #[test]
+    fn interpolated_azimuth_north_crossing() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        let sampling = Duration::from_seconds(30.0);
+        // SV crosses north between the samples adjacent to the midpoint
+        for rate in [0.05, -0.05] {
+            let mut tracker = SVTracker::default();
+            latch_attitude(&mut tracker, t0, |dt| {
+                (45.0, (rate * (dt - 385.0)).rem_euclid(360.0))
+            });
+            let ((_, azi), _, _) = tracker
+                .fit(1, Scheduler::bipm_tracking_duration(), sampling, mid)
+                .unwrap();
+            let expected = (rate * 5.0_f64).rem_euclid(360.0);
+            assert!((azi - expected).abs() < 1.0E-9, "{}", azi);
+        }
+    }
+    #[test]
+    fn latch_policies() {
+        let t0 = Epoch::from_mjd_utc(59506.0);
+        let t1 = t0 + Duration::from_seconds(1.0);
+        let t2 = t0 + Duration::from_seconds(2.0);
+        let data = FitData::default();
+        let replayed = FitData {
+            refsys: 1.0,
+            ..Default::default()
+        };
+
+        let mut tracker = SVTracker::default();
+        assert!(tracker.latch_measurement(t0, data.clone()).is_ok());
+        assert!(tracker.latch_measurement(t2, data.clone()).is_ok());
+        assert_eq!(
+            tracker.latch_measurement(t2, replayed.clone()),
+            Err(LatchError::Duplicate)
+        );
+        assert_eq!(
+            tracker.latch_measurement(t1, replayed.clone()),
+            Err(LatchError::OutOfOrder)
+        );
+        assert_eq!(tracker.buffer.len(), 2);
+
+        let mut tracker = tracker.with_latch_policy(LatchPolicy::Drop);
+        assert!(tracker.latch_measurement(t1, replayed.clone()).is_ok());
+        assert!(tracker.latch_measurement(t2, replayed.clone()).is_ok());
+        assert_eq!(tracker.buffer.len(), 2);
+        assert_eq!(tracker.buffer[&t2].refsys, 0.0);
+
+        let mut tracker = tracker.with_latch_policy(LatchPolicy::Replace);
+        assert!(tracker.latch_measurement(t1, replayed.clone()).is_ok());
+        assert!(tracker.latch_measurement(t2, replayed.clone()).is_ok());
+        assert_eq!(tracker.buffer.len(), 3);
+        assert_eq!(tracker.buffer[&t2].refsys, 1.0);
+    }
+    #[test]
+    fn tolerated_gaps() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        let mut full = SVTracker::default();
+        latch_track(&mut full, t0, |dt| 1.0E-7 + 2.0E-12 * dt);
+
+        // 10 s interruption
+        let mut tracker = SVTracker::default();
+        for (t, data) in full.buffer.iter() {
+            let dt = (*t - t0).to_seconds();
+            if !(100.0..110.0).contains(&dt) {
+                tracker.latch_measurement(*t, data.clone()).unwrap();
+            }
+        }
+        let sampling = Duration::from_seconds(1.0);
+        let duration = Scheduler::bipm_tracking_duration();
+
+        assert!(!tracker.no_gaps(sampling));
+        assert!(tracker.fit(1, duration, sampling, mid).is_err());
+
+        // gap is tolerated, but track is not complete
+        let tracker = tracker.with_gap_tolerance(Duration::from_seconds(10.0));
+        assert!(tracker.no_gaps(sampling));
+        assert!(matches!(
+            tracker.fit(1, duration, sampling, mid),
+            Err(FitError::IncompleteTrackMissingMeasurements)
+        ));
+
+        let tracker = tracker.with_min_completeness(0.98);
+        let (_, data, _) = tracker.fit(1, duration, sampling, mid).unwrap();
+        assert!((data.refsys - 1.0E-7).abs() < 1.0E-15);
+
+        // gap exceeds tolerance
+        let tracker = tracker.with_gap_tolerance(Duration::from_seconds(5.0));
+        assert!(matches!(
+            tracker.fit(1, duration, sampling, mid),
+            Err(FitError::NonContiguousBuffer)
+        ));
+    }
+    #[test]
+    fn dsg_isg() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        // uniform noise within [-a, a] has a/sqrt(3) RMS
+        let (refsys_a, msio_a) = (3.0E-9, 1.0E-9);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let refsys_noise = rand::distributions::Uniform::new_inclusive(-refsys_a, refsys_a);
+        let msio_noise = rand::distributions::Uniform::new_inclusive(-msio_a, msio_a);
+
+        let mut tracker = SVTracker::default();
+        for i in 0..=780 {
+            let dt = i as f64 - 390.0;
+            tracker
+                .latch_measurement(
+                    t0 + Duration::from_seconds(i as f64),
+                    FitData {
+                        refsv: 1.0E-6,
+                        refsys: 1.0E-7 + 2.0E-12 * dt + refsys_noise.sample(&mut rng),
+                        mdtr: 1.0E-8,
+                        elevation: 45.0,
+                        azimuth: 180.0,
+                        mdio: None,
+                        msio: Some(5.0E-9 + 1.0E-13 * dt + msio_noise.sample(&mut rng)),
+                    },
+                )
+                .unwrap();
+        }
+        let (_, data, iono) = tracker
+            .fit(
+                1,
+                Scheduler::bipm_tracking_duration(),
+                Duration::from_seconds(1.0),
+                mid,
+            )
+            .unwrap();
+        let iono = iono.unwrap();
+
+        let expected = refsys_a / 3.0_f64.sqrt();
+        assert!(
+            (data.dsg - expected).abs() < 0.05 * expected,
+            "{}",
+            data.dsg
+        );
+        let expected = msio_a / 3.0_f64.sqrt();
+        assert!(
+            (iono.isg - expected).abs() < 0.05 * expected,
+            "{}",
+            iono.isg
+        );
+
+        assert!((data.refsys - 1.0E-7).abs() < 1.0E-9);
+        assert!((data.srsys - 2.0E-12).abs() < 1.0E-12);
+        assert!((iono.msio - 5.0E-9).abs() < 1.0E-9);
+        assert!(data.srsv.abs() < 1.0E-18);
+        assert!((data.refsv - 1.0E-6).abs() < 1.0E-15);
+    }
+    #[test]
+    fn two_stage_fit() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        let mut tracker = SVTracker::default().with_fit_mode(FitMode::TwoStage);
+        // quadratic within each 30 s block is absorbed by the first stage
+        latch_track(&mut tracker, t0, |dt| {
+            let block_dt = (dt + 390.0).rem_euclid(30.0) - 15.0;
+            1.0E-7 + 2.0E-12 * dt + 1.0E-13 * block_dt.powi(2)
+        });
+        let duration = Scheduler::bipm_tracking_duration();
+        let (xs, ys) = tracker.fit_points(mid, duration, |f| f.refsys).unwrap();
+        assert_eq!(xs.len(), 26);
+        assert_eq!(xs[0], -375.0);
+        assert_eq!(xs[25], 375.0);
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            assert!((y - 1.0E-7 - 2.0E-12 * x).abs() < 1.0E-15);
+        }
+
+        // first 20 s are missing: blocks remain anchored on the track start
+        let mut late = SVTracker::default()
+            .with_fit_mode(FitMode::TwoStage)
+            .with_min_completeness(0.95);
+        for (t, data) in tracker.buffer.iter() {
+            if (*t - t0).to_seconds() >= 20.0 {
+                late.latch_measurement(*t, data.clone()).unwrap();
+            }
+        }
+        let (xs, ys) = late.fit_points(mid, duration, |f| f.refsys).unwrap();
+        assert_eq!(xs.len(), 26);
+        assert_eq!(xs[0], -375.0);
+        assert!((ys[0] - 1.0E-7 + 2.0E-12 * 375.0).abs() < 1.0E-15);
+        let sampling = Duration::from_seconds(1.0);
+        let (_, data, _) = late.fit(1, duration, sampling, mid).unwrap();
+        assert!((data.refsys - 1.0E-7).abs() < 1.0E-15);
+        assert!((data.srsys - 2.0E-12).abs() < 1.0E-18);
+    }
+    #[test]
+    fn two_stage_under_sampled() {
+        let t0 = Epoch::from_mjd_utc(59506.0) + Duration::from_seconds(120.0);
+        let mid = t0 + Duration::from_seconds(390.0);
+        let duration = Scheduler::bipm_tracking_duration();
+        // 30 s sampling: a single measurement per block
+        let mut tracker = SVTracker::default().with_fit_mode(FitMode::TwoStage);
+        latch_attitude(&mut tracker, t0, |dt| (45.0, 10.0 + dt / 100.0));
+        let sampling = Duration::from_seconds(30.0);
+        assert!(matches!(
+            tracker.fit(1, duration, sampling, mid),
+            Err(FitError::UnderSampledBlocks)
+        ));
+        // linear fit is still feasible
+        let tracker = tracker.with_fit_mode(FitMode::Linear);
+        assert!(tracker.fit(1, duration, sampling, mid).is_ok());
+    }
     #[test]
     fn t0_offset_minutes() {
-        let duration = Duration::from_seconds(Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64);
+        let duration = Duration::from_seconds(Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64);
         for (mjd, expected) in vec![
             (50721, 6 * 60 * 1_000_000_000),
             (50722, 2 * 60 * 1_000_000_000),
@@ -423,6 +878,94 @@ mod test {
         }
     }
     #[test]
+    fn scheduled_tracks() {
+        let scheduler = Scheduler::default();
+        for (t, expected) in [
+            (
+                Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(120.0),
+                true,
+            ),
+            (
+                Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(1080.0),
+                true,
+            ),
+            (
+                Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(121.0),
+                false,
+            ),
+            (Epoch::from_mjd_utc(50722.0), false),
+            // 4' earlier every day
+            (
+                Epoch::from_mjd_utc(50723.0) + Duration::from_seconds(840.0),
+                true,
+            ),
+            (
+                Epoch::from_mjd_utc(50723.0) + Duration::from_seconds(120.0),
+                false,
+            ),
+            (
+                Epoch::from_mjd_utc(50724.0) + Duration::from_seconds(600.0),
+                true,
+            ),
+            (
+                Epoch::from_mjd_utc(59508.0) + Duration::from_seconds(600.0),
+                true,
+            ),
+            (
+                Epoch::from_mjd_utc(59509.0) + Duration::from_seconds(600.0),
+                false,
+            ),
+            // last slot of MJD 50722 would overlap midnight
+            (
+                Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(120.0 + 89.0 * 960.0),
+                false,
+            ),
+            (
+                Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(120.0 + 88.0 * 960.0),
+                true,
+            ),
+        ] {
+            assert_eq!(scheduler.is_scheduled(t), expected, "failed for {}", t);
+        }
+    }
+    #[test]
+    fn daily_tracks() {
+        let scheduler = Scheduler::default();
+        for mjd in [50721, 50722, 50723, 50724, 59506, 60258] {
+            let tracks: Vec<_> = scheduler.tracks_for_mjd(mjd).collect();
+            assert_eq!(tracks.len(), 89, "failed for MJD {}", mjd);
+            let (start, mid, end) = tracks[0];
+            assert_eq!(
+                start,
+                scheduler.next_track_start(Epoch::from_mjd_utc(mjd as f64))
+            );
+            assert_eq!(mid - start, Duration::from_seconds(390.0));
+            assert_eq!(end - start, Duration::from_seconds(780.0));
+            let (_, _, end) = tracks[88];
+            assert!(end <= Epoch::from_mjd_utc((mjd + 1) as f64));
+        }
+        // custom tracking
+        let scheduler = Scheduler::new(Duration::from_seconds(480.0))
+            .with_period(Duration::from_seconds(600.0));
+        let tracks: Vec<_> = scheduler.tracks_for_mjd(50722).collect();
+        assert_eq!(tracks.len(), 143);
+        let (start, mid, end) = tracks[0];
+        assert_eq!(
+            start,
+            Epoch::from_mjd_utc(50722.0) + Duration::from_seconds(120.0)
+        );
+        assert_eq!(mid - start, Duration::from_seconds(240.0));
+        assert_eq!(end - start, Duration::from_seconds(480.0));
+        // back to back tracking
+        let scheduler = Scheduler::new(Duration::from_seconds(960.0));
+        assert_eq!(scheduler.period, Duration::from_seconds(960.0));
+        let tracks: Vec<_> = scheduler.tracks_for_mjd(50722).collect();
+        assert_eq!(tracks.len(), 89);
+        let (start, _, end) = tracks[0];
+        assert_eq!(end, tracks[1].0);
+        assert_eq!(end - start, Duration::from_seconds(960.0));
+    }
+    #[test]
     fn next_track_scheduler() {
         for (t, expected) in vec![
             // reference MJD
@@ -454,33 +997,33 @@ mod test {
             (
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        2.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 120.0,
+                        2.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 120.0,
                     ),
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        2.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 120.0,
+                        2.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 120.0,
                     ),
             ),
             // two tracks + 10sec into reference MJD
             (
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        2.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 130.0,
+                        2.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 130.0,
                     ),
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        3.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 120.0,
+                        3.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 120.0,
                     ),
             ),
             // two tracks + 950 sec into reference MJD
             (
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        2.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 120.0 + 950.0,
+                        2.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 120.0 + 950.0,
                     ),
                 Epoch::from_mjd_utc(50722.0)
                     + Duration::from_seconds(
-                        3.0 * Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64 + 120.0,
+                        3.0 * Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64 + 120.0,
                     ),
             ),
             // MJD = 59_506
@@ -522,5 +1065,12 @@ mod test {
             Scheduler::bipm_tracking_duration(),
             Duration::from_seconds(Scheduler::BIPM_TRACKING_DURATION_SECONDS as f64)
         );
+        assert_eq!(
+            Scheduler::bipm_slot_period(),
+            Duration::from_seconds(Scheduler::BIPM_SLOT_PERIOD_SECONDS as f64)
+        );
+        let scheduler = Scheduler::default();
+        assert_eq!(scheduler.period, Duration::from_seconds(960.0));
+        assert_eq!(scheduler.trk_duration, Duration::from_seconds(780.0));
     }
 }
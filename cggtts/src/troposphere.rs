@@ -0,0 +1,138 @@
+//! Tropospheric delay models, used to determine MDTR and SMDT
+use crate::SPEED_OF_LIGHT;
+
+/// Elevation step [°] used in the numerical derivation of the delay
+const ELEVATION_STEP: f64 = 1.0E-3;
+
+/// Tropospheric delay model. Implement this trait to use
+/// other zenith delay and mapping functions.
+pub trait TroposphericModel {
+    /// Returns the zenith tropospheric delay [s],
+    /// for given receiver height above sea level [m]
+    fn zenith_delay(&self, height: f64) -> f64;
+    /// Returns the mapping function value, for given elevation [°]
+    fn mapping(&self, elevation: f64) -> f64;
+    /// Returns the modeled tropospheric delay [s] (MDTR),
+    /// for given receiver height [m] and elevation [°]
+    fn delay(&self, height: f64, elevation: f64) -> f64 {
+        self.zenith_delay(height) * self.mapping(elevation)
+    }
+    /// Returns the modeled tropospheric delay [s] (MDTR) and its slope [s/s] (SMDT),
+    /// for given receiver height [m], elevation [°] and elevation rate [°/s]
+    fn delay_and_slope(&self, height: f64, elevation: f64, elevation_rate: f64) -> (f64, f64) {
+        let d_delay = self.delay(height, elevation + ELEVATION_STEP / 2.0)
+            - self.delay(height, elevation - ELEVATION_STEP / 2.0);
+        (
+            self.delay(height, elevation),
+            d_delay / ELEVATION_STEP * elevation_rate,
+        )
+    }
+}
+
+/// NATO (STANAG) tropospheric model, as defined in the CGGTTS guidelines
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Nato;
+
+impl Nato {
+    /// Refractivity index at sea level
+    pub const NS: f64 = 324.8;
+
+    /* Δn constant */
+    fn dn() -> f64 {
+        -7.32 * (0.005577 * Self::NS).exp()
+    }
+}
+
+impl TroposphericModel for Nato {
+    /// Returns the NATO zenith delay,
+    /// defined piecewise below 1 km, from 1 to 9 km and above 9 km
+    /// ```
+    /// use cggtts::troposphere::{Nato, TroposphericModel};
+    /// use cggtts::SPEED_OF_LIGHT;
+    /// // zenith delay at sea level [m]
+    /// let delay = Nato.zenith_delay(0.0) * SPEED_OF_LIGHT;
+    /// assert!((delay - 2.4644).abs() < 1.0E-4);
+    /// ```
+    fn zenith_delay(&self, height: f64) -> f64 {
+        let h = height / 1.0E3;
+        let dn = Self::dn();
+        let ns_dn = Self::NS + dn;
+        let nslog = (ns_dn / 105.0).ln();
+        let delay_mm = if h < 1.0 {
+            2162.0 + Self::NS * (1.0 - h) + 0.5 * dn * (1.0 - h.powi(2))
+        } else if h < 9.0 {
+            let frac = 8.0 * ns_dn / nslog;
+            let e_1 = (-nslog).exp();
+            let e_2 = (-0.125 * (h - 1.0) * nslog).exp();
+            732.0 - frac * (e_1 - e_2)
+        } else {
+            732.0 * (-0.1424 * (h - 9.0)).exp()
+        };
+        delay_mm * 1.0E-3 / SPEED_OF_LIGHT
+    }
+    /// Returns the NATO mapping function 1 / (sin(E) + 0.00143 / (tan(E) + 0.0455))
+    fn mapping(&self, elevation: f64) -> f64 {
+        let e = elevation.to_radians();
+        1.0 / (e.sin() + 0.00143 / (e.tan() + 0.0455))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Nato, TroposphericModel};
+    use crate::SPEED_OF_LIGHT;
+    #[test]
+    fn nato_zenith_delay() {
+        // height [m], zenith delay [m]
+        for (height, expected) in [
+            (0.0, 2.4644),
+            (500.0, 2.3076),
+            (1000.0, 2.1594),
+            (5000.0, 1.2741),
+            (9000.0, 0.7320),
+            (15000.0, 0.3115),
+        ] {
+            let delay = Nato.zenith_delay(height) * SPEED_OF_LIGHT;
+            assert!(
+                (delay - expected).abs() < 5.0E-4,
+                "failed for {} m: {}",
+                height,
+                delay
+            );
+        }
+    }
+    #[test]
+    fn nato_mapping() {
+        // elevation [°], mapping
+        for (elevation, expected) in [
+            (90.0, 1.0000),
+            (60.0, 1.1536),
+            (30.0, 1.9909),
+            (15.0, 3.7968),
+            (10.0, 5.5526),
+            (5.0, 10.2136),
+        ] {
+            let mapping = Nato.mapping(elevation);
+            assert!(
+                (mapping - expected).abs() < 1.0E-3,
+                "failed for {}°: {}",
+                elevation,
+                mapping
+            );
+        }
+    }
+    #[test]
+    fn slope() {
+        let (height, elevation) = (100.0, 20.0);
+        // rising SV
+        let rate = 0.01;
+        let (mdtr, smdt) = Nato.delay_and_slope(height, elevation, rate);
+        assert_eq!(mdtr, Nato.delay(height, elevation));
+        assert!(smdt < 0.0);
+        let later = Nato.delay(height, elevation + rate * 10.0);
+        assert!((mdtr + smdt * 10.0 - later).abs() < 1.0E-12);
+        // setting SV
+        let (_, smdt) = Nato.delay_and_slope(height, elevation, -rate);
+        assert!(smdt > 0.0);
+    }
+}
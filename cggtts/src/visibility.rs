@@ -0,0 +1,466 @@
+//! SV visibility: sky tracks, elevation statistics and common visibility prediction
+use crate::ephemeris::kepler_constants;
+use crate::prelude::{Epoch, SV};
+use crate::{Coordinates, CGGTTS};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "scheduler")]
+use crate::prelude::{Constellation, Duration};
+
+#[cfg(feature = "scheduler")]
+use crate::track::Scheduler;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// SV position in the local sky, at track midpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SkyPoint {
+    /// Track (start) Epoch
+    pub epoch: Epoch,
+    /// Elevation [°]
+    pub elevation: f64,
+    /// Azimuth [°]
+    pub azimuth: f64,
+}
+
+/// Keplerian almanac of a single SV, as broadcast
+/// by GPS, Galileo, BeiDou or QZSS
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Almanac {
+    /// SV described by this almanac
+    pub sv: SV,
+    /// Almanac reference Epoch
+    pub toa: Epoch,
+    /// Square root of the semi major axis [m^1/2]
+    pub sqrt_a: f64,
+    /// Eccentricity
+    pub e: f64,
+    /// Inclination [rad]
+    pub i0: f64,
+    /// Longitude of ascending node at weekly epoch [rad]
+    pub omega0: f64,
+    /// Rate of right ascension [rad/s]
+    pub omega_dot: f64,
+    /// Argument of perigee [rad]
+    pub w: f64,
+    /// Mean anomaly at reference Epoch [rad]
+    pub m0: f64,
+}
+
+impl Almanac {
+    /// Returns SV position at given Epoch, ECEF [m], using the Earth
+    /// constants and system time of the SV constellation
+    pub fn position(&self, t: Epoch) -> Coordinates {
+        let (gm, omega_e, timescale) = kepler_constants(self.sv.constellation);
+        let a = self.sqrt_a.powi(2);
+        let n = (gm / a.powi(3)).sqrt();
+        let tk = (t - self.toa).to_seconds();
+
+        let m = self.m0 + n * tk;
+        let mut e_k = m;
+        for _ in 0..10 {
+            e_k = m + self.e * e_k.sin();
+        }
+
+        let nu = ((1.0 - self.e.powi(2)).sqrt() * e_k.sin()).atan2(e_k.cos() - self.e);
+        let u = nu + self.w;
+        let r = a * (1.0 - self.e * e_k.cos());
+
+        let (_, toa_nanos) = self.toa.to_time_scale(timescale).to_time_of_week();
+        let toa_seconds = toa_nanos as f64 * 1.0E-9;
+        let omega = self.omega0 + (self.omega_dot - omega_e) * tk - omega_e * toa_seconds;
+
+        let (x, y) = (r * u.cos(), r * u.sin());
+        Coordinates {
+            x: x * omega.cos() - y * self.i0.cos() * omega.sin(),
+            y: x * omega.sin() + y * self.i0.cos() * omega.cos(),
+            z: y * self.i0.sin(),
+        }
+    }
+}
+
+/* nominal orbit radius [m], used to project observed directions */
+#[cfg(feature = "scheduler")]
+fn nominal_orbit_radius(constellation: Constellation) -> f64 {
+    match constellation {
+        Constellation::Glonass => 25_508_000.0,
+        Constellation::Galileo => 29_600_000.0,
+        Constellation::BeiDou => 27_906_000.0,
+        Constellation::QZSS | Constellation::IRNSS => 42_164_000.0,
+        c if c.is_sbas() => 42_164_000.0,
+        _ => 26_560_000.0,
+    }
+}
+
+/*
+ * Projects a direction observed at given site, onto the nominal orbit sphere.
+ */
+#[cfg(feature = "scheduler")]
+fn project(site: &Coordinates, azimuth: f64, elevation: f64, radius: f64) -> Coordinates {
+    let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+    // line of sight, scaled to preserve accuracy
+    let scale = 1.0E7;
+    let los = Coordinates::from_enu(
+        scale * elevation.cos() * azimuth.sin(),
+        scale * elevation.cos() * azimuth.cos(),
+        scale * elevation.sin(),
+        site,
+    ) - *site;
+    let unit = Coordinates {
+        x: los.x / scale,
+        y: los.y / scale,
+        z: los.z / scale,
+    };
+    // solve |site + k unit| = radius, k > 0
+    let b = site.x * unit.x + site.y * unit.y + site.z * unit.z;
+    let c = site.norm().powi(2) - radius.powi(2);
+    let k = -b + (b.powi(2) - c).sqrt();
+    Coordinates {
+        x: site.x + k * unit.x,
+        y: site.y + k * unit.y,
+        z: site.z + k * unit.z,
+    }
+}
+
+/// SV in common visibility from two stations, during a scheduled track
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommonVisibility {
+    /// Scheduled track start
+    pub epoch: Epoch,
+    /// SV above the elevation mask at both stations
+    pub sv: Vec<SV>,
+}
+
+/// Predicts the SV in common visibility from two stations, for every scheduled track
+/// within [start, end], using Keplerian almanacs. Visibility is evaluated
+/// at track midpoint, considering given elevation mask [°].
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+pub fn common_visibility(
+    local: &Coordinates,
+    remote: &Coordinates,
+    almanacs: &[Almanac],
+    elevation_mask: f64,
+    scheduler: &Scheduler,
+    start: Epoch,
+    end: Epoch,
+) -> Vec<CommonVisibility> {
+    let mut ret = Vec::<CommonVisibility>::new();
+    let mut t = scheduler.next_track_start(start);
+    while t <= end {
+        let midpoint = t + scheduler.trk_duration / 2;
+        let sv = almanacs
+            .iter()
+            .filter(|almanac| {
+                let position = almanac.position(midpoint);
+                let (_, local_elev) = local.azimuth_elevation(&position);
+                let (_, remote_elev) = remote.azimuth_elevation(&position);
+                local_elev >= elevation_mask && remote_elev >= elevation_mask
+            })
+            .map(|almanac| almanac.sv)
+            .collect();
+        ret.push(CommonVisibility { epoch: t, sv });
+        t = scheduler.next_track_start(t + Duration::from_seconds(1.0));
+    }
+    ret
+}
+
+impl CGGTTS {
+    /// Predicts the SV that a remote station would see in common with Self,
+    /// for every scheduled track covered by Self. Directions observed at the
+    /// local antenna are projected onto the nominal orbit of each constellation,
+    /// then evaluated from the remote antenna. Both stations must see the SV
+    /// above given elevation mask [°]. Tracks are assigned to the scheduled
+    /// slot they start in.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// use cggtts::track::Scheduler;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// // co-located station
+    /// let remote = cggtts.apc_coordinates;
+    /// let prediction = cggtts.predict_common_visibility(&remote, 15.0, &Scheduler::default());
+    /// assert!(prediction.iter().any(|cv| cv.sv.len() > 0));
+    /// ```
+    #[cfg(feature = "scheduler")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+    pub fn predict_common_visibility(
+        &self,
+        remote: &Coordinates,
+        elevation_mask: f64,
+        scheduler: &Scheduler,
+    ) -> Vec<CommonVisibility> {
+        let (first, last) = match (
+            self.tracks().map(|trk| trk.epoch).min(),
+            self.tracks().map(|trk| trk.epoch).max(),
+        ) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Vec::new(),
+        };
+        let local = self.apc_coordinates;
+        let sky = self.sky_tracks();
+
+        // scheduled slot a track started in
+        let slot = |t: Epoch| {
+            scheduler.next_track_start(t - scheduler.period + Duration::from_nanoseconds(1.0))
+        };
+
+        // every scheduled slot covered by Self
+        let mut slots = BTreeMap::<Epoch, Vec<SV>>::new();
+        let mut t = slot(first);
+        while t <= last {
+            slots.insert(t, Vec::new());
+            t = scheduler.next_track_start(t + scheduler.period);
+        }
+
+        for (satellite, points) in &sky {
+            let radius = nominal_orbit_radius(satellite.constellation);
+            for p in points.iter().filter(|p| p.elevation >= elevation_mask) {
+                let position = project(&local, p.azimuth, p.elevation, radius);
+                let (_, remote_elev) = remote.azimuth_elevation(&position);
+                if remote_elev < elevation_mask {
+                    continue;
+                }
+                let sv = slots.entry(slot(p.epoch)).or_default();
+                if !sv.contains(satellite) {
+                    sv.push(*satellite);
+                }
+            }
+        }
+
+        slots
+            .into_iter()
+            .map(|(epoch, sv)| CommonVisibility { epoch, sv })
+            .collect()
+    }
+    /// Returns the path of each SV in the local sky, in chronological order.
+    /// Tracks of the same SV and Epoch (several signals) only
+    /// contribute once.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// for (sv, points) in cggtts.sky_tracks() {
+    ///     for point in points {
+    ///         assert!(point.elevation >= 0.0 && point.elevation <= 90.0);
+    ///     }
+    /// }
+    /// ```
+    pub fn sky_tracks(&self) -> BTreeMap<SV, Vec<SkyPoint>> {
+        let mut ret = BTreeMap::<SV, Vec<SkyPoint>>::new();
+        for trk in self.tracks() {
+            let points = ret.entry(trk.sv).or_default();
+            if points.iter().any(|p| p.epoch == trk.epoch) {
+                continue;
+            }
+            points.push(SkyPoint {
+                epoch: trk.epoch,
+                elevation: trk.elevation,
+                azimuth: trk.azimuth,
+            });
+        }
+        for points in ret.values_mut() {
+            points.sort_by_key(|p| p.epoch);
+        }
+        ret
+    }
+    /// Returns the number of (SV, Epoch) tracks per elevation bin,
+    /// as a list of (lower bin edge [°], count), from the horizon to the zenith.
+    /// A lack of tracks in a given bin, or unbalanced low elevation
+    /// bins, usually reveals obstructions of the antenna site.
+    /// Returns an empty list when `bin_width` [°] is not strictly positive or not finite.
+    /// ```
+    /// use cggtts::prelude::CGGTTS;
+    /// let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258")
+    ///     .unwrap();
+    /// let histogram = cggtts.elevation_histogram(10.0);
+    /// assert_eq!(histogram.len(), 9);
+    /// assert_eq!(histogram[0].0, 0.0);
+    /// ```
+    pub fn elevation_histogram(&self, bin_width: f64) -> Vec<(f64, usize)> {
+        if !(bin_width.is_finite() && bin_width > 0.0) {
+            return Vec::new();
+        }
+        let nb_bins = (90.0 / bin_width).ceil() as usize;
+        let mut histogram: Vec<(f64, usize)> =
+            (0..nb_bins).map(|i| (i as f64 * bin_width, 0)).collect();
+        for points in self.sky_tracks().values() {
+            for point in points {
+                let bin =
+                    ((point.elevation / bin_width).floor().max(0.0) as usize).min(nb_bins - 1);
+                histogram[bin].1 += 1;
+            }
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Almanac;
+    use crate::prelude::{Epoch, TimeScale, CGGTTS, SV};
+    #[cfg(feature = "scheduler")]
+    use crate::{coordinates::Ellipsoid, Coordinates};
+    use std::str::FromStr;
+    #[test]
+    #[cfg(feature = "scheduler")]
+    fn projection() {
+        use super::project;
+        let site = Coordinates::from_geodetic(45.0, 7.5, 250.0, Ellipsoid::WGS84);
+        for (azimuth, elevation) in [(0.0, 90.0), (45.0, 30.0), (270.0, 5.0)] {
+            let sv = project(&site, azimuth, elevation, 26_560_000.0);
+            assert!((sv.norm() - 26_560_000.0).abs() < 1.0E-3);
+            let (azi, elev) = site.azimuth_elevation(&sv);
+            assert!((elev - elevation).abs() < 1.0E-6);
+            if elevation < 90.0 {
+                assert!((azi - azimuth).abs() < 1.0E-6);
+            }
+        }
+    }
+    #[test]
+    fn almanac() {
+        let toa = Epoch::from_gregorian(2023, 11, 7, 0, 0, 0, 0, TimeScale::GPST);
+        let almanac = Almanac {
+            sv: SV::from_str("G01").unwrap(),
+            toa,
+            sqrt_a: 5153.6,
+            e: 0.0,
+            i0: 55.0_f64.to_radians(),
+            omega0: 0.0,
+            omega_dot: 0.0,
+            w: 0.0,
+            m0: 0.0,
+        };
+        // circular orbit
+        for hours in 0..12 {
+            let t = toa + crate::prelude::Duration::from_hours(hours as f64);
+            let position = almanac.position(t);
+            assert!((position.norm() - 5153.6_f64.powi(2)).abs() < 1.0E-3);
+        }
+        // at the ascending node, on the equator
+        assert!(almanac.position(toa).z.abs() < 1.0E-3);
+    }
+    #[test]
+    fn sky_tracks() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        let sky = cggtts.sky_tracks();
+        assert_eq!(sky.len(), 31);
+        let nb_points: usize = sky.values().map(|points| points.len()).sum();
+        assert!(nb_points < cggtts.tracks.len(), "signals are merged");
+        for points in sky.values() {
+            for pair in points.windows(2) {
+                assert!(pair[0].epoch < pair[1].epoch);
+            }
+        }
+        let histogram = cggtts.elevation_histogram(5.0);
+        assert_eq!(histogram.len(), 18);
+        assert_eq!(histogram[17].0, 85.0);
+        assert_eq!(histogram.iter().map(|(_, n)| n).sum::<usize>(), nb_points);
+    }
+    #[test]
+    fn non_positive_bin_width() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        assert!(cggtts.elevation_histogram(0.0).is_empty());
+        assert!(cggtts.elevation_histogram(-10.0).is_empty());
+    }
+    #[test]
+    fn non_finite_bin_width() {
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        assert!(cggtts.elevation_histogram(f64::NAN).is_empty());
+        assert!(cggtts.elevation_histogram(f64::INFINITY).is_empty());
+        assert!(cggtts.elevation_histogram(f64::NEG_INFINITY).is_empty());
+    }
+    #[test]
+    #[cfg(feature = "scheduler")]
+    fn common_visibility() {
+        use super::common_visibility;
+        use crate::prelude::Duration;
+        use crate::track::Scheduler;
+        let cggtts = CGGTTS::from_file("../data/dual/GZGTR560.258").unwrap();
+        let scheduler = Scheduler::default();
+
+        let local = cggtts.apc_coordinates;
+        let colocated = cggtts.predict_common_visibility(&local, 0.0, &scheduler);
+        let nb_tracks: usize = colocated.iter().map(|cv| cv.sv.len()).sum();
+        let nb_points: usize = cggtts.sky_tracks().values().map(|p| p.len()).sum();
+        assert_eq!(nb_tracks, nb_points);
+
+        // afternoon tracks, the first one being on the schedule
+        let mut scheduled = cggtts.clone();
+        let (noon, evening) = (Epoch::from_mjd_utc(60258.5), Epoch::from_mjd_utc(60258.75));
+        scheduled
+            .tracks
+            .retain(|trk| trk.epoch > noon && trk.epoch < evening);
+        let first = scheduled.tracks().map(|trk| trk.epoch).min().unwrap();
+        let offset = scheduler.next_track_start(first) - first;
+        for trk in scheduled.tracks.iter_mut() {
+            trk.epoch += offset;
+        }
+        let first = first + offset;
+        let prediction = scheduled.predict_common_visibility(&local, 0.0, &scheduler);
+        // one slot per track epoch, no empty leading slot
+        assert_eq!(prediction.len(), 23);
+        assert_eq!(prediction[0].epoch, first);
+        assert!(prediction.iter().all(|cv| !cv.sv.is_empty()));
+
+        // same station, 700 km away: fewer SV in common
+        let (lat, lon, h) = local.to_geodetic(Ellipsoid::WGS84);
+        let remote = Coordinates::from_geodetic(lat + 1.0, lon - 10.0, h, Ellipsoid::WGS84);
+        let distant = cggtts.predict_common_visibility(&remote, 15.0, &scheduler);
+        assert_eq!(distant.len(), colocated.len());
+        let nb_common: usize = distant.iter().map(|cv| cv.sv.len()).sum();
+        assert!(nb_common > 0 && nb_common < nb_tracks);
+
+        // antipode
+        let antipode = Coordinates {
+            x: -local.x,
+            y: -local.y,
+            z: -local.z,
+        };
+        let prediction = cggtts.predict_common_visibility(&antipode, 15.0, &scheduler);
+        assert!(prediction.iter().all(|cv| cv.sv.is_empty()));
+
+        // from almanacs
+        let start = Epoch::from_mjd_utc(60258.0);
+        let almanacs: Vec<_> = (0..6)
+            .map(|plane| Almanac {
+                sv: SV::from_str(&format!("G{:02}", plane + 1)).unwrap(),
+                toa: start,
+                sqrt_a: 5153.6,
+                e: 0.0,
+                i0: 55.0_f64.to_radians(),
+                omega0: (plane as f64 * 60.0).to_radians(),
+                omega_dot: 0.0,
+                w: 0.0,
+                m0: (plane as f64 * 30.0).to_radians(),
+            })
+            .collect();
+        let prediction = common_visibility(
+            &local,
+            &remote,
+            &almanacs,
+            15.0,
+            &scheduler,
+            start,
+            start + Duration::from_days(1.0),
+        );
+        assert_eq!(prediction.len(), 90);
+        assert!(prediction.iter().any(|cv| !cv.sv.is_empty()));
+        let self_prediction = common_visibility(
+            &local,
+            &local,
+            &almanacs,
+            15.0,
+            &scheduler,
+            start,
+            start + Duration::from_days(1.0),
+        );
+        for (a, b) in prediction.iter().zip(self_prediction.iter()) {
+            assert!(a.sv.len() <= b.sv.len());
+        }
+    }
+}